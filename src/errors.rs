@@ -72,6 +72,10 @@ pub enum TransactionError {
     SignMsgError(String),
     #[error("Generic Error: {0}")]
     Generic(String),
+    #[error("Transaction too large: {0} bytes, {1} accounts")]
+    Oversize(u64, u64),
+    #[error("Sponsorship rejected: {0}")]
+    Sponsorship(String),
 }
 
 /// Some methods to quickly create an error from a given generic error
@@ -115,6 +119,14 @@ impl TransactionError {
     pub fn generic_error<E: Error>(error: E) -> Self {
         Self::Generic(format!("{error:?}"))
     }
+
+    pub fn oversize(size: u64, account_count: u64) -> Self {
+        Self::Oversize(size, account_count)
+    }
+
+    pub fn sponsorship(reason: impl AsRef<str>) -> Self {
+        Self::Sponsorship(reason.as_ref().to_string())
+    }
 }
 
 // Small error wrapper to go from box errors to a string formatted
@@ -122,6 +122,19 @@ pub fn token_transaction(
         .token_transaction(destination, owner, token, kind, parameters)
 }
 
+/// Pays many recipients at once and returns the transactions in the order they
+/// have to be sent
+pub fn batch_transaction(
+    chain: Blockchain,
+    sender: ChainPublicKey,
+    transfers: Vec<BatchTransfer>,
+    parameters: TransactionParameters,
+) -> Result<Vec<String>, TransactionError> {
+    chain
+        .tx_factory()
+        .batch_transaction(sender, transfers, parameters)
+}
+
 pub fn sign_transaction(
     chain: Blockchain,
     transaction: String,
@@ -133,6 +146,36 @@ pub fn sign_transaction(
         .sign_transaction(transaction, signers, parameters)
 }
 
+/// Inspect the transaction before `owner` signs it and return warnings about
+/// risky instructions
+pub fn analyze_transaction(
+    chain: Blockchain,
+    transaction: String,
+    owner: ChainPublicKey,
+    allowed_programs: Option<Vec<String>>,
+    trusted_delegates: Vec<String>,
+    address_lookup_tables: Vec<AddressLookupTableData>,
+) -> Result<Vec<TransactionWarning>, TransactionError> {
+    chain.tx_factory().analyze_transaction(
+        transaction,
+        owner,
+        allowed_programs,
+        trusted_delegates,
+        address_lookup_tables,
+    )
+}
+
+pub fn estimate_balance_changes(
+    chain: Blockchain,
+    transaction: String,
+    accounts: Vec<TokenAccountSnapshot>,
+    address_lookup_tables: Vec<AddressLookupTableData>,
+) -> Result<Vec<BalanceChange>, TransactionError> {
+    chain
+        .tx_factory()
+        .estimate_balance_changes(transaction, accounts, address_lookup_tables)
+}
+
 pub fn sign_message(
     chain: Blockchain,
     message: String,
@@ -166,8 +209,11 @@ pub fn modify_transaction(
 pub fn parse_transaction(
     chain: Blockchain,
     transaction: String,
+    address_lookup_tables: Vec<AddressLookupTableData>,
 ) -> Result<ChainTransaction, TransactionError> {
-    chain.tx_factory().parse_transaction(transaction)
+    chain
+        .tx_factory()
+        .parse_transaction(transaction, address_lookup_tables)
 }
 
 pub fn get_associated_token_address(
@@ -193,6 +239,146 @@ pub fn append_signature_to_transaction(signer: String, signature: String, transa
     solana::Factory.append_signature_to_transaction(signer, signature, transaction)
 }
 
+pub fn estimate_transaction_size(transaction: String) -> Result<TransactionSize, TransactionError> {
+    solana::Factory.estimate_transaction_size(transaction)
+}
+
+pub fn calculate_transaction_fee(transaction: String) -> Result<TransactionFee, TransactionError> {
+    solana::Factory.calculate_transaction_fee(transaction)
+}
+
+pub fn compute_unit_price_for_priority_fee(
+    priority_fee: u64,
+    compute_unit_limit: u32,
+) -> Result<u64, TransactionError> {
+    solana::Factory.compute_unit_price_for_priority_fee(priority_fee, compute_unit_limit)
+}
+
+pub fn nonce_account_transaction(
+    action: NonceAccountAction,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.nonce_account_transaction(action, parameters)
+}
+
+pub fn parse_nonce_account(data: String) -> Result<NonceAccount, TransactionError> {
+    solana::Factory.parse_nonce_account(data)
+}
+
+pub fn stake_account_transaction(
+    action: StakeAccountAction,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.stake_account_transaction(action, parameters)
+}
+
+pub fn get_stake_account_address_with_seed(
+    base: String,
+    seed: String,
+) -> Result<ChainPublicKey, TransactionError> {
+    solana::Factory.get_stake_account_address_with_seed(base, seed)
+}
+
+pub fn parse_stake_account(data: String) -> Result<StakeAccount, TransactionError> {
+    solana::Factory.parse_stake_account(data)
+}
+
+pub fn stake_pool_transaction(
+    stake_pool: StakePoolAccount,
+    action: StakePoolAction,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.stake_pool_transaction(stake_pool, action, parameters)
+}
+
+pub fn parse_stake_pool(stake_pool: StakePoolAccount) -> Result<StakePoolState, TransactionError> {
+    solana::Factory.parse_stake_pool(stake_pool)
+}
+
+/// Checks that a Jupiter swap transaction does what its quote promised
+pub fn validate_jupiter_swap(
+    transaction: String,
+    quote: String,
+    user: ChainPublicKey,
+    fee_account: Option<String>,
+    address_lookup_tables: Vec<AddressLookupTableData>,
+) -> Result<Vec<JupiterSwapMismatch>, TransactionError> {
+    solana::Factory.validate_jupiter_swap(transaction, quote, user, fee_account, address_lookup_tables)
+}
+
+/// Builds a Jupiter swap from a quote, without asking the swap API for the transaction
+pub fn jupiter_swap_transaction(
+    quote: String,
+    user: ChainPublicKey,
+    legs: Vec<JupiterSwapLeg>,
+    options: JupiterSwapOptions,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.jupiter_swap_transaction(quote, user, legs, options, parameters)
+}
+
+pub fn parse_jupiter_swap_events(
+    transaction: String,
+    meta: String,
+) -> Result<Option<JupiterSwapFill>, TransactionError> {
+    solana::Factory.parse_jupiter_swap_events(transaction, meta)
+}
+
+pub fn jupiter_order_transaction(
+    action: JupiterOrderAction,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.jupiter_order_transaction(action, parameters)
+}
+
+pub fn parse_jupiter_order(account: JupiterOrderAccount) -> Result<JupiterOrder, TransactionError> {
+    solana::Factory.parse_jupiter_order(account)
+}
+
+pub fn get_jupiter_referral_account(
+    project: Option<String>,
+    name: String,
+) -> Result<ChainPublicKey, TransactionError> {
+    solana::Factory.get_jupiter_referral_account(project, name)
+}
+
+pub fn get_jupiter_referral_token_account(
+    referral_account: String,
+    mint: String,
+) -> Result<ChainPublicKey, TransactionError> {
+    solana::Factory.get_jupiter_referral_token_account(referral_account, mint)
+}
+
+pub fn jupiter_referral_transaction(
+    action: JupiterReferralAction,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.jupiter_referral_transaction(action, parameters)
+}
+
+pub fn sign_sponsored_transaction(
+    transaction: String,
+    sponsor: ChainPrivateKey,
+    max_fee: u64,
+    allow_rent: bool,
+    address_lookup_tables: Vec<AddressLookupTableData>,
+) -> Result<ChainTransaction, TransactionError> {
+    solana::Factory.sign_sponsored_transaction(
+        transaction,
+        sponsor,
+        max_fee,
+        allow_rent,
+        address_lookup_tables,
+    )
+}
+
+pub fn token_account_transaction(
+    actions: Vec<TokenAccountAction>,
+    parameters: TransactionParameters,
+) -> Result<String, TransactionError> {
+    solana::Factory.token_account_transaction(actions, parameters)
+}
+
 impl Blockchain {
     fn all() -> Vec<Blockchain> {
         vec![Blockchain::Solana]
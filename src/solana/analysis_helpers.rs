@@ -0,0 +1,84 @@
+use super::types::{SolanaInstruction, TransactionWarning};
+
+/// Inspects the decoded `instructions` of a transaction that `owner` is asked to sign.
+/// `programs` are the program ids of the instructions, in the same order.
+pub(crate) fn analyze_instructions(
+    instructions: &[SolanaInstruction],
+    programs: &[String],
+    owner: &str,
+    allowed_programs: Option<&[String]>,
+    trusted_delegates: &[String],
+) -> Vec<TransactionWarning> {
+    let mut warnings = Vec::new();
+
+    for instruction in instructions.iter() {
+        match instruction {
+            SolanaInstruction::TokenSetAuthority {
+                account,
+                current_authority,
+                authority_type,
+                new_authority,
+                ..
+            } if current_authority == owner => warnings.push(TransactionWarning::SetAuthority {
+                account: account.clone(),
+                authority_type: authority_type.clone(),
+                new_authority: new_authority.clone(),
+            }),
+            SolanaInstruction::TokenApprove {
+                source,
+                delegate,
+                owner: approver,
+                amount,
+                ..
+            } if approver == owner && !trusted_delegates.contains(delegate) => {
+                warnings.push(TransactionWarning::Approve {
+                    source: source.clone(),
+                    delegate: delegate.clone(),
+                    amount: *amount,
+                })
+            }
+            SolanaInstruction::TokenCloseAccount {
+                account,
+                destination,
+                owner: closer,
+                ..
+            } if closer == owner && destination != owner => {
+                warnings.push(TransactionWarning::CloseAccountToForeignWallet {
+                    account: account.clone(),
+                    destination: destination.clone(),
+                })
+            }
+            SolanaInstruction::SystemAssign {
+                account,
+                owner: new_owner,
+            } if account == owner => warnings.push(TransactionWarning::AssignOwner {
+                account: account.clone(),
+                new_owner: new_owner.clone(),
+            }),
+            SolanaInstruction::SystemAdvanceNonceAccount { nonce_account, .. } => {
+                warnings.push(TransactionWarning::DurableNonce {
+                    nonce_account: nonce_account.clone(),
+                })
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(allowed_programs) = allowed_programs {
+        let mut unknown_programs: Vec<&String> = Vec::new();
+        for program in programs.iter() {
+            if !allowed_programs.contains(program) && !unknown_programs.contains(&program) {
+                unknown_programs.push(program);
+            }
+        }
+        warnings.extend(
+            unknown_programs
+                .into_iter()
+                .map(|program| TransactionWarning::UnknownProgram {
+                    program: program.clone(),
+                }),
+        );
+    }
+
+    warnings
+}
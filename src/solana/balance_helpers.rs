@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use solana_sdk::{instruction::CompiledInstruction, message::VersionedMessage, pubkey::Pubkey};
+
+use super::fee_helpers::transaction_fee;
+use super::jupiter_helpers::{route_accounts, JUPITER_V6_PROGRAM_ID};
+use super::types::{BalanceChange, SolanaInstruction, TokenAccountSnapshot};
+use super::TOKEN_2022_PROGRAM;
+use crate::errors::*;
+
+/// Rent exemption of a 165 byte SPL token account
+pub(crate) const TOKEN_ACCOUNT_RENT: u64 = 2_039_280;
+/// Rent exemption of a Token-2022 associated token account, which carries
+/// the `ImmutableOwner` extension (170 bytes)
+const TOKEN_2022_ACCOUNT_RENT: u64 = 2_074_080;
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Estimates how the balances of every involved wallet change when the message
+/// is executed, without simulating it. `account_keys` are the keys the account
+/// indices of the instructions refer to and `instructions` the decoded instructions.
+pub(crate) fn estimate_balance_changes(
+    message: &VersionedMessage,
+    account_keys: &[Pubkey],
+    instructions: &[SolanaInstruction],
+    snapshots: &[TokenAccountSnapshot],
+) -> Result<Vec<BalanceChange>, TransactionError> {
+    let mut estimator = Estimator::new(snapshots);
+    estimator.learn_token_accounts(instructions);
+
+    if let Some(fee_payer) = account_keys.first() {
+        let fee = transaction_fee(message, instructions)?.total_fee;
+        estimator.add(&fee_payer.to_string(), None, -i128::from(fee), false);
+    }
+
+    for (compiled_instruction, instruction) in message.instructions().iter().zip(instructions) {
+        estimator.apply(compiled_instruction, account_keys, instruction);
+    }
+
+    Ok(estimator.into_changes())
+}
+
+struct TokenAccount {
+    owner: String,
+    mint: String,
+}
+
+struct Estimator {
+    token_accounts: HashMap<String, TokenAccount>,
+    /// Known lamports of token accounts, needed to know what a close returns
+    lamports: HashMap<String, i128>,
+    /// Accounts that existed before the transaction
+    existing_accounts: Vec<String>,
+    changes: Vec<(String, Option<String>, i128, bool)>,
+}
+
+impl Estimator {
+    fn new(snapshots: &[TokenAccountSnapshot]) -> Self {
+        let mut token_accounts = HashMap::new();
+        let mut lamports = HashMap::new();
+        for snapshot in snapshots.iter() {
+            token_accounts.insert(
+                snapshot.address.clone(),
+                TokenAccount {
+                    owner: snapshot.owner.clone(),
+                    mint: snapshot.mint.clone(),
+                },
+            );
+            if let Some(account_lamports) = snapshot.lamports {
+                lamports.insert(snapshot.address.clone(), i128::from(account_lamports));
+            }
+        }
+        Self {
+            token_accounts,
+            lamports,
+            existing_accounts: snapshots.iter().map(|s| s.address.clone()).collect(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Token accounts created or used with a mint in the transaction itself
+    fn learn_token_accounts(&mut self, instructions: &[SolanaInstruction]) {
+        for instruction in instructions.iter() {
+            match instruction {
+                SolanaInstruction::AssociatedTokenAccountCreate {
+                    account,
+                    wallet,
+                    mint,
+                    ..
+                } => {
+                    self.token_accounts
+                        .entry(account.clone())
+                        .or_insert_with(|| TokenAccount {
+                            owner: wallet.clone(),
+                            mint: mint.clone(),
+                        });
+                }
+                SolanaInstruction::TokenTransferChecked {
+                    source,
+                    mint,
+                    authority,
+                    ..
+                }
+                | SolanaInstruction::TokenTransferCheckedWithFee {
+                    source,
+                    mint,
+                    authority,
+                    ..
+                } => {
+                    self.token_accounts
+                        .entry(source.clone())
+                        .or_insert_with(|| TokenAccount {
+                            owner: authority.clone(),
+                            mint: mint.clone(),
+                        });
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn apply(
+        &mut self,
+        compiled_instruction: &CompiledInstruction,
+        account_keys: &[Pubkey],
+        instruction: &SolanaInstruction,
+    ) {
+        match instruction {
+            SolanaInstruction::SystemTransfer { from, to, lamports } => {
+                let lamports = i128::from(*lamports);
+                self.add(from, None, -lamports, false);
+                match self.token_accounts.get(to) {
+                    // Wrapping SOL
+                    Some(account) => {
+                        if account.mint == native_mint() {
+                            let owner = account.owner.clone();
+                            self.add(&owner, Some(native_mint()), lamports, false);
+                        }
+                        *self.lamports.entry(to.clone()).or_default() += lamports;
+                    }
+                    None => self.add(to, None, lamports, false),
+                }
+            }
+            SolanaInstruction::SystemCreateAccount {
+                from,
+                new_account,
+                lamports,
+                ..
+            } => {
+                self.add(from, None, -i128::from(*lamports), false);
+                self.lamports
+                    .insert(new_account.clone(), i128::from(*lamports));
+            }
+            SolanaInstruction::AssociatedTokenAccountCreate {
+                payer,
+                account,
+                token_program,
+                idempotent,
+                ..
+            } => {
+                if *idempotent && self.existing_accounts.contains(account) {
+                    return;
+                }
+                let rent = i128::from(token_account_rent(token_program));
+                self.add(payer, None, -rent, false);
+                self.lamports.insert(account.clone(), rent);
+            }
+            SolanaInstruction::TokenTransfer {
+                source,
+                destination,
+                authority,
+                amount,
+                ..
+            } => {
+                let mint = self
+                    .token_accounts
+                    .get(source)
+                    .or_else(|| self.token_accounts.get(destination))
+                    .map(|account| account.mint.clone());
+                if let Some(mint) = mint {
+                    self.transfer_tokens(
+                        source,
+                        destination,
+                        &mint,
+                        authority,
+                        *amount,
+                        *amount,
+                        false,
+                    );
+                }
+            }
+            SolanaInstruction::TokenTransferChecked {
+                source,
+                mint,
+                destination,
+                authority,
+                amount,
+                ..
+            } => self.transfer_tokens(
+                source,
+                destination,
+                mint,
+                authority,
+                *amount,
+                *amount,
+                false,
+            ),
+            SolanaInstruction::TokenTransferCheckedWithFee {
+                source,
+                mint,
+                destination,
+                authority,
+                amount,
+                fee,
+                ..
+            } => self.transfer_tokens(
+                source,
+                destination,
+                mint,
+                authority,
+                *amount,
+                amount.saturating_sub(*fee),
+                false,
+            ),
+            SolanaInstruction::TokenCloseAccount {
+                program,
+                account,
+                destination,
+                owner,
+            } => {
+                let lamports = match self.lamports.get(account) {
+                    Some(lamports) => *lamports,
+                    // Every SPL token account has the same size, Token-2022 accounts don't
+                    None if *program == spl_token::id().to_string() => {
+                        i128::from(TOKEN_ACCOUNT_RENT)
+                    }
+                    None => return,
+                };
+                self.add(destination, None, lamports, false);
+
+                // Unwrapping SOL, everything above the rent was the token balance
+                let is_native = self
+                    .token_accounts
+                    .get(account)
+                    .is_some_and(|token_account| token_account.mint == native_mint());
+                if is_native {
+                    let wrapped = lamports - i128::from(token_account_rent(program));
+                    let owner = self.token_owner(account, owner);
+                    self.add(&owner, Some(native_mint()), -wrapped, false);
+                }
+                self.lamports.insert(account.clone(), 0);
+            }
+            SolanaInstruction::JupiterRoute {
+                name,
+                in_amount,
+                quoted_in_amount,
+                out_amount,
+                quoted_out_amount,
+                slippage_bps,
+                ..
+            } => {
+                let Some(positions) = route_accounts(name) else {
+                    return;
+                };
+                let key = |position: usize| -> Option<String> {
+                    let index = compiled_instruction.accounts.get(position)?;
+                    account_keys.get(usize::from(*index)).map(|k| k.to_string())
+                };
+                let (Some(authority), Some(source), Some(destination), Some(destination_mint)) = (
+                    key(positions.user_transfer_authority),
+                    key(positions.source_token_account),
+                    key(positions.destination_token_account),
+                    key(positions.destination_mint),
+                ) else {
+                    return;
+                };
+                let destination = positions
+                    .optional_destination_token_account
+                    .and_then(key)
+                    .filter(|account| *account != JUPITER_V6_PROGRAM_ID.to_string())
+                    .unwrap_or(destination);
+                let source_mint = self
+                    .token_accounts
+                    .get(&source)
+                    .map(|account| account.mint.clone())
+                    .or_else(|| positions.source_mint.and_then(key));
+
+                let slippage_bps = u128::from(*slippage_bps);
+                // Exact in routes spend exactly `in_amount` and receive at least the quote
+                // minus slippage, exact out routes the other way around.
+                let (sent, sent_is_estimate, received, received_is_estimate) =
+                    match (in_amount, quoted_out_amount, out_amount, quoted_in_amount) {
+                        (in_amount, Some(quoted_out_amount), _, _) => (
+                            *in_amount,
+                            false,
+                            Some(
+                                (u128::from(*quoted_out_amount)
+                                    * (BPS_DENOMINATOR.saturating_sub(slippage_bps))
+                                    / BPS_DENOMINATOR) as u64,
+                            ),
+                            true,
+                        ),
+                        (_, None, Some(out_amount), Some(quoted_in_amount)) => (
+                            Some(
+                                (u128::from(*quoted_in_amount) * (BPS_DENOMINATOR + slippage_bps))
+                                    .div_ceil(BPS_DENOMINATOR)
+                                    as u64,
+                            ),
+                            true,
+                            Some(*out_amount),
+                            false,
+                        ),
+                        _ => return,
+                    };
+
+                if let (Some(sent), Some(source_mint)) = (sent, source_mint) {
+                    let owner = self.token_owner(&source, &authority);
+                    self.add(
+                        &owner,
+                        Some(source_mint.clone()),
+                        -i128::from(sent),
+                        sent_is_estimate,
+                    );
+                    self.move_native_lamports(&source_mint, &source, -i128::from(sent));
+                }
+                if let Some(received) = received {
+                    let owner = self.token_owner(&destination, &authority);
+                    self.add(
+                        &owner,
+                        Some(destination_mint.clone()),
+                        i128::from(received),
+                        received_is_estimate,
+                    );
+                    self.move_native_lamports(
+                        &destination_mint,
+                        &destination,
+                        i128::from(received),
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transfer_tokens(
+        &mut self,
+        source: &str,
+        destination: &str,
+        mint: &str,
+        authority: &str,
+        sent: u64,
+        received: u64,
+        is_estimate: bool,
+    ) {
+        let source_owner = self.token_owner(source, authority);
+        let destination_owner = self.token_owner(destination, destination);
+        self.add(
+            &source_owner,
+            Some(mint.to_string()),
+            -i128::from(sent),
+            is_estimate,
+        );
+        self.add(
+            &destination_owner,
+            Some(mint.to_string()),
+            i128::from(received),
+            is_estimate,
+        );
+        self.move_native_lamports(mint, source, -i128::from(sent));
+        self.move_native_lamports(mint, destination, i128::from(received));
+    }
+
+    /// Wrapped SOL token balances are backed by the lamports of the account
+    fn move_native_lamports(&mut self, mint: &str, account: &str, amount: i128) {
+        if mint == native_mint() {
+            if let Some(lamports) = self.lamports.get_mut(account) {
+                *lamports += amount;
+            }
+        }
+    }
+
+    fn token_owner(&self, account: &str, fallback: &str) -> String {
+        self.token_accounts
+            .get(account)
+            .map(|account| account.owner.clone())
+            .unwrap_or_else(|| fallback.to_string())
+    }
+
+    fn add(&mut self, owner: &str, mint: Option<String>, amount: i128, is_estimate: bool) {
+        match self
+            .changes
+            .iter_mut()
+            .find(|(o, m, _, _)| o == owner && *m == mint)
+        {
+            Some(change) => {
+                change.2 += amount;
+                change.3 |= is_estimate;
+            }
+            None => self
+                .changes
+                .push((owner.to_string(), mint, amount, is_estimate)),
+        }
+    }
+
+    fn into_changes(self) -> Vec<BalanceChange> {
+        self.changes
+            .into_iter()
+            .filter(|(_, _, amount, _)| *amount != 0)
+            .map(|(owner, mint, amount, is_estimate)| BalanceChange {
+                owner,
+                mint,
+                amount: amount.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64,
+                is_estimate,
+            })
+            .collect()
+    }
+}
+
+fn token_account_rent(token_program: &str) -> u64 {
+    if token_program == TOKEN_2022_PROGRAM {
+        TOKEN_2022_ACCOUNT_RENT
+    } else {
+        TOKEN_ACCOUNT_RENT
+    }
+}
+
+fn native_mint() -> String {
+    spl_token::native_mint::id().to_string()
+}
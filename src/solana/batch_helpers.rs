@@ -0,0 +1,409 @@
+//! Packs many transfers of one sender into as few transactions as possible
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_instruction,
+    transaction::VersionedTransaction,
+};
+use spl_memo::build_memo;
+
+use super::fee_helpers::MAX_COMPUTE_UNIT_LIMIT;
+use super::token_2022_helpers::apply_transfer_extensions;
+use super::{
+    associated_token_address_2022, build_versioned_transaction, check_transaction_size,
+    compute_budget_instructions, create_associated_token_account_idempotent_2022, sol_to_lamports,
+    transaction_size, transfer_2022,
+};
+use crate::errors::*;
+use crate::types::{BatchTransfer, TransactionParameters};
+
+// Upper bounds of what the instructions of a payout consume
+const SOL_TRANSFER_COMPUTE_UNITS: u32 = 300;
+const TOKEN_TRANSFER_COMPUTE_UNITS: u32 = 10_000;
+const CREATE_TOKEN_ACCOUNT_COMPUTE_UNITS: u32 = 35_000;
+const MEMO_COMPUTE_UNITS: u32 = 15_000;
+
+/// Instructions paying a single recipient
+struct Payout {
+    instructions: Vec<Instruction>,
+    compute_units: u32,
+}
+
+/// Splits `transfers` into transactions that stay below the packet size and the
+/// compute unit limit of `parameters`. Token accounts of recipients are created by
+/// the first transaction paying them, so the transactions have to land in order.
+pub(crate) fn batch_transfer_transactions(
+    sender: &Pubkey,
+    transfers: &[BatchTransfer],
+    parameters: &TransactionParameters,
+) -> Result<Vec<VersionedTransaction>, TransactionError> {
+    if transfers.is_empty() {
+        return Err(TransactionError::parameters("No transfers given"));
+    }
+    if let Some(token_extensions) = parameters.token_extensions() {
+        // The extensions describe a single mint and destination account
+        let mints: HashSet<&str> = transfers
+            .iter()
+            .filter_map(|transfer| Some(transfer.mint.as_ref()?.contents.as_str()))
+            .collect();
+        if mints.len() > 1 {
+            return Err(TransactionError::parameters(
+                "Token extensions only describe the mint of batches transferring a single token",
+            ));
+        }
+        if token_extensions.destination_data.is_some() {
+            return Err(TransactionError::parameters(
+                "Token extensions of batches can't describe the accounts of every recipient",
+            ));
+        }
+    }
+
+    let mut header = compute_budget_instructions(parameters);
+    let mut header_compute_units = 0;
+    if let Some(memo) = parameters.memo() {
+        header.push(build_memo(memo.as_bytes(), &[sender]));
+        header_compute_units += MEMO_COMPUTE_UNITS;
+    }
+    let compute_unit_limit = parameters
+        .compute_budget_unit_limit()
+        .unwrap_or(MAX_COMPUTE_UNIT_LIMIT);
+
+    let references = parameters
+        .references()
+        .iter()
+        .map(|reference| Pubkey::from_str(reference).map_err(TransactionError::public_key))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut created_accounts = HashSet::new();
+    let mut transactions = Vec::new();
+    let mut instructions = header.clone();
+    let mut compute_units = header_compute_units;
+
+    for transfer in transfers {
+        let payout = payout(
+            sender,
+            transfer,
+            &references,
+            parameters,
+            &mut created_accounts,
+        )?;
+
+        let mut candidate = instructions.clone();
+        candidate.extend(payout.instructions.iter().cloned());
+        if compute_units + payout.compute_units <= compute_unit_limit {
+            let transaction = build_versioned_transaction(&candidate, sender, parameters)?;
+            if transaction_size(&transaction.message)?.fits() {
+                instructions = candidate;
+                compute_units += payout.compute_units;
+                continue;
+            }
+        }
+
+        if instructions.len() > header.len() {
+            transactions.push(build_versioned_transaction(
+                &instructions,
+                sender,
+                parameters,
+            )?);
+            instructions = header.clone();
+            compute_units = header_compute_units;
+        }
+
+        // The payout has to fit into a transaction of its own
+        if compute_units + payout.compute_units > compute_unit_limit {
+            return Err(TransactionError::parameters(format!(
+                "The transfer to {} exceeds the compute unit limit",
+                transfer.recipient.contents
+            )));
+        }
+        instructions.extend(payout.instructions);
+        check_transaction_size(&build_versioned_transaction(
+            &instructions,
+            sender,
+            parameters,
+        )?)?;
+        compute_units += payout.compute_units;
+    }
+    transactions.push(build_versioned_transaction(
+        &instructions,
+        sender,
+        parameters,
+    )?);
+
+    // Every transaction would advance the same nonce, only the first one could land
+    if transactions.len() > 1 && parameters.durable_nonce().is_some() {
+        return Err(TransactionError::parameters(
+            "The transfers need several transactions, which can't share a durable nonce",
+        ));
+    }
+
+    Ok(transactions)
+}
+
+fn payout(
+    sender: &Pubkey,
+    transfer: &BatchTransfer,
+    references: &[Pubkey],
+    parameters: &TransactionParameters,
+    created_accounts: &mut HashSet<Pubkey>,
+) -> Result<Payout, TransactionError> {
+    let recipient = transfer.recipient.to_solana_pubkey()?;
+
+    let Some(mint) = &transfer.mint else {
+        let mut instruction =
+            system_instruction::transfer(sender, &recipient, sol_to_lamports(&transfer.amount)?);
+        for reference in references {
+            instruction
+                .accounts
+                .push(AccountMeta::new_readonly(*reference, false));
+        }
+        return Ok(Payout {
+            instructions: vec![instruction],
+            compute_units: SOL_TRANSFER_COMPUTE_UNITS,
+        });
+    };
+
+    let mint = mint.to_solana_pubkey()?;
+    let owner_program = match transfer
+        .owner_program
+        .clone()
+        .or(parameters.owner_program())
+    {
+        Some(program) => Pubkey::from_str(&program).map_err(TransactionError::public_key)?,
+        None => spl_token::id(),
+    };
+    let decimals = transfer.decimals.or(parameters.decimals()).unwrap_or(0);
+    let source = associated_token_address_2022(sender, &owner_program, &mint);
+    let destination = associated_token_address_2022(&recipient, &owner_program, &mint);
+
+    let mut instructions = Vec::new();
+    let mut compute_units = TOKEN_TRANSFER_COMPUTE_UNITS;
+    if !transfer.account_exists && created_accounts.insert(destination) {
+        let funder = parameters.fee_payer()?.unwrap_or(*sender);
+        instructions.push(create_associated_token_account_idempotent_2022(
+            &funder,
+            &recipient,
+            &mint,
+            owner_program,
+        ));
+        compute_units += CREATE_TOKEN_ACCOUNT_COMPUTE_UNITS;
+    }
+    let amount = transfer.amount.to_u64()?;
+    let mut transfer_instruction = transfer_2022(
+        &owner_program,
+        &source,
+        &destination,
+        sender,
+        &[],
+        references,
+        amount,
+        decimals,
+        &mint,
+    )?;
+    if let Some(token_extensions) = parameters.token_extensions() {
+        // Recipients requiring memos were rejected along with `destination_data`
+        apply_transfer_extensions(
+            &mut transfer_instruction,
+            amount,
+            decimals,
+            &token_extensions,
+        )?;
+    }
+    instructions.push(transfer_instruction);
+
+    Ok(Payout {
+        instructions,
+        compute_units,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::types::TokenExtensionAccounts;
+    use crate::solana::TOKEN_2022_PROGRAM;
+    use crate::types::{Blockchain, ChainPublicKey, DecimalNumber, SolanaTransactionType};
+    use crate::utils::to_base64;
+
+    #[test]
+    fn test_batch_transfer_transactions() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let transfer = |mint: Option<Pubkey>, amount: &str| BatchTransfer {
+            recipient: ChainPublicKey::new(Pubkey::new_unique().to_string(), Blockchain::Solana),
+            amount: DecimalNumber {
+                value: amount.to_string(),
+            },
+            mint: mint.map(|mint| ChainPublicKey::new(mint.to_string(), Blockchain::Solana)),
+            owner_program: None,
+            decimals: Some(6),
+            account_exists: false,
+        };
+        let mut transfers: Vec<_> = (0..40).map(|_| transfer(None, "0.5")).collect();
+        transfers.extend((0..20).map(|_| transfer(Some(mint), "1000000")));
+
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Legacy,
+            owner_program: None,
+            decimals: None,
+            memo: Some("payroll".to_string()),
+            references: vec![Pubkey::new_unique().to_string()],
+            swap_slippage_bps: None,
+            compute_budget_unit_price: Some(1_000),
+            compute_budget_unit_limit: Some(200_000),
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let transactions = batch_transfer_transactions(&sender, &transfers, &parameters).unwrap();
+        assert!(transactions.len() > 2);
+
+        let mut transfer_count = 0;
+        for transaction in &transactions {
+            assert!(transaction_size(&transaction.message).unwrap().fits());
+            let message = &transaction.message;
+            assert_eq!(message.static_account_keys()[0], sender);
+            assert_eq!(message.header().num_required_signatures, 1);
+
+            let instructions = message.instructions();
+            let program = |index: usize| {
+                message.static_account_keys()[instructions[index].program_id_index as usize]
+            };
+            assert_eq!(program(2), spl_memo::id());
+            let created_accounts = (3..instructions.len())
+                .filter(|index| program(*index) == spl_associated_token_account::id())
+                .count();
+            // The compute budget fits 4 token transfers creating an account
+            assert!(created_accounts <= 4);
+            transfer_count += instructions.len() - 3 - created_accounts;
+        }
+        assert_eq!(transfer_count, transfers.len());
+
+        // A fee payer funds the token accounts of the recipients as well
+        let fee_payer = Pubkey::new_unique();
+        let sponsored_parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Legacy,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: Some(fee_payer.to_string()),
+            token_extensions: None,
+        };
+        let transactions =
+            batch_transfer_transactions(&sender, &transfers[40..41], &sponsored_parameters)
+                .unwrap();
+        let message = &transactions[0].message;
+        assert_eq!(message.static_account_keys()[0], fee_payer);
+        let create = &message.instructions()[0];
+        assert_eq!(
+            message.static_account_keys()[create.program_id_index as usize],
+            spl_associated_token_account::id()
+        );
+        assert_eq!(
+            message.static_account_keys()[create.accounts[0] as usize],
+            fee_payer
+        );
+
+        let Err(TransactionError::Parameters(_)) =
+            batch_transfer_transactions(&sender, &[], &parameters)
+        else {
+            panic!("An empty batch should fail");
+        };
+    }
+
+    #[test]
+    fn test_batch_transfer_with_token_extensions() {
+        let sender = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let transfer = |mint: &Pubkey| BatchTransfer {
+            recipient: ChainPublicKey::new(Pubkey::new_unique().to_string(), Blockchain::Solana),
+            amount: DecimalNumber {
+                value: "1000000".to_string(),
+            },
+            mint: Some(ChainPublicKey::new(mint.to_string(), Blockchain::Solana)),
+            owner_program: Some(TOKEN_2022_PROGRAM.to_string()),
+            decimals: Some(6),
+            account_exists: true,
+        };
+
+        // A mint with a transfer fee of 1% capped at 5_000
+        let mut mint_data = vec![0; 165];
+        mint_data.extend_from_slice(&[1, 1, 0, 108, 0]);
+        mint_data.extend_from_slice(&[0; 72]);
+        for _ in 0..2 {
+            mint_data.extend_from_slice(&0u64.to_le_bytes());
+            mint_data.extend_from_slice(&5_000u64.to_le_bytes());
+            mint_data.extend_from_slice(&100u16.to_le_bytes());
+        }
+        let mut token_extensions = TokenExtensionAccounts {
+            mint_data: to_base64(mint_data),
+            destination_data: None,
+            extra_account_metas_data: None,
+            epoch: 0,
+        };
+        let parameters =
+            |token_extensions: &TokenExtensionAccounts| TransactionParameters::Solana {
+                external_address: None,
+                transaction_type: SolanaTransactionType::Legacy,
+                owner_program: None,
+                decimals: None,
+                memo: None,
+                references: Vec::new(),
+                swap_slippage_bps: None,
+                compute_budget_unit_price: None,
+                compute_budget_unit_limit: None,
+                loaded_accounts_data_size_limit: None,
+                address_lookup_tables: Vec::new(),
+                durable_nonce: None,
+                jito_tip: None,
+                fee_payer: None,
+                token_extensions: Some(token_extensions.clone()),
+            };
+
+        let transactions = batch_transfer_transactions(
+            &sender,
+            &[transfer(&mint), transfer(&mint)],
+            &parameters(&token_extensions),
+        )
+        .unwrap();
+        let instructions = transactions[0].message.instructions();
+        assert_eq!(instructions.len(), 2);
+        for instruction in instructions {
+            assert_eq!(instruction.data[..2], [26, 1]);
+            assert_eq!(instruction.data[11..], 5_000u64.to_le_bytes());
+        }
+
+        let Err(TransactionError::Parameters(_)) = batch_transfer_transactions(
+            &sender,
+            &[transfer(&mint), transfer(&Pubkey::new_unique())],
+            &parameters(&token_extensions),
+        ) else {
+            panic!("The extensions should only describe a single mint");
+        };
+        token_extensions.destination_data = Some(to_base64([0; 165]));
+        let Err(TransactionError::Parameters(_)) = batch_transfer_transactions(
+            &sender,
+            &[transfer(&mint)],
+            &parameters(&token_extensions),
+        ) else {
+            panic!("The extensions shouldn't describe the account of a single recipient");
+        };
+    }
+}
@@ -0,0 +1,149 @@
+//! Fee math of the runtime: every signature pays the base fee and the compute
+//! budget instructions add a priority fee on top
+use solana_sdk::message::VersionedMessage;
+
+use super::types::{SolanaInstruction, TransactionFee};
+use crate::errors::*;
+
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const DEFAULT_COMPUTE_UNITS_PER_INSTRUCTION: u32 = 200_000;
+pub(crate) const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+/// Fee of the message, `instructions` are its decoded instructions. Fails when the
+/// fee doesn't fit in a `u64`, which no payer could afford anyway.
+pub(crate) fn transaction_fee(
+    message: &VersionedMessage,
+    instructions: &[SolanaInstruction],
+) -> Result<TransactionFee, TransactionError> {
+    let signatures = u64::from(message.header().num_required_signatures);
+    let mut unit_limit = None;
+    let mut unit_price = 0;
+    let mut other_instructions: u32 = 0;
+
+    for instruction in instructions.iter() {
+        match instruction {
+            SolanaInstruction::ComputeUnitLimit { units } => unit_limit = Some(*units),
+            SolanaInstruction::ComputeUnitPrice { micro_lamports } => unit_price = *micro_lamports,
+            SolanaInstruction::ComputeLoadedAccountsDataSizeLimit { .. } => (),
+            _ => other_instructions += 1,
+        }
+    }
+
+    let unit_limit = unit_limit
+        .unwrap_or(other_instructions.saturating_mul(DEFAULT_COMPUTE_UNITS_PER_INSTRUCTION))
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+    let priority_fee = priority_fee(unit_price, unit_limit)?;
+    let base_fee = signatures * LAMPORTS_PER_SIGNATURE;
+    let total_fee = base_fee
+        .checked_add(priority_fee)
+        .ok_or_else(|| TransactionError::parameters("The transaction fee overflows"))?;
+
+    Ok(TransactionFee {
+        signatures,
+        base_fee,
+        compute_unit_limit: unit_limit,
+        compute_unit_price: unit_price,
+        priority_fee,
+        total_fee,
+    })
+}
+
+/// Priority fee in lamports, rounded up like the runtime does
+pub(crate) fn priority_fee(unit_price: u64, unit_limit: u32) -> Result<u64, TransactionError> {
+    let priority_fee =
+        (u128::from(unit_price) * u128::from(unit_limit)).div_ceil(MICRO_LAMPORTS_PER_LAMPORT);
+    u64::try_from(priority_fee)
+        .map_err(|_| TransactionError::parameters("The priority fee overflows"))
+}
+
+/// Highest unit price in micro-lamports whose priority fee doesn't exceed `priority_fee` lamports
+pub(crate) fn unit_price_for_priority_fee(
+    priority_fee: u64,
+    unit_limit: u32,
+) -> Result<u64, TransactionError> {
+    if unit_limit == 0 {
+        return Err(TransactionError::parameters(
+            "The compute unit limit has to be greater than 0",
+        ));
+    }
+    let unit_price = u128::from(priority_fee) * MICRO_LAMPORTS_PER_LAMPORT / u128::from(unit_limit);
+    u64::try_from(unit_price)
+        .map_err(|_| TransactionError::parameters("The priority fee is too high"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, message::Message, pubkey::Pubkey,
+        system_instruction,
+    };
+
+    use crate::solana::instruction_helpers::decode_instructions;
+
+    #[test]
+    fn test_transaction_fee() {
+        let payer = Pubkey::new_unique();
+        let fee_of = |instructions: &[solana_sdk::instruction::Instruction]| {
+            let message = VersionedMessage::Legacy(Message::new(instructions, Some(&payer)));
+            let decoded = decode_instructions(&message, message.static_account_keys());
+            transaction_fee(&message, &decoded).unwrap()
+        };
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let fee = fee_of(&[
+            ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+            ComputeBudgetInstruction::set_compute_unit_price(25_001),
+            transfer.clone(),
+        ]);
+        assert_eq!(
+            fee,
+            TransactionFee {
+                signatures: 1,
+                base_fee: 5_000,
+                compute_unit_limit: 300_000,
+                compute_unit_price: 25_001,
+                priority_fee: 7_501,
+                total_fee: 12_501,
+            }
+        );
+
+        // Without a limit every instruction gets the default
+        let fee = fee_of(&[
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            transfer.clone(),
+            transfer,
+        ]);
+        assert_eq!(fee.compute_unit_limit, 400_000);
+        assert_eq!(fee.priority_fee, 400);
+
+        assert_eq!(unit_price_for_priority_fee(7_500, 300_000).unwrap(), 25_000);
+        assert_eq!(priority_fee(25_000, 300_000).unwrap(), 7_500);
+        assert_eq!(unit_price_for_priority_fee(1, 3).unwrap(), 333_333);
+        assert!(priority_fee(333_333, 3).unwrap() <= 1);
+        assert!(unit_price_for_priority_fee(1_000, 0).is_err());
+
+        // Extreme compute budgets overflow instead of wrapping around
+        assert_eq!(priority_fee(u64::MAX, 1_000_000).unwrap(), u64::MAX);
+        assert!(priority_fee(u64::MAX, 1_000_001).is_err());
+        let overflowing = |unit_limit: u32, unit_price: u64| {
+            let instructions = [
+                ComputeBudgetInstruction::set_compute_unit_limit(unit_limit),
+                ComputeBudgetInstruction::set_compute_unit_price(unit_price),
+            ];
+            let message = VersionedMessage::Legacy(Message::new(&instructions, Some(&payer)));
+            let decoded = decode_instructions(&message, message.static_account_keys());
+            transaction_fee(&message, &decoded)
+        };
+        let Err(TransactionError::Parameters(_)) =
+            overflowing(MAX_COMPUTE_UNIT_LIMIT, 13_176_245_766_935_394_012)
+        else {
+            panic!("The priority fee should overflow");
+        };
+        // The priority fee fits but not once the base fee is added
+        let Err(TransactionError::Parameters(_)) = overflowing(1_000_000, u64::MAX) else {
+            panic!("The total fee should overflow");
+        };
+    }
+}
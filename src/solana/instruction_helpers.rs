@@ -0,0 +1,315 @@
+use solana_sdk::{
+    compute_budget, instruction::CompiledInstruction, message::VersionedMessage, pubkey::Pubkey,
+    system_instruction::SystemInstruction, system_program,
+};
+use spl_token::instruction::TokenInstruction;
+
+use super::jupiter_helpers::{
+    decode_order_instruction, decode_route_instruction, DCA_PROGRAM_ID, JUPITER_V6_PROGRAM_ID,
+    LIMIT_ORDER_PROGRAM_ID,
+};
+use super::types::SolanaInstruction;
+use super::TOKEN_2022_PROGRAM;
+use crate::utils::to_base64;
+
+/// Decodes every instruction of `message`. `account_keys` are the keys the
+/// account indices of the instructions refer to.
+pub(crate) fn decode_instructions(
+    message: &VersionedMessage,
+    account_keys: &[Pubkey],
+) -> Vec<SolanaInstruction> {
+    message
+        .instructions()
+        .iter()
+        .map(|instruction| decode_instruction(instruction, account_keys))
+        .collect()
+}
+
+pub(crate) fn decode_instruction(
+    instruction: &CompiledInstruction,
+    account_keys: &[Pubkey],
+) -> SolanaInstruction {
+    let program_id = account_keys.get(usize::from(instruction.program_id_index));
+
+    program_id
+        .and_then(|program_id| {
+            let accounts = InstructionAccounts {
+                instruction,
+                account_keys,
+            };
+            decode_known_instruction(program_id, &accounts, &instruction.data)
+        })
+        .unwrap_or_else(|| SolanaInstruction::Unknown {
+            program: program_id.map(|p| p.to_string()).unwrap_or_default(),
+            data: to_base64(&instruction.data),
+        })
+}
+
+/// Resolves the accounts of a compiled instruction by their position
+struct InstructionAccounts<'a> {
+    instruction: &'a CompiledInstruction,
+    account_keys: &'a [Pubkey],
+}
+
+impl InstructionAccounts<'_> {
+    fn get(&self, position: usize) -> Option<String> {
+        let index = self.instruction.accounts.get(position)?;
+        self.account_keys
+            .get(usize::from(*index))
+            .map(|key| key.to_string())
+    }
+}
+
+fn decode_known_instruction(
+    program_id: &Pubkey,
+    accounts: &InstructionAccounts,
+    data: &[u8],
+) -> Option<SolanaInstruction> {
+    if *program_id == system_program::id() {
+        decode_system_instruction(accounts, data)
+    } else if *program_id == spl_token::id() || is_token_2022(program_id) {
+        decode_token_instruction(program_id, accounts, data)
+    } else if *program_id == spl_associated_token_account::id() {
+        decode_associated_token_account_instruction(accounts, data)
+    } else if *program_id == spl_memo::id() || *program_id == spl_memo::v1::id() {
+        Some(SolanaInstruction::Memo {
+            memo: String::from_utf8_lossy(data).to_string(),
+        })
+    } else if *program_id == compute_budget::id() {
+        decode_compute_budget_instruction(data)
+    } else if *program_id == JUPITER_V6_PROGRAM_ID {
+        decode_route_instruction(data)
+    } else if *program_id == LIMIT_ORDER_PROGRAM_ID || *program_id == DCA_PROGRAM_ID {
+        decode_order_instruction(program_id, data, |position| accounts.get(position))
+    } else {
+        None
+    }
+}
+
+fn decode_system_instruction(
+    accounts: &InstructionAccounts,
+    data: &[u8],
+) -> Option<SolanaInstruction> {
+    match bincode::deserialize::<SystemInstruction>(data).ok()? {
+        SystemInstruction::Transfer { lamports } => Some(SolanaInstruction::SystemTransfer {
+            from: accounts.get(0)?,
+            to: accounts.get(1)?,
+            lamports,
+        }),
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => Some(SolanaInstruction::SystemCreateAccount {
+            from: accounts.get(0)?,
+            new_account: accounts.get(1)?,
+            lamports,
+            space,
+            owner: owner.to_string(),
+        }),
+        SystemInstruction::Assign { owner } => Some(SolanaInstruction::SystemAssign {
+            account: accounts.get(0)?,
+            owner: owner.to_string(),
+        }),
+        SystemInstruction::AdvanceNonceAccount => {
+            Some(SolanaInstruction::SystemAdvanceNonceAccount {
+                nonce_account: accounts.get(0)?,
+                authority: accounts.get(2)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn decode_token_instruction(
+    program_id: &Pubkey,
+    accounts: &InstructionAccounts,
+    data: &[u8],
+) -> Option<SolanaInstruction> {
+    let program = program_id.to_string();
+    // `TransferCheckedWithFee` of the transfer fee extension
+    if is_token_2022(program_id) && data.len() == 19 && data[..2] == [26, 1] {
+        return Some(SolanaInstruction::TokenTransferCheckedWithFee {
+            program,
+            source: accounts.get(0)?,
+            mint: accounts.get(1)?,
+            destination: accounts.get(2)?,
+            authority: accounts.get(3)?,
+            amount: u64::from_le_bytes(data[2..10].try_into().ok()?),
+            decimals: data[10],
+            fee: u64::from_le_bytes(data[11..19].try_into().ok()?),
+        });
+    }
+    match TokenInstruction::unpack(data).ok()? {
+        TokenInstruction::Transfer { amount } => Some(SolanaInstruction::TokenTransfer {
+            program,
+            source: accounts.get(0)?,
+            destination: accounts.get(1)?,
+            authority: accounts.get(2)?,
+            amount,
+        }),
+        TokenInstruction::TransferChecked { amount, decimals } => {
+            Some(SolanaInstruction::TokenTransferChecked {
+                program,
+                source: accounts.get(0)?,
+                mint: accounts.get(1)?,
+                destination: accounts.get(2)?,
+                authority: accounts.get(3)?,
+                amount,
+                decimals,
+            })
+        }
+        TokenInstruction::CloseAccount => Some(SolanaInstruction::TokenCloseAccount {
+            program,
+            account: accounts.get(0)?,
+            destination: accounts.get(1)?,
+            owner: accounts.get(2)?,
+        }),
+        TokenInstruction::Approve { amount } => Some(SolanaInstruction::TokenApprove {
+            program,
+            source: accounts.get(0)?,
+            delegate: accounts.get(1)?,
+            owner: accounts.get(2)?,
+            amount,
+        }),
+        TokenInstruction::ApproveChecked { amount, .. } => Some(SolanaInstruction::TokenApprove {
+            program,
+            source: accounts.get(0)?,
+            delegate: accounts.get(2)?,
+            owner: accounts.get(3)?,
+            amount,
+        }),
+        TokenInstruction::SetAuthority {
+            authority_type,
+            new_authority,
+        } => Some(SolanaInstruction::TokenSetAuthority {
+            program,
+            account: accounts.get(0)?,
+            current_authority: accounts.get(1)?,
+            authority_type: format!("{authority_type:?}"),
+            new_authority: Option::<Pubkey>::from(new_authority).map(|key| key.to_string()),
+        }),
+        _ => None,
+    }
+}
+
+fn decode_associated_token_account_instruction(
+    accounts: &InstructionAccounts,
+    data: &[u8],
+) -> Option<SolanaInstruction> {
+    // An empty instruction is the original `Create`
+    let idempotent = match data.first() {
+        None | Some(0) => false,
+        Some(1) => true,
+        _ => return None,
+    };
+    Some(SolanaInstruction::AssociatedTokenAccountCreate {
+        payer: accounts.get(0)?,
+        account: accounts.get(1)?,
+        wallet: accounts.get(2)?,
+        mint: accounts.get(3)?,
+        token_program: accounts.get(5)?,
+        idempotent,
+    })
+}
+
+/// The compute budget program uses borsh, so we decode the few variants by hand
+pub(crate) fn decode_compute_budget_instruction(data: &[u8]) -> Option<SolanaInstruction> {
+    let (tag, payload) = data.split_first()?;
+    match tag {
+        2 => Some(SolanaInstruction::ComputeUnitLimit {
+            units: u32::from_le_bytes(payload.try_into().ok()?),
+        }),
+        3 => Some(SolanaInstruction::ComputeUnitPrice {
+            micro_lamports: u64::from_le_bytes(payload.try_into().ok()?),
+        }),
+        4 => Some(SolanaInstruction::ComputeLoadedAccountsDataSizeLimit {
+            bytes: u32::from_le_bytes(payload.try_into().ok()?),
+        }),
+        _ => None,
+    }
+}
+
+fn is_token_2022(program_id: &Pubkey) -> bool {
+    program_id.to_string() == TOKEN_2022_PROGRAM
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction, message::Message, system_instruction,
+    };
+
+    #[test]
+    fn test_decode_instructions() {
+        let owner = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            system_instruction::transfer(&owner, &receiver, 42),
+            spl_token::instruction::transfer_checked(
+                &spl_token::id(),
+                &source,
+                &mint,
+                &destination,
+                &owner,
+                &[],
+                1_000_000,
+                6,
+            )
+            .unwrap(),
+            spl_memo::build_memo(b"hello", &[&owner]),
+            solana_sdk::instruction::Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[1, 2, 3],
+                vec![],
+            ),
+        ];
+        let message = VersionedMessage::Legacy(Message::new(&instructions, Some(&owner)));
+        let decoded = decode_instructions(&message, message.static_account_keys());
+
+        assert_eq!(decoded[0], SolanaInstruction::ComputeUnitLimit { units: 200_000 });
+        assert_eq!(
+            decoded[1],
+            SolanaInstruction::ComputeUnitPrice {
+                micro_lamports: 1_000
+            }
+        );
+        assert_eq!(
+            decoded[2],
+            SolanaInstruction::SystemTransfer {
+                from: owner.to_string(),
+                to: receiver.to_string(),
+                lamports: 42,
+            }
+        );
+        assert_eq!(
+            decoded[3],
+            SolanaInstruction::TokenTransferChecked {
+                program: spl_token::id().to_string(),
+                source: source.to_string(),
+                mint: mint.to_string(),
+                destination: destination.to_string(),
+                authority: owner.to_string(),
+                amount: 1_000_000,
+                decimals: 6,
+            }
+        );
+        assert_eq!(
+            decoded[4],
+            SolanaInstruction::Memo {
+                memo: "hello".to_string()
+            }
+        );
+        assert!(matches!(
+            &decoded[5],
+            SolanaInstruction::Unknown { data, .. } if data == "AQID"
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+//! Tips for Jito validators, which only forward bundles and protected transactions
+//! that pay one of their tip accounts
+use rand::Rng;
+use solana_sdk::{
+    message::VersionedMessage,
+    pubkey,
+    pubkey::Pubkey,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+};
+
+use super::message_helpers::{message_parts_mut, push_instruction};
+use super::types::JitoTip;
+use crate::errors::*;
+
+pub(crate) const JITO_TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+/// Makes the fee payer tip `tip.lamports`. A tip the message already pays is updated
+/// instead of adding a second one. Without a `tip_account` a random one of the known
+/// accounts is used, which spreads the write locks across them.
+pub(crate) fn set_jito_tip(
+    message: &mut VersionedMessage,
+    tip: &JitoTip,
+) -> Result<(), TransactionError> {
+    let tip_account = match &tip.tip_account {
+        Some(tip_account) => Some(
+            tip_account
+                .parse::<Pubkey>()
+                .map_err(TransactionError::public_key)?,
+        ),
+        None => None,
+    };
+    let fee_payer = *message
+        .static_account_keys()
+        .first()
+        .ok_or(TransactionError::SignerMissing)?;
+
+    let account_keys = message.static_account_keys().to_vec();
+    let is_tip_account = |key: &Pubkey| match tip_account {
+        Some(tip_account) => *key == tip_account,
+        None => JITO_TIP_ACCOUNTS.contains(key),
+    };
+    let existing_tip = message_parts_mut(message).2.iter_mut().find(|instruction| {
+        let key =
+            |index: Option<&u8>| index.and_then(|index| account_keys.get(usize::from(*index)));
+        key(Some(&instruction.program_id_index)) == Some(&system_program::id())
+            && key(instruction.accounts.first()) == Some(&fee_payer)
+            && key(instruction.accounts.get(1)).is_some_and(is_tip_account)
+            && matches!(
+                bincode::deserialize(&instruction.data),
+                Ok(SystemInstruction::Transfer { .. })
+            )
+    });
+    if let Some(instruction) = existing_tip {
+        instruction.data = bincode::serialize(&SystemInstruction::Transfer {
+            lamports: tip.lamports,
+        })
+        .map_err(TransactionError::parsing_failure)?;
+        return Ok(());
+    }
+
+    let tip_account = tip_account.unwrap_or_else(|| {
+        JITO_TIP_ACCOUNTS[rand::thread_rng().gen_range(0..JITO_TIP_ACCOUNTS.len())]
+    });
+    push_instruction(
+        message,
+        &system_instruction::transfer(&fee_payer, &tip_account, tip.lamports),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        message::{v0, Message},
+    };
+
+    #[test]
+    fn test_set_jito_tip() {
+        let payer = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+        let tip = |lamports: u64| JitoTip {
+            lamports,
+            tip_account: None,
+        };
+
+        let legacy =
+            Message::new_with_blockhash(&[transfer.clone()], Some(&payer), &Hash::default());
+        let v0 = v0::Message::try_compile(&payer, &[transfer], &[], Hash::default()).unwrap();
+
+        for mut message in [VersionedMessage::Legacy(legacy), VersionedMessage::V0(v0)] {
+            set_jito_tip(&mut message, &tip(10_000)).unwrap();
+            assert_eq!(message.instructions().len(), 2);
+            let tip_instruction = &message.instructions()[1];
+            let tip_account =
+                message.static_account_keys()[usize::from(tip_instruction.accounts[1])];
+            assert!(JITO_TIP_ACCOUNTS.contains(&tip_account));
+            assert!(message.is_maybe_writable(usize::from(tip_instruction.accounts[1])));
+            assert_eq!(message.header().num_required_signatures, 1);
+
+            // A second tip only updates the amount
+            set_jito_tip(&mut message, &tip(20_000)).unwrap();
+            assert_eq!(message.instructions().len(), 2);
+            assert_eq!(
+                bincode::deserialize::<SystemInstruction>(&message.instructions()[1].data).unwrap(),
+                SystemInstruction::Transfer { lamports: 20_000 }
+            );
+
+            // Tipping a chosen account adds a transfer to it
+            let tip_account = Pubkey::new_unique();
+            let custom_tip = JitoTip {
+                lamports: 5_000,
+                tip_account: Some(tip_account.to_string()),
+            };
+            set_jito_tip(&mut message, &custom_tip).unwrap();
+            assert_eq!(message.instructions().len(), 3);
+            assert!(message.static_account_keys().contains(&tip_account));
+        }
+    }
+}
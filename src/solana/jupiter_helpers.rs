@@ -1,17 +1,911 @@
 
+use std::str::FromStr;
+
 use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
     pubkey,
     pubkey::Pubkey,
+    system_program,
     transaction::VersionedTransaction,
 };
 
+use serde::Deserialize;
+
+use super::associated_token_address_2022;
+use super::types::{
+    JupiterOrder, JupiterOrderAccount, JupiterOrderAction, JupiterRoutePlanStep, JupiterSwapEvent,
+    JupiterSwapFill, SolanaInstruction,
+};
 use crate::errors::*;
+use crate::types::DecimalNumber;
+use crate::utils::from_base64;
 
-const JUPITER_V6_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
-const ROUTE_DISCRIMINATOR: &[u8] = &[229, 23, 203, 151, 122, 227, 173, 42];
+pub(crate) const JUPITER_V6_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+pub(crate) const ROUTE_DISCRIMINATOR: &[u8] = &[229, 23, 203, 151, 122, 227, 173, 42];
 const SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR: &[u8] = &[193, 32, 155, 51, 65, 214, 156, 129];
-const EXACT_OUT_ROUTE_DISCRIMINATOR: &[u8] = &[208, 51, 239, 151, 123, 43, 237, 92];
+pub(crate) const EXACT_OUT_ROUTE_DISCRIMINATOR: &[u8] = &[208, 51, 239, 151, 123, 43, 237, 92];
 const SHARED_ACCOUNTS_EXACT_OUT_DISCRIMINATOR: &[u8] = &[176, 209, 105, 168, 154, 125, 69, 62];
+pub(crate) const ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: &[u8] = &[150, 86, 71, 116, 167, 93, 14, 104];
+const SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR: &[u8] =
+    &[230, 121, 143, 80, 119, 159, 106, 170];
+/// Anchor's `EVENT_IX_TAG`, the start of the self-CPI instructions emitting events
+const EVENT_IX_TAG: &[u8] = &[228, 69, 165, 46, 81, 203, 154, 29];
+const SWAP_EVENT_DISCRIMINATOR: &[u8] = &[64, 198, 205, 232, 38, 8, 113, 226];
+
+pub(crate) const LIMIT_ORDER_PROGRAM_ID: Pubkey =
+    pubkey!("j1o2qRpjcyUwEvwtcfhEQefh773ZgjxcVRry7LDqg5X");
+pub(crate) const DCA_PROGRAM_ID: Pubkey = pubkey!("DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M");
+const INITIALIZE_ORDER_DISCRIMINATOR: &[u8] = &[133, 110, 74, 175, 112, 159, 245, 159];
+const CANCEL_ORDER_DISCRIMINATOR: &[u8] = &[95, 129, 237, 240, 8, 49, 223, 132];
+const OPEN_DCA_DISCRIMINATOR: &[u8] = &[36, 65, 185, 54, 1, 210, 100, 163];
+const OPEN_DCA_V2_DISCRIMINATOR: &[u8] = &[142, 119, 43, 109, 162, 52, 11, 177];
+const CLOSE_DCA_DISCRIMINATOR: &[u8] = &[22, 7, 33, 98, 168, 183, 34, 243];
+const ORDER_ACCOUNT_DISCRIMINATOR: &[u8] = &[134, 173, 223, 185, 77, 86, 28, 51];
+const DCA_ACCOUNT_DISCRIMINATOR: &[u8] = &[82, 93, 90, 127, 40, 101, 145, 154];
+
+/// Positions of the accounts we care about in the route instructions
+pub(crate) struct RouteAccounts {
+    pub user_transfer_authority: usize,
+    pub source_token_account: usize,
+    pub destination_token_account: usize,
+    /// Overrides `destination_token_account` unless the program id is passed in its place
+    pub optional_destination_token_account: Option<usize>,
+    pub source_mint: Option<usize>,
+    pub destination_mint: usize,
+    /// Passed as the program id when the route takes no platform fee
+    pub platform_fee_account: usize,
+}
+
+/// Returns the account positions of the route instruction `name`,
+/// as returned by `decode_route_instruction`
+pub(crate) fn route_accounts(name: &str) -> Option<RouteAccounts> {
+    match name {
+        "route" | "route_with_token_ledger" => Some(RouteAccounts {
+            user_transfer_authority: 1,
+            source_token_account: 2,
+            destination_token_account: 3,
+            optional_destination_token_account: Some(4),
+            source_mint: None,
+            destination_mint: 5,
+            platform_fee_account: 6,
+        }),
+        "exact_out_route" => Some(RouteAccounts {
+            user_transfer_authority: 1,
+            source_token_account: 2,
+            destination_token_account: 3,
+            optional_destination_token_account: Some(4),
+            source_mint: Some(5),
+            destination_mint: 6,
+            platform_fee_account: 7,
+        }),
+        "shared_accounts_route"
+        | "shared_accounts_exact_out_route"
+        | "shared_accounts_route_with_token_ledger" => Some(RouteAccounts {
+            user_transfer_authority: 2,
+            source_token_account: 3,
+            destination_token_account: 6,
+            optional_destination_token_account: None,
+            source_mint: Some(7),
+            destination_mint: 8,
+            platform_fee_account: 9,
+        }),
+        _ => None,
+    }
+}
+
+/// Borsh encoded arguments of a `Swap` variant
+#[derive(Clone, Copy)]
+enum SwapField {
+    Bytes(usize),
+    RemainingAccountsInfo,
+    OptionalRemainingAccountsInfo,
+}
+
+const NO_FIELDS: &[SwapField] = &[];
+const BOOL: &[SwapField] = &[SwapField::Bytes(1)];
+
+/// Variants of the `Swap` enum of the Jupiter v6 IDL, in order. The route plan of a swap
+/// through any later AMM can't be decoded, but its amounts still can.
+const SWAPS: &[(&str, &[SwapField])] = &[
+    ("Saber", NO_FIELDS),
+    ("SaberAddDecimalsDeposit", NO_FIELDS),
+    ("SaberAddDecimalsWithdraw", NO_FIELDS),
+    ("TokenSwap", NO_FIELDS),
+    ("Sencha", NO_FIELDS),
+    ("Step", NO_FIELDS),
+    ("Cropper", NO_FIELDS),
+    ("Raydium", NO_FIELDS),
+    ("Crema", BOOL),
+    ("Lifinity", NO_FIELDS),
+    ("Mercurial", NO_FIELDS),
+    ("Cykura", NO_FIELDS),
+    ("Serum", BOOL),
+    ("MarinadeDeposit", NO_FIELDS),
+    ("MarinadeUnstake", NO_FIELDS),
+    ("Aldrin", BOOL),
+    ("AldrinV2", BOOL),
+    ("Whirlpool", BOOL),
+    ("Invariant", BOOL),
+    ("Meteora", NO_FIELDS),
+    ("GooseFX", NO_FIELDS),
+    ("DeltaFi", BOOL),
+    ("Balansol", NO_FIELDS),
+    ("MarcoPolo", BOOL),
+    ("Dradex", BOOL),
+    ("LifinityV2", NO_FIELDS),
+    ("RaydiumClmm", NO_FIELDS),
+    ("Openbook", BOOL),
+    ("Phoenix", BOOL),
+    ("Symmetry", &[SwapField::Bytes(8 + 8)]),
+    ("TokenSwapV2", NO_FIELDS),
+    ("HeliumTreasuryManagementRedeemV0", NO_FIELDS),
+    ("StakeDexStakeWrappedSol", NO_FIELDS),
+    ("StakeDexSwapViaStake", &[SwapField::Bytes(4)]),
+    ("GooseFXV2", NO_FIELDS),
+    ("Perps", NO_FIELDS),
+    ("PerpsAddLiquidity", NO_FIELDS),
+    ("PerpsRemoveLiquidity", NO_FIELDS),
+    ("MeteoraDlmm", NO_FIELDS),
+    ("OpenBookV2", BOOL),
+    ("RaydiumClmmV2", NO_FIELDS),
+    ("StakeDexPrefundWithdrawStakeAndDepositStake", &[SwapField::Bytes(4)]),
+    ("Clone", &[SwapField::Bytes(1 + 1 + 1)]),
+    ("SanctumS", &[SwapField::Bytes(1 + 1 + 4 + 4)]),
+    ("SanctumSAddLiquidity", &[SwapField::Bytes(1 + 4)]),
+    ("SanctumSRemoveLiquidity", &[SwapField::Bytes(1 + 4)]),
+    ("RaydiumCP", NO_FIELDS),
+    (
+        "WhirlpoolSwapV2",
+        &[
+            SwapField::Bytes(1),
+            SwapField::OptionalRemainingAccountsInfo,
+        ],
+    ),
+    ("OneIntro", NO_FIELDS),
+    ("PumpdotfunWrappedBuy", NO_FIELDS),
+    ("PumpdotfunWrappedSell", NO_FIELDS),
+    ("PerpsV2", NO_FIELDS),
+    ("PerpsV2AddLiquidity", NO_FIELDS),
+    ("PerpsV2RemoveLiquidity", NO_FIELDS),
+    ("MoonshotWrappedBuy", NO_FIELDS),
+    ("MoonshotWrappedSell", NO_FIELDS),
+    ("StabbleStableSwap", NO_FIELDS),
+    ("StabbleWeightedSwap", NO_FIELDS),
+    ("Obric", BOOL),
+    ("FoxBuyFromEstimatedCost", NO_FIELDS),
+    ("FoxClaimPartial", BOOL),
+    ("SolFi", BOOL),
+    ("SolayerDelegateNoInit", NO_FIELDS),
+    ("SolayerUndelegateNoInit", NO_FIELDS),
+    ("TokenMill", BOOL),
+    ("DaosFunBuy", NO_FIELDS),
+    ("DaosFunSell", NO_FIELDS),
+    ("ZeroFi", NO_FIELDS),
+    ("StakeDexWithdrawWrappedSol", NO_FIELDS),
+    ("VirtualsBuy", NO_FIELDS),
+    ("VirtualsSell", NO_FIELDS),
+    ("Perena", &[SwapField::Bytes(1 + 1)]),
+    ("PumpdotfunAmmBuy", NO_FIELDS),
+    ("PumpdotfunAmmSell", NO_FIELDS),
+    ("Gamma", NO_FIELDS),
+    ("MeteoraDlmmSwapV2", &[SwapField::RemainingAccountsInfo]),
+    ("Woofi", NO_FIELDS),
+    ("MeteoraDammV2", NO_FIELDS),
+    ("MeteoraDynamicBondingCurveSwap", NO_FIELDS),
+    ("StabbleStableSwapV2", NO_FIELDS),
+    ("StabbleWeightedSwapV2", NO_FIELDS),
+    ("RaydiumLaunchlabBuy", &[SwapField::Bytes(8)]),
+    ("RaydiumLaunchlabSell", &[SwapField::Bytes(8)]),
+    ("BoopdotfunWrappedBuy", NO_FIELDS),
+    ("BoopdotfunWrappedSell", NO_FIELDS),
+    ("Plasma", BOOL),
+    ("GoonFi", &[SwapField::Bytes(1 + 1)]),
+    ("HumidiFi", &[SwapField::Bytes(8 + 1)]),
+    ("MeteoraDynamicBondingCurveSwapWithRemainingAccounts", NO_FIELDS),
+    ("TesseraV", BOOL),
+];
+
+/// Decodes a route instruction. The arguments of every route are
+/// `[id: u8,] route_plan: Vec<RoutePlanStep>, amount: u64, quoted_amount: u64,
+/// slippage_bps: u16, platform_fee_bps: u8`, where only the shared accounts routes
+/// have an `id` and the token ledger variants lack the first amount.
+///
+/// When the route plan uses an AMM we don't know, its length is unknown, so the
+/// amounts are read from the end and `route_plan` is `None`.
+pub(crate) fn decode_route_instruction(data: &[u8]) -> Option<SolanaInstruction> {
+    let (discriminator, args) = data.split_at_checked(8)?;
+    let (name, exact_out, with_amount, with_id) = match discriminator {
+        ROUTE_DISCRIMINATOR => ("route", false, true, false),
+        SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR => ("shared_accounts_route", false, true, true),
+        EXACT_OUT_ROUTE_DISCRIMINATOR => ("exact_out_route", true, true, false),
+        SHARED_ACCOUNTS_EXACT_OUT_DISCRIMINATOR => {
+            ("shared_accounts_exact_out_route", true, true, true)
+        }
+        ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => ("route_with_token_ledger", false, false, false),
+        SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => {
+            ("shared_accounts_route_with_token_ledger", false, false, true)
+        }
+        _ => return None,
+    };
+
+    let tail_len = if with_amount { 8 + 8 + 2 + 1 } else { 8 + 2 + 1 };
+    let route_plan = args
+        .get(usize::from(with_id)..)
+        .and_then(|args| decode_route_plan(args, tail_len));
+
+    let mut tail = Reader(args.get(args.len().checked_sub(tail_len)?..)?);
+    let amount = if with_amount { Some(tail.u64()?) } else { None };
+    let quoted_amount = tail.u64()?;
+    let slippage_bps = tail.u16()?;
+    let platform_fee_bps = tail.u8()?;
+
+    Some(if exact_out {
+        SolanaInstruction::JupiterRoute {
+            name: name.to_string(),
+            route_plan,
+            in_amount: None,
+            quoted_in_amount: Some(quoted_amount),
+            out_amount: amount,
+            quoted_out_amount: None,
+            slippage_bps,
+            platform_fee_bps,
+        }
+    } else {
+        SolanaInstruction::JupiterRoute {
+            name: name.to_string(),
+            route_plan,
+            in_amount: amount,
+            quoted_in_amount: None,
+            out_amount: None,
+            quoted_out_amount: Some(quoted_amount),
+            slippage_bps,
+            platform_fee_bps,
+        }
+    })
+}
+
+/// Decodes the route plan at the start of `args`, which have to end with exactly
+/// `tail_len` bytes of amounts behind it
+fn decode_route_plan(args: &[u8], tail_len: usize) -> Option<Vec<JupiterRoutePlanStep>> {
+    let mut reader = Reader(args);
+    let len = reader.u32()?;
+    let mut route_plan = Vec::new();
+    for _ in 0..len {
+        let swap = reader.swap()?;
+        route_plan.push(JupiterRoutePlanStep {
+            swap: swap.to_string(),
+            percent: reader.u8()?,
+            input_index: reader.u8()?,
+            output_index: reader.u8()?,
+        });
+    }
+    (reader.0.len() == tail_len).then_some(route_plan)
+}
+
+/// Returns the name of the borsh encoded `Swap` variant in `swap`, if that's all it holds
+pub(crate) fn swap_name(swap: &[u8]) -> Option<&'static str> {
+    let mut reader = Reader(swap);
+    let name = reader.swap()?;
+    reader.0.is_empty().then_some(name)
+}
+
+/// Minimal borsh reader for the instruction arguments
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let (bytes, rest) = self.0.split_at_checked(len)?;
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// Reads an `Option` with `read` reading its value
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Option<T>) -> Option<Option<T>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => read(self).map(Some),
+            _ => None,
+        }
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Some(Pubkey::new_from_array(self.take(32)?.try_into().ok()?))
+    }
+
+    /// Reads a `Swap` and returns the name of its variant
+    fn swap(&mut self) -> Option<&'static str> {
+        let (swap, fields) = SWAPS.get(usize::from(self.u8()?))?;
+        for field in fields.iter() {
+            match field {
+                SwapField::Bytes(len) => self.skip(*len)?,
+                SwapField::RemainingAccountsInfo => self.skip_remaining_accounts_info()?,
+                SwapField::OptionalRemainingAccountsInfo => {
+                    if self.u8()? != 0 {
+                        self.skip_remaining_accounts_info()?;
+                    }
+                }
+            }
+        }
+        Some(swap)
+    }
+
+    /// `RemainingAccountsInfo { slices: Vec<{ accounts_type: u8, length: u8 }> }`
+    fn skip_remaining_accounts_info(&mut self) -> Option<()> {
+        let len = self.u32()?;
+        self.skip(usize::try_from(len).ok()?.checked_mul(2)?)
+    }
+}
+
+/// The parts of the metadata of a confirmed transaction, as returned by `getTransaction`,
+/// that carry events. Inner instructions are `json` or `jsonParsed` encoded.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionMeta {
+    inner_instructions: Option<Vec<InnerInstructions>>,
+    log_messages: Option<Vec<String>>,
+    loaded_addresses: Option<LoadedAddresses>,
+}
+
+#[derive(Deserialize)]
+struct InnerInstructions {
+    instructions: Vec<InnerInstruction>,
+}
+
+/// `json` encoded instructions refer to their program by index, `jsonParsed` ones by id.
+/// Instructions the RPC parsed for us have no `data`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InnerInstruction {
+    program_id_index: Option<usize>,
+    program_id: Option<String>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LoadedAddresses {
+    writable: Vec<String>,
+    readonly: Vec<String>,
+}
+
+/// Decodes the `SwapEvent`s Jupiter emitted while executing a transaction from its `meta`.
+/// `account_keys` are the static keys of the transaction, the keys it loaded from lookup
+/// tables are read from the metadata. Events are emitted through self-CPI, older routes
+/// logged them instead, so the logs are only read when no inner instruction holds one.
+///
+/// Returns `None` when the transaction didn't swap through Jupiter.
+pub(crate) fn decode_swap_events(
+    meta: &str,
+    account_keys: &[Pubkey],
+) -> Result<Option<JupiterSwapFill>, TransactionError> {
+    let meta: TransactionMeta =
+        serde_json::from_str(meta).map_err(TransactionError::parsing_failure)?;
+
+    let mut keys: Vec<String> = account_keys.iter().map(Pubkey::to_string).collect();
+    if let Some(loaded_addresses) = meta.loaded_addresses {
+        keys.extend(loaded_addresses.writable);
+        keys.extend(loaded_addresses.readonly);
+    }
+    let jupiter = JUPITER_V6_PROGRAM_ID.to_string();
+
+    let mut events = Vec::new();
+    let instructions = meta
+        .inner_instructions
+        .iter()
+        .flatten()
+        .flat_map(|inner| &inner.instructions);
+    for instruction in instructions {
+        let program = match (&instruction.program_id, instruction.program_id_index) {
+            (Some(program), _) => Some(program),
+            (None, Some(index)) => keys.get(index),
+            (None, None) => None,
+        };
+        let (Some(program), Some(data)) = (program, &instruction.data) else {
+            continue;
+        };
+        if *program != jupiter {
+            continue;
+        }
+        let data = bs58::decode(data)
+            .into_vec()
+            .map_err(TransactionError::parsing_failure)?;
+        if let Some(event) = data.strip_prefix(EVENT_IX_TAG).and_then(decode_swap_event) {
+            events.push(event);
+        }
+    }
+
+    if events.is_empty() {
+        // Logged events belong to the program on top of the invocation stack
+        let mut programs = Vec::new();
+        for log in meta.log_messages.iter().flatten() {
+            if let Some(data) = log.strip_prefix("Program data: ") {
+                if programs.last() == Some(&jupiter) {
+                    let data = from_base64(data).map_err(TransactionError::parsing_failure)?;
+                    events.extend(decode_swap_event(&data));
+                }
+            } else if let Some((program, status)) = log
+                .strip_prefix("Program ")
+                .and_then(|log| log.split_once(' '))
+            {
+                if status.starts_with("invoke [") {
+                    programs.push(program.to_string());
+                } else if status == "success" || status.starts_with("failed") {
+                    programs.pop();
+                }
+            }
+        }
+    }
+
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        return Ok(None);
+    };
+    // Split routes swap the input and output mints in several legs
+    let input_mint = first.input_mint.clone();
+    let output_mint = last.output_mint.clone();
+    let input_amount = events
+        .iter()
+        .filter(|event| event.input_mint == input_mint)
+        .fold(0u64, |total, event| total.saturating_add(event.input_amount));
+    let output_amount = events
+        .iter()
+        .filter(|event| event.output_mint == output_mint)
+        .fold(0u64, |total, event| total.saturating_add(event.output_amount));
+
+    Ok(Some(JupiterSwapFill {
+        legs: events,
+        input_mint,
+        input_amount,
+        output_mint,
+        output_amount,
+    }))
+}
+
+/// Decodes `SwapEvent { amm: Pubkey, input_mint: Pubkey, input_amount: u64,
+/// output_mint: Pubkey, output_amount: u64 }` behind its discriminator
+fn decode_swap_event(data: &[u8]) -> Option<JupiterSwapEvent> {
+    let mut reader = Reader(data.strip_prefix(SWAP_EVENT_DISCRIMINATOR)?);
+    Some(JupiterSwapEvent {
+        amm: reader.pubkey()?.to_string(),
+        input_mint: reader.pubkey()?.to_string(),
+        input_amount: reader.u64()?,
+        output_mint: reader.pubkey()?.to_string(),
+        output_amount: reader.u64()?,
+    })
+}
+
+/// Decodes the instructions of the limit order and DCA programs we build, with
+/// `account` resolving the account at a position of the instruction
+pub(crate) fn decode_order_instruction(
+    program_id: &Pubkey,
+    data: &[u8],
+    account: impl Fn(usize) -> Option<String>,
+) -> Option<SolanaInstruction> {
+    let (discriminator, args) = data.split_at_checked(8)?;
+    let mut args = Reader(args);
+    if *program_id == LIMIT_ORDER_PROGRAM_ID {
+        match discriminator {
+            INITIALIZE_ORDER_DISCRIMINATOR => {
+                args.skip(8)?;
+                Some(SolanaInstruction::JupiterLimitOrderCreate {
+                    maker: account(1)?,
+                    order: account(2)?,
+                    input_mint: account(7)?,
+                    output_mint: account(8)?,
+                    making_amount: args.u64()?,
+                    taking_amount: args.u64()?,
+                    expired_at: args.option(Reader::i64)?,
+                })
+            }
+            CANCEL_ORDER_DISCRIMINATOR => Some(SolanaInstruction::JupiterLimitOrderCancel {
+                maker: account(1)?,
+                order: account(2)?,
+            }),
+            _ => None,
+        }
+    } else if *program_id == DCA_PROGRAM_ID {
+        // `open_dca` has no separate payer, so the accounts behind the user shift by one
+        let payer = match discriminator {
+            OPEN_DCA_DISCRIMINATOR => 0,
+            OPEN_DCA_V2_DISCRIMINATOR => 1,
+            CLOSE_DCA_DISCRIMINATOR => {
+                return Some(SolanaInstruction::JupiterDcaClose {
+                    user: account(0)?,
+                    dca: account(1)?,
+                })
+            }
+            _ => return None,
+        };
+        args.skip(8)?;
+        Some(SolanaInstruction::JupiterDcaOpen {
+            user: account(1)?,
+            dca: account(0)?,
+            input_mint: account(2 + payer)?,
+            output_mint: account(3 + payer)?,
+            in_amount: args.u64()?,
+            in_amount_per_cycle: args.u64()?,
+            cycle_frequency: args.i64()?,
+            min_out_amount: args.option(Reader::u64)?,
+            max_out_amount: args.option(Reader::u64)?,
+            start_at: args.option(Reader::i64)?,
+        })
+    } else {
+        None
+    }
+}
+
+/// Returns the instructions of `action` and the account paying for them
+pub(crate) fn jupiter_order_instructions(
+    action: &JupiterOrderAction,
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    let pubkey = |key: &str| Pubkey::from_str(key).map_err(TransactionError::public_key);
+    let token_program = |program: &Option<String>| match program {
+        Some(program) => pubkey(program),
+        None => Ok(spl_token::id()),
+    };
+    let event_authority =
+        |program: &Pubkey| Pubkey::find_program_address(&[b"__event_authority"], program).0;
+
+    match action {
+        JupiterOrderAction::CreateLimitOrder {
+            maker,
+            input_mint,
+            output_mint,
+            making_amount,
+            taking_amount,
+            expired_at,
+            unique_id,
+            input_token_program,
+            output_token_program,
+        } => {
+            let maker = maker.to_solana_pubkey()?;
+            let input_mint = input_mint.to_solana_pubkey()?;
+            let output_mint = output_mint.to_solana_pubkey()?;
+            let input_token_program = token_program(input_token_program)?;
+            let output_token_program = token_program(output_token_program)?;
+            let order = limit_order_address(&maker, *unique_id);
+            let fee = Pubkey::find_program_address(&[b"fee"], &LIMIT_ORDER_PROGRAM_ID).0;
+
+            let mut data = INITIALIZE_ORDER_DISCRIMINATOR.to_vec();
+            data.extend(unique_id.to_le_bytes());
+            data.extend(making_amount.to_u64()?.to_le_bytes());
+            data.extend(taking_amount.to_u64()?.to_le_bytes());
+            push_option(&mut data, expired_at.map(i64::to_le_bytes));
+            // No custom fee
+            push_option(&mut data, None::<[u8; 2]>);
+
+            let instruction = Instruction {
+                program_id: LIMIT_ORDER_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(maker, true),
+                    AccountMeta::new_readonly(maker, true),
+                    AccountMeta::new(order, false),
+                    AccountMeta::new(
+                        associated_token_address_2022(&order, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new(
+                        associated_token_address_2022(&maker, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(fee, false),
+                    // No referral
+                    AccountMeta::new_readonly(LIMIT_ORDER_PROGRAM_ID, false),
+                    AccountMeta::new_readonly(input_mint, false),
+                    AccountMeta::new_readonly(output_mint, false),
+                    AccountMeta::new_readonly(input_token_program, false),
+                    AccountMeta::new_readonly(output_token_program, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                    AccountMeta::new_readonly(event_authority(&LIMIT_ORDER_PROGRAM_ID), false),
+                    AccountMeta::new_readonly(LIMIT_ORDER_PROGRAM_ID, false),
+                ],
+                data,
+            };
+            Ok((vec![instruction], maker))
+        }
+        JupiterOrderAction::CancelLimitOrder {
+            maker,
+            order,
+            input_mint,
+            input_token_program,
+        } => {
+            let maker = maker.to_solana_pubkey()?;
+            let order = order.to_solana_pubkey()?;
+            let input_mint = input_mint.to_solana_pubkey()?;
+            let input_token_program = token_program(input_token_program)?;
+
+            let instruction = Instruction {
+                program_id: LIMIT_ORDER_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(maker, true),
+                    AccountMeta::new(maker, false),
+                    AccountMeta::new(order, false),
+                    AccountMeta::new(
+                        associated_token_address_2022(&order, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new(
+                        associated_token_address_2022(&maker, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(input_mint, false),
+                    AccountMeta::new_readonly(input_token_program, false),
+                    AccountMeta::new_readonly(event_authority(&LIMIT_ORDER_PROGRAM_ID), false),
+                    AccountMeta::new_readonly(LIMIT_ORDER_PROGRAM_ID, false),
+                ],
+                data: CANCEL_ORDER_DISCRIMINATOR.to_vec(),
+            };
+            Ok((vec![instruction], maker))
+        }
+        JupiterOrderAction::OpenDca {
+            user,
+            input_mint,
+            output_mint,
+            in_amount,
+            in_amount_per_cycle,
+            cycle_frequency,
+            min_out_amount,
+            max_out_amount,
+            start_at,
+            application_idx,
+            input_token_program,
+            output_token_program,
+        } => {
+            let user = user.to_solana_pubkey()?;
+            let input_mint = input_mint.to_solana_pubkey()?;
+            let output_mint = output_mint.to_solana_pubkey()?;
+            let input_token_program = token_program(input_token_program)?;
+            let output_token_program = token_program(output_token_program)?;
+            let dca = dca_address(&user, &input_mint, &output_mint, *application_idx);
+
+            let mut data = OPEN_DCA_V2_DISCRIMINATOR.to_vec();
+            data.extend(application_idx.to_le_bytes());
+            data.extend(in_amount.to_u64()?.to_le_bytes());
+            data.extend(in_amount_per_cycle.to_u64()?.to_le_bytes());
+            data.extend(cycle_frequency.to_le_bytes());
+            let min_out_amount = min_out_amount
+                .as_ref()
+                .map(DecimalNumber::to_u64)
+                .transpose()?;
+            let max_out_amount = max_out_amount
+                .as_ref()
+                .map(DecimalNumber::to_u64)
+                .transpose()?;
+            push_option(&mut data, min_out_amount.map(u64::to_le_bytes));
+            push_option(&mut data, max_out_amount.map(u64::to_le_bytes));
+            push_option(&mut data, start_at.map(i64::to_le_bytes));
+
+            let instruction = Instruction {
+                program_id: DCA_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(dca, false),
+                    AccountMeta::new_readonly(user, true),
+                    AccountMeta::new(user, true),
+                    AccountMeta::new_readonly(input_mint, false),
+                    AccountMeta::new_readonly(output_mint, false),
+                    AccountMeta::new(
+                        associated_token_address_2022(&user, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new(
+                        associated_token_address_2022(&dca, &input_token_program, &input_mint),
+                        false,
+                    ),
+                    AccountMeta::new(
+                        associated_token_address_2022(&dca, &output_token_program, &output_mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(input_token_program, false),
+                    AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                    AccountMeta::new_readonly(event_authority(&DCA_PROGRAM_ID), false),
+                    AccountMeta::new_readonly(DCA_PROGRAM_ID, false),
+                ],
+                data,
+            };
+            Ok((vec![instruction], user))
+        }
+        JupiterOrderAction::CloseDca {
+            user,
+            dca,
+            input_mint,
+            output_mint,
+            input_token_program,
+            output_token_program,
+        } => {
+            let user = user.to_solana_pubkey()?;
+            let dca = dca.to_solana_pubkey()?;
+            let input_mint = input_mint.to_solana_pubkey()?;
+            let output_mint = output_mint.to_solana_pubkey()?;
+            let input_token_program = token_program(input_token_program)?;
+            let output_token_program = token_program(output_token_program)?;
+            let token_account = |owner: &Pubkey, program: &Pubkey, mint: &Pubkey| {
+                AccountMeta::new(associated_token_address_2022(owner, program, mint), false)
+            };
+
+            let instruction = Instruction {
+                program_id: DCA_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(user, true),
+                    AccountMeta::new(dca, false),
+                    AccountMeta::new_readonly(input_mint, false),
+                    AccountMeta::new_readonly(output_mint, false),
+                    token_account(&dca, &input_token_program, &input_mint),
+                    token_account(&dca, &output_token_program, &output_mint),
+                    token_account(&user, &input_token_program, &input_mint),
+                    token_account(&user, &output_token_program, &output_mint),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(input_token_program, false),
+                    AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                    AccountMeta::new_readonly(event_authority(&DCA_PROGRAM_ID), false),
+                    AccountMeta::new_readonly(DCA_PROGRAM_ID, false),
+                ],
+                data: CLOSE_DCA_DISCRIMINATOR.to_vec(),
+            };
+            Ok((vec![instruction], user))
+        }
+    }
+}
+
+/// The limit order `maker` opens with `unique_id`
+fn limit_order_address(maker: &Pubkey, unique_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"order", maker.as_ref(), &unique_id.to_le_bytes()],
+        &LIMIT_ORDER_PROGRAM_ID,
+    )
+    .0
+}
+
+/// The DCA `user` opens from `input_mint` to `output_mint` with `application_idx`
+fn dca_address(
+    user: &Pubkey,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    application_idx: u64,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"dca",
+            user.as_ref(),
+            input_mint.as_ref(),
+            output_mint.as_ref(),
+            &application_idx.to_le_bytes(),
+        ],
+        &DCA_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Appends a borsh encoded `Option`
+fn push_option<const N: usize>(data: &mut Vec<u8>, value: Option<[u8; N]>) {
+    match value {
+        Some(value) => {
+            data.push(1);
+            data.extend(value);
+        }
+        None => data.push(0),
+    }
+}
+
+/// Decodes a limit order or DCA account, as returned by `getProgramAccounts`.
+///
+/// `Order { maker, input_mint, output_mint, input_token_program, output_token_program,
+/// input_mint_reserve: Pubkey, unique_id, ori_making_amount, ori_taking_amount,
+/// making_amount, taking_amount, borrow_making_amount: u64, expired_at: Option<i64>,
+/// fee_bps: u16, fee_account: Pubkey, created_at, updated_at: i64, bump: u8 }`
+///
+/// `Dca { user, input_mint, output_mint: Pubkey, idx: u64, next_cycle_at: i64,
+/// in_deposited, in_withdrawn, out_withdrawn, in_used, out_received, in_amount_per_cycle: u64,
+/// cycle_frequency: i64, next_cycle_amount_left: u64, in_account, out_account: Pubkey,
+/// min_out_amount, max_out_amount, ..., created_at: i64, bump: u8 }`
+pub(crate) fn parse_order_account(
+    account: &JupiterOrderAccount,
+) -> Result<JupiterOrder, TransactionError> {
+    let data = from_base64(&account.data).map_err(TransactionError::parsing_failure)?;
+    let invalid =
+        || TransactionError::parameters(format!("{} is no Jupiter order account", account.address));
+    let (discriminator, data) = data.split_at_checked(8).ok_or_else(invalid)?;
+    let mut reader = Reader(data);
+    let order = match discriminator {
+        ORDER_ACCOUNT_DISCRIMINATOR => decode_limit_order(&account.address, &mut reader),
+        DCA_ACCOUNT_DISCRIMINATOR => decode_dca(&account.address, &mut reader),
+        _ => None,
+    };
+    order.ok_or_else(invalid)
+}
+
+fn decode_limit_order(address: &str, reader: &mut Reader) -> Option<JupiterOrder> {
+    let maker = reader.pubkey()?;
+    let input_mint = reader.pubkey()?;
+    let output_mint = reader.pubkey()?;
+    // Token programs and the reserve
+    reader.skip(3 * 32)?;
+    let unique_id = reader.u64()?;
+    let ori_making_amount = reader.u64()?;
+    let ori_taking_amount = reader.u64()?;
+    let making_amount = reader.u64()?;
+    let taking_amount = reader.u64()?;
+    reader.skip(8)?;
+    let expired_at = reader.option(Reader::i64)?;
+    // Fee
+    reader.skip(2 + 32)?;
+    Some(JupiterOrder::LimitOrder {
+        address: address.to_string(),
+        maker: maker.to_string(),
+        input_mint: input_mint.to_string(),
+        output_mint: output_mint.to_string(),
+        unique_id,
+        ori_making_amount,
+        ori_taking_amount,
+        making_amount,
+        taking_amount,
+        expired_at,
+        created_at: reader.i64()?,
+    })
+}
+
+fn decode_dca(address: &str, reader: &mut Reader) -> Option<JupiterOrder> {
+    let user = reader.pubkey()?;
+    let input_mint = reader.pubkey()?;
+    let output_mint = reader.pubkey()?;
+    let idx = reader.u64()?;
+    let next_cycle_at = reader.i64()?;
+    let in_deposited = reader.u64()?;
+    let in_withdrawn = reader.u64()?;
+    let out_withdrawn = reader.u64()?;
+    let in_used = reader.u64()?;
+    let out_received = reader.u64()?;
+    let in_amount_per_cycle = reader.u64()?;
+    let cycle_frequency = reader.i64()?;
+    // Amount left in the cycle and the token accounts
+    reader.skip(8 + 2 * 32)?;
+    let min_out_amount = reader.u64()?;
+    let max_out_amount = reader.u64()?;
+    // Balances of the keeper and of the DCA before a swap
+    reader.skip(2 * 8)?;
+    Some(JupiterOrder::Dca {
+        address: address.to_string(),
+        user: user.to_string(),
+        input_mint: input_mint.to_string(),
+        output_mint: output_mint.to_string(),
+        idx,
+        next_cycle_at,
+        in_deposited,
+        in_withdrawn,
+        in_used,
+        out_received,
+        out_withdrawn,
+        in_amount_per_cycle,
+        cycle_frequency,
+        // Zero stands for no bound
+        min_out_amount: (min_out_amount != 0).then_some(min_out_amount),
+        max_out_amount: (max_out_amount != 0).then_some(max_out_amount),
+        created_at: reader.i64()?,
+    })
+}
 
 pub(crate) fn mutate_transaction_slippage_bps(
     versioned_transaction: &mut VersionedTransaction,
@@ -46,7 +940,9 @@ pub(crate) fn mutate_transaction_slippage_bps(
                     ROUTE_DISCRIMINATOR
                     | SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR
                     | SHARED_ACCOUNTS_EXACT_OUT_DISCRIMINATOR
-                    | EXACT_OUT_ROUTE_DISCRIMINATOR => {
+                    | EXACT_OUT_ROUTE_DISCRIMINATOR
+                    | ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR
+                    | SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR => {
                         if jupiter_swap_ix_found {
                             return Err(TransactionError::Generic(
                                 "Duplicate swap instruction".into(),
@@ -82,8 +978,10 @@ pub(crate) fn mutate_transaction_slippage_bps(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::from_base64;
-    
+    use crate::solana::TOKEN_2022_PROGRAM;
+    use crate::types::{Blockchain, ChainPublicKey};
+    use crate::utils::to_base64;
+
     #[test]
     fn test_mutate_transaction_slippage_bps() {
         let base64_tx = "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAQAGCmb9xdrDtJYk7SvJmju4CpS8tgk++rcm6zvJ55YhNnkyFyMa9+i/QdXyfkMKzum7vNcYFEYFPWEHOkn7ubmPMy8uy3ly9YjP0u4bWlq58MCtylAkiN9u7LB/14O1R2UKGEtLpKDA2nb16o7DnkNeYpajr8pWfkX5+cYZej/F5CTJAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAADBkZv5SEXMv/srbpyw5vnvIzlu8X3EmssQ5s6QAAAAAR51VvyMcBu7nTFbs5oFQf9sbLeo/SOUQKxzaJWvBOPBt324ddloZPZy+FGzut5rBy0he1fWzeROoz1hX7/AKmMlyWPTiSJ8bs9ECkUjg2DC1oTmdr/EIQEjnvY2+n4WbQ/+if11/ZKdMCbHylYed5LCas238ndUUsyGqezjOXo4AY1NdAvbDuSSJJNK0yR9lJs7g4BkENiJvgeZ7c1JKcHBQAFAm5dAgAFAAkDBgAAAAAAAAAIBgACABEEBwEBBAIAAgwCAAAAAOH1BQAAAAAHAQIBEQYdBwACAwYTBgkGEA4QCwoCAxETDxAABwcSEA0MAQYj5RfLl3rjrSoBAAAAJmQAAQDh9QUAAAAA53bhAAAAAAAsAQAHAwIAAAEJAbaRFM1U56as5v3jHnktfIiBQXM0Thew4qJELNzQaM6RBqnMqM/R0AQlAhXN";
@@ -95,4 +993,307 @@ mod tests {
             mutate_transaction_slippage_bps(&mut transaction, 12345).unwrap()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decode_route_instruction() {
+        let base64_tx = "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAQAGCmb9xdrDtJYk7SvJmju4CpS8tgk++rcm6zvJ55YhNnkyFyMa9+i/QdXyfkMKzum7vNcYFEYFPWEHOkn7ubmPMy8uy3ly9YjP0u4bWlq58MCtylAkiN9u7LB/14O1R2UKGEtLpKDA2nb16o7DnkNeYpajr8pWfkX5+cYZej/F5CTJAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAADBkZv5SEXMv/srbpyw5vnvIzlu8X3EmssQ5s6QAAAAAR51VvyMcBu7nTFbs5oFQf9sbLeo/SOUQKxzaJWvBOPBt324ddloZPZy+FGzut5rBy0he1fWzeROoz1hX7/AKmMlyWPTiSJ8bs9ECkUjg2DC1oTmdr/EIQEjnvY2+n4WbQ/+if11/ZKdMCbHylYed5LCas238ndUUsyGqezjOXo4AY1NdAvbDuSSJJNK0yR9lJs7g4BkENiJvgeZ7c1JKcHBQAFAm5dAgAFAAkDBgAAAAAAAAAIBgACABEEBwEBBAIAAgwCAAAAAOH1BQAAAAAHAQIBEQYdBwACAwYTBgkGEA4QCwoCAxETDxAABwcSEA0MAQYj5RfLl3rjrSoBAAAAJmQAAQDh9QUAAAAA53bhAAAAAAAsAQAHAwIAAAEJAbaRFM1U56as5v3jHnktfIiBQXM0Thew4qJELNzQaM6RBqnMqM/R0AQlAhXN";
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(base64_tx).unwrap()).unwrap();
+        let route = transaction
+            .message
+            .instructions()
+            .iter()
+            .find_map(|instruction| decode_route_instruction(&instruction.data))
+            .unwrap();
+
+        assert_eq!(
+            route,
+            SolanaInstruction::JupiterRoute {
+                name: "route".to_string(),
+                route_plan: Some(vec![JupiterRoutePlanStep {
+                    swap: "MeteoraDlmm".to_string(),
+                    percent: 100,
+                    input_index: 0,
+                    output_index: 1,
+                }]),
+                in_amount: Some(100_000_000),
+                quoted_in_amount: None,
+                out_amount: None,
+                quoted_out_amount: Some(14_776_039),
+                slippage_bps: 300,
+                platform_fee_bps: 0,
+            }
+        );
+
+        // Shared accounts exact out route splitting between Whirlpool and Phoenix
+        let mut data = SHARED_ACCOUNTS_EXACT_OUT_DISCRIMINATOR.to_vec();
+        data.push(3); // id
+        data.extend(2u32.to_le_bytes());
+        data.extend([17, 1, 60, 0, 1]); // Whirlpool { a_to_b: true }
+        data.extend([28, 0, 40, 0, 1]); // Phoenix { side: Bid }
+        data.extend(5_000u64.to_le_bytes());
+        data.extend(1_234u64.to_le_bytes());
+        data.extend(50u16.to_le_bytes());
+        data.push(20);
+        let route_plan = vec![
+            JupiterRoutePlanStep {
+                swap: "Whirlpool".to_string(),
+                percent: 60,
+                input_index: 0,
+                output_index: 1,
+            },
+            JupiterRoutePlanStep {
+                swap: "Phoenix".to_string(),
+                percent: 40,
+                input_index: 0,
+                output_index: 1,
+            },
+        ];
+        let route = SolanaInstruction::JupiterRoute {
+            name: "shared_accounts_exact_out_route".to_string(),
+            route_plan: Some(route_plan),
+            in_amount: None,
+            quoted_in_amount: Some(1_234),
+            out_amount: Some(5_000),
+            quoted_out_amount: None,
+            slippage_bps: 50,
+            platform_fee_bps: 20,
+        };
+        assert_eq!(decode_route_instruction(&data), Some(route.clone()));
+
+        // An unknown AMM still leaves the amounts
+        data[8 + 1 + 4] = 250;
+        let SolanaInstruction::JupiterRoute { route_plan, .. } =
+            decode_route_instruction(&data).unwrap()
+        else {
+            unreachable!()
+        };
+        assert_eq!(route_plan, None);
+    }
+
+    #[test]
+    fn test_decode_swap_events() {
+        let user = Pubkey::new_unique();
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (whirlpool, dlmm) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let event = |amm: &Pubkey, input_amount: u64, output_amount: u64| {
+            let mut data = SWAP_EVENT_DISCRIMINATOR.to_vec();
+            data.extend(amm.to_bytes());
+            data.extend(sol.to_bytes());
+            data.extend(input_amount.to_le_bytes());
+            data.extend(usdc.to_bytes());
+            data.extend(output_amount.to_le_bytes());
+            data
+        };
+        let cpi = |data: Vec<u8>| bs58::encode([EVENT_IX_TAG, &data].concat()).into_string();
+        let fill = JupiterSwapFill {
+            legs: vec![
+                JupiterSwapEvent {
+                    amm: whirlpool.to_string(),
+                    input_mint: sol.to_string(),
+                    input_amount: 600,
+                    output_mint: usdc.to_string(),
+                    output_amount: 90,
+                },
+                JupiterSwapEvent {
+                    amm: dlmm.to_string(),
+                    input_mint: sol.to_string(),
+                    input_amount: 400,
+                    output_mint: usdc.to_string(),
+                    output_amount: 61,
+                },
+            ],
+            input_mint: sol.to_string(),
+            input_amount: 1_000,
+            output_mint: usdc.to_string(),
+            output_amount: 151,
+        };
+
+        // `json` encoded, with Jupiter loaded from a lookup table
+        let meta = format!(
+            r#"{{
+                "err": null,
+                "fee": 5000,
+                "innerInstructions": [{{
+                    "index": 2,
+                    "instructions": [
+                        {{ "programIdIndex": 2, "accounts": [], "data": "{}", "stackHeight": 2 }},
+                        {{ "programIdIndex": 1, "accounts": [], "data": "{}", "stackHeight": 2 }},
+                        {{ "programIdIndex": 1, "accounts": [], "data": "{}", "stackHeight": 2 }}
+                    ]
+                }}],
+                "loadedAddresses": {{ "writable": [], "readonly": ["{JUPITER_V6_PROGRAM_ID}", "{}"] }},
+                "logMessages": []
+            }}"#,
+            cpi(event(&Pubkey::new_unique(), 1, 1)),
+            cpi(event(&whirlpool, 600, 90)),
+            cpi(event(&dlmm, 400, 61)),
+            Pubkey::new_unique(),
+        );
+        assert_eq!(decode_swap_events(&meta, &[user]).unwrap(), Some(fill.clone()));
+
+        // Logged events of older routes, only the ones logged by Jupiter itself count
+        let meta = format!(
+            r#"{{
+                "innerInstructions": [],
+                "logMessages": [
+                    "Program {JUPITER_V6_PROGRAM_ID} invoke [1]",
+                    "Program log: Instruction: Route",
+                    "Program {whirlpool} invoke [2]",
+                    "Program data: {}",
+                    "Program {whirlpool} success",
+                    "Program data: {}",
+                    "Program data: {}",
+                    "Program {JUPITER_V6_PROGRAM_ID} success"
+                ]
+            }}"#,
+            to_base64(event(&whirlpool, 1, 1)),
+            to_base64(event(&whirlpool, 600, 90)),
+            to_base64(event(&dlmm, 400, 61)),
+        );
+        assert_eq!(decode_swap_events(&meta, &[user]).unwrap(), Some(fill));
+
+        let meta = r#"{ "innerInstructions": null, "logMessages": null }"#;
+        assert_eq!(decode_swap_events(meta, &[user]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_jupiter_orders() {
+        let maker = Pubkey::new_unique();
+        let (usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let key = |key: &Pubkey| ChainPublicKey::new(key.to_string(), Blockchain::Solana);
+        let amount = |value: &str| DecimalNumber {
+            value: value.to_string(),
+        };
+        let decode = |instruction: &Instruction| {
+            decode_order_instruction(&instruction.program_id, &instruction.data, |position| {
+                Some(instruction.accounts.get(position)?.pubkey.to_string())
+            })
+        };
+
+        let (instructions, payer) =
+            jupiter_order_instructions(&JupiterOrderAction::CreateLimitOrder {
+                maker: key(&maker),
+                input_mint: key(&usdc),
+                output_mint: key(&bonk),
+                making_amount: amount("100000000"),
+                taking_amount: amount("5000000000000"),
+                expired_at: Some(1_800_000_000),
+                unique_id: 7,
+                input_token_program: None,
+                output_token_program: None,
+            })
+            .unwrap();
+        assert_eq!(payer, maker);
+        let order = limit_order_address(&maker, 7);
+        assert_eq!(
+            decode(&instructions[0]),
+            Some(SolanaInstruction::JupiterLimitOrderCreate {
+                maker: maker.to_string(),
+                order: order.to_string(),
+                input_mint: usdc.to_string(),
+                output_mint: bonk.to_string(),
+                making_amount: 100_000_000,
+                taking_amount: 5_000_000_000_000,
+                expired_at: Some(1_800_000_000),
+            })
+        );
+
+        let (instructions, _) = jupiter_order_instructions(&JupiterOrderAction::OpenDca {
+            user: key(&maker),
+            input_mint: key(&usdc),
+            output_mint: key(&bonk),
+            in_amount: amount("1000"),
+            in_amount_per_cycle: amount("100"),
+            cycle_frequency: 3_600,
+            min_out_amount: None,
+            max_out_amount: Some(amount("20")),
+            start_at: None,
+            application_idx: 0,
+            input_token_program: None,
+            output_token_program: Some(TOKEN_2022_PROGRAM.to_string()),
+        })
+        .unwrap();
+        let dca = dca_address(&maker, &usdc, &bonk, 0);
+        let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+        let accounts = &instructions[0].accounts;
+        assert_eq!(
+            accounts[5].pubkey,
+            associated_token_address_2022(&maker, &spl_token::id(), &usdc)
+        );
+        assert_eq!(
+            accounts[7].pubkey,
+            associated_token_address_2022(&dca, &token_2022_program, &bonk)
+        );
+        assert_eq!(accounts[9].pubkey, spl_token::id());
+        assert_eq!(
+            decode(&instructions[0]),
+            Some(SolanaInstruction::JupiterDcaOpen {
+                user: maker.to_string(),
+                dca: dca.to_string(),
+                input_mint: usdc.to_string(),
+                output_mint: bonk.to_string(),
+                in_amount: 1_000,
+                in_amount_per_cycle: 100,
+                cycle_frequency: 3_600,
+                min_out_amount: None,
+                max_out_amount: Some(20),
+                start_at: None,
+            })
+        );
+
+        let mut data = ORDER_ACCOUNT_DISCRIMINATOR.to_vec();
+        for key in [
+            maker,
+            usdc,
+            bonk,
+            spl_token::id(),
+            spl_token::id(),
+            Pubkey::new_unique(),
+        ] {
+            data.extend(key.to_bytes());
+        }
+        for value in [
+            7u64,
+            100_000_000,
+            5_000_000_000_000,
+            40_000_000,
+            2_000_000_000_000,
+            0,
+        ] {
+            data.extend(value.to_le_bytes());
+        }
+        data.push(0);
+        data.extend(0u16.to_le_bytes());
+        data.extend(Pubkey::new_unique().to_bytes());
+        data.extend(1_700_000_000i64.to_le_bytes());
+        data.extend(1_700_000_100i64.to_le_bytes());
+        data.push(255);
+        let account = JupiterOrderAccount {
+            address: order.to_string(),
+            data: to_base64(&data),
+        };
+        assert_eq!(
+            parse_order_account(&account).unwrap(),
+            JupiterOrder::LimitOrder {
+                address: order.to_string(),
+                maker: maker.to_string(),
+                input_mint: usdc.to_string(),
+                output_mint: bonk.to_string(),
+                unique_id: 7,
+                ori_making_amount: 100_000_000,
+                ori_taking_amount: 5_000_000_000_000,
+                making_amount: 40_000_000,
+                taking_amount: 2_000_000_000_000,
+                expired_at: None,
+                created_at: 1_700_000_000,
+            }
+        );
+
+        let account = JupiterOrderAccount {
+            address: order.to_string(),
+            data: to_base64(&data[..100]),
+        };
+        assert!(parse_order_account(&account).is_err());
+    }
+}
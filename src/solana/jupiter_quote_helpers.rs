@@ -0,0 +1,415 @@
+//! Checks a Jupiter swap transaction against the quote it was requested for
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer};
+use solana_sdk::{message::VersionedMessage, pubkey::Pubkey};
+
+use super::jupiter_helpers::{decode_route_instruction, route_accounts, JUPITER_V6_PROGRAM_ID};
+use super::types::{JupiterSwapField, JupiterSwapMismatch, SolanaInstruction};
+use super::{associated_token_address_2022, TOKEN_2022_PROGRAM};
+use crate::errors::*;
+
+/// The parts of a response of the Jupiter quote API a swap transaction has to match
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JupiterQuote {
+    pub input_mint: String,
+    #[serde(deserialize_with = "amount")]
+    pub in_amount: u64,
+    pub output_mint: String,
+    #[serde(deserialize_with = "amount")]
+    pub out_amount: u64,
+    /// Minimum output of exact in swaps, maximum input of exact out swaps
+    #[serde(deserialize_with = "amount")]
+    pub other_amount_threshold: u64,
+    pub swap_mode: JupiterSwapMode,
+    pub slippage_bps: u16,
+    pub platform_fee: Option<JupiterPlatformFee>,
+    pub route_plan: Vec<JupiterQuoteStep>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub(crate) enum JupiterSwapMode {
+    ExactIn,
+    ExactOut,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JupiterPlatformFee {
+    pub fee_bps: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JupiterQuoteStep {
+    pub swap_info: JupiterSwapInfo,
+    pub percent: u8,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JupiterSwapInfo {
+    pub input_mint: String,
+    pub output_mint: String,
+}
+
+/// The quote API returns amounts as strings
+fn amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(serde::de::Error::custom)
+}
+
+impl JupiterQuote {
+    pub fn from_json(quote: &str) -> Result<Self, TransactionError> {
+        serde_json::from_str(quote).map_err(TransactionError::parsing_failure)
+    }
+}
+
+/// Compares the single Jupiter route instruction of `message` with `quote`. The swap
+/// has to spend from and pay into associated token accounts of `user`, and the platform
+/// fee has to go to `fee_account` if one is given. `account_keys` are the resolved keys
+/// of the message. Returns every field that doesn't match, so an empty list means the
+/// transaction does what the quote promised. Token ledger routes swap what the ledger
+/// recorded earlier in the transaction, so their input amount is always reported.
+pub(crate) fn validate_jupiter_swap(
+    message: &VersionedMessage,
+    account_keys: &[Pubkey],
+    quote: &JupiterQuote,
+    user: &Pubkey,
+    fee_account: Option<&Pubkey>,
+) -> Result<Vec<JupiterSwapMismatch>, TransactionError> {
+    let mut routes = message.instructions().iter().filter_map(|instruction| {
+        let program = account_keys.get(usize::from(instruction.program_id_index))?;
+        if *program != JUPITER_V6_PROGRAM_ID {
+            return None;
+        }
+        Some((instruction, decode_route_instruction(&instruction.data)?))
+    });
+    let Some((compiled_instruction, route)) = routes.next() else {
+        return Err(TransactionError::parameters(
+            "The transaction has no Jupiter route instruction",
+        ));
+    };
+    if routes.next().is_some() {
+        return Err(TransactionError::parameters(
+            "The transaction has more than one Jupiter route instruction",
+        ));
+    }
+    let SolanaInstruction::JupiterRoute {
+        name,
+        route_plan,
+        in_amount,
+        quoted_in_amount,
+        out_amount,
+        quoted_out_amount,
+        slippage_bps,
+        platform_fee_bps,
+    } = route
+    else {
+        unreachable!("decode_route_instruction only returns routes");
+    };
+    let positions = route_accounts(&name)
+        .ok_or_else(|| TransactionError::parameters(format!("Unknown route {name}")))?;
+    let key = |position: usize| -> Result<Pubkey, TransactionError> {
+        compiled_instruction
+            .accounts
+            .get(position)
+            .and_then(|index| account_keys.get(usize::from(*index)))
+            .copied()
+            .ok_or_else(|| {
+                TransactionError::parameters(
+                    "The accounts of the route are missing, are the address lookup tables given?",
+                )
+            })
+    };
+
+    let input_mint = Pubkey::from_str(&quote.input_mint).map_err(TransactionError::public_key)?;
+    let output_mint = Pubkey::from_str(&quote.output_mint).map_err(TransactionError::public_key)?;
+
+    let mut mismatches = Vec::new();
+    let mut check = |field: JupiterSwapField, expected: String, actual: Option<String>| {
+        if actual.as_ref() != Some(&expected) {
+            mismatches.push(JupiterSwapMismatch {
+                field,
+                expected,
+                actual,
+            });
+        }
+    };
+
+    let exact_out = out_amount.is_some();
+    let swap_mode = if exact_out {
+        JupiterSwapMode::ExactOut
+    } else {
+        JupiterSwapMode::ExactIn
+    };
+    check(
+        JupiterSwapField::SwapMode,
+        format!("{:?}", quote.swap_mode),
+        Some(format!("{swap_mode:?}")),
+    );
+    // Exact in routes carry the input and the quoted output, exact out routes the other
+    // way around. Token ledger routes read their input from the ledger, which earlier
+    // instructions can inflate, so it's reported as missing.
+    let (amount, quoted_amount) = match quote.swap_mode {
+        JupiterSwapMode::ExactIn => (
+            (quote.in_amount, in_amount),
+            (quote.out_amount, quoted_out_amount),
+        ),
+        JupiterSwapMode::ExactOut => (
+            (quote.out_amount, out_amount),
+            (quote.in_amount, quoted_in_amount),
+        ),
+    };
+    check(
+        JupiterSwapField::Amount,
+        amount.0.to_string(),
+        amount.1.map(|amount| amount.to_string()),
+    );
+    check(
+        JupiterSwapField::QuotedAmount,
+        quoted_amount.0.to_string(),
+        quoted_amount.1.map(|amount| amount.to_string()),
+    );
+    check(
+        JupiterSwapField::SlippageBps,
+        quote.slippage_bps.to_string(),
+        Some(slippage_bps.to_string()),
+    );
+    let fee_bps = quote
+        .platform_fee
+        .as_ref()
+        .map_or(0, |platform_fee| platform_fee.fee_bps);
+    check(
+        JupiterSwapField::PlatformFeeBps,
+        fee_bps.to_string(),
+        Some(platform_fee_bps.to_string()),
+    );
+
+    if let Some(source_mint) = positions.source_mint {
+        check(
+            JupiterSwapField::InputMint,
+            input_mint.to_string(),
+            Some(key(source_mint)?.to_string()),
+        );
+    }
+    check(
+        JupiterSwapField::OutputMint,
+        output_mint.to_string(),
+        Some(key(positions.destination_mint)?.to_string()),
+    );
+    check(
+        JupiterSwapField::UserTransferAuthority,
+        user.to_string(),
+        Some(key(positions.user_transfer_authority)?.to_string()),
+    );
+
+    // Routes without a source mint only reveal the input mint through the source account
+    let source = key(positions.source_token_account)?;
+    check_token_account(
+        &mut check,
+        JupiterSwapField::SourceTokenAccount,
+        user,
+        &input_mint,
+        &source,
+    );
+    let destination = match positions.optional_destination_token_account {
+        Some(position) => Some(key(position)?).filter(|account| *account != JUPITER_V6_PROGRAM_ID),
+        None => None,
+    };
+    let destination = match destination {
+        Some(destination) => destination,
+        None => key(positions.destination_token_account)?,
+    };
+    check_token_account(
+        &mut check,
+        JupiterSwapField::DestinationTokenAccount,
+        user,
+        &output_mint,
+        &destination,
+    );
+
+    if let Some(fee_account) = fee_account {
+        check(
+            JupiterSwapField::FeeAccount,
+            fee_account.to_string(),
+            Some(key(positions.platform_fee_account)?.to_string()),
+        );
+    }
+
+    // The quote labels its AMMs differently than the IDL, so only the split is compared
+    if let Some(route_plan) = route_plan {
+        let percents = |percents: Vec<u8>| {
+            percents
+                .iter()
+                .map(u8::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        check(
+            JupiterSwapField::RoutePlan,
+            percents(quote.route_plan.iter().map(|step| step.percent).collect()),
+            Some(percents(
+                route_plan.iter().map(|step| step.percent).collect(),
+            )),
+        );
+    }
+
+    Ok(mismatches)
+}
+
+/// Checks that `account` is the associated token account of `user` for `mint`, under
+/// either token program
+fn check_token_account(
+    check: &mut impl FnMut(JupiterSwapField, String, Option<String>),
+    field: JupiterSwapField,
+    user: &Pubkey,
+    mint: &Pubkey,
+    account: &Pubkey,
+) {
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+    let expected = associated_token_address_2022(user, &spl_token::id(), mint);
+    let expected_2022 = associated_token_address_2022(user, &token_2022_program, mint);
+    if *account == expected_2022 {
+        return;
+    }
+    check(field, expected.to_string(), Some(account.to_string()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::jupiter_helpers::{
+        ROUTE_DISCRIMINATOR, ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR,
+    };
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::Message;
+
+    #[test]
+    fn test_validate_jupiter_swap() {
+        let user = Pubkey::new_unique();
+        let fee_account = Pubkey::new_unique();
+        let input_mint = Pubkey::new_unique();
+        let output_mint = Pubkey::new_unique();
+        let source = associated_token_address_2022(&user, &spl_token::id(), &input_mint);
+        let destination = associated_token_address_2022(&user, &spl_token::id(), &output_mint);
+
+        // `route` with a single Meteora DLMM step, swapping 1000 for a quoted 500
+        let mut data = ROUTE_DISCRIMINATOR.to_vec();
+        data.extend(1u32.to_le_bytes());
+        data.extend([38, 100, 0, 1]);
+        data.extend(1_000u64.to_le_bytes());
+        data.extend(500u64.to_le_bytes());
+        data.extend(50u16.to_le_bytes());
+        data.push(20);
+        let route = |destination: Pubkey| Instruction {
+            program_id: JUPITER_V6_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(user, true),
+                AccountMeta::new(source, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(JUPITER_V6_PROGRAM_ID, false),
+                AccountMeta::new_readonly(output_mint, false),
+                AccountMeta::new(fee_account, false),
+            ],
+            data: data.clone(),
+        };
+        let message = |destination: Pubkey| {
+            VersionedMessage::Legacy(Message::new(&[route(destination)], Some(&user)))
+        };
+
+        let quote = JupiterQuote::from_json(&format!(
+            r#"{{
+                "inputMint": "{input_mint}",
+                "inAmount": "1000",
+                "outputMint": "{output_mint}",
+                "outAmount": "500",
+                "otherAmountThreshold": "497",
+                "swapMode": "ExactIn",
+                "slippageBps": 50,
+                "platformFee": {{ "amount": "1", "feeBps": 20 }},
+                "priceImpactPct": "0",
+                "routePlan": [{{
+                    "swapInfo": {{
+                        "ammKey": "{}",
+                        "label": "Meteora DLMM",
+                        "inputMint": "{input_mint}",
+                        "outputMint": "{output_mint}"
+                    }},
+                    "percent": 100
+                }}],
+                "contextSlot": 1,
+                "timeTaken": 0.01
+            }}"#,
+            Pubkey::new_unique()
+        ))
+        .unwrap();
+
+        let validate = |message: &VersionedMessage, quote: &JupiterQuote| {
+            validate_jupiter_swap(
+                message,
+                message.static_account_keys(),
+                quote,
+                &user,
+                Some(&fee_account),
+            )
+            .unwrap()
+        };
+        assert_eq!(validate(&message(destination), &quote), Vec::new());
+
+        let attacker = Pubkey::new_unique();
+        let mut tampered = quote.clone();
+        tampered.in_amount = 900;
+        tampered.slippage_bps = 100;
+        assert_eq!(
+            validate(&message(attacker), &tampered),
+            vec![
+                JupiterSwapMismatch {
+                    field: JupiterSwapField::Amount,
+                    expected: "900".to_string(),
+                    actual: Some("1000".to_string()),
+                },
+                JupiterSwapMismatch {
+                    field: JupiterSwapField::SlippageBps,
+                    expected: "100".to_string(),
+                    actual: Some("50".to_string()),
+                },
+                JupiterSwapMismatch {
+                    field: JupiterSwapField::DestinationTokenAccount,
+                    expected: destination.to_string(),
+                    actual: Some(attacker.to_string()),
+                },
+            ]
+        );
+
+        // `route_with_token_ledger` shares the accounts of `route` but carries no input
+        // amount, which can't be confirmed then
+        let mut ledger_data = ROUTE_WITH_TOKEN_LEDGER_DISCRIMINATOR.to_vec();
+        ledger_data.extend(1u32.to_le_bytes());
+        ledger_data.extend([38, 100, 0, 1]);
+        ledger_data.extend(500u64.to_le_bytes());
+        ledger_data.extend(50u16.to_le_bytes());
+        ledger_data.push(20);
+        let mut ledger_route = route(destination);
+        ledger_route.data = ledger_data;
+        let ledger_message = VersionedMessage::Legacy(Message::new(&[ledger_route], Some(&user)));
+        assert_eq!(
+            validate(&ledger_message, &quote),
+            vec![JupiterSwapMismatch {
+                field: JupiterSwapField::Amount,
+                expected: "1000".to_string(),
+                actual: None,
+            }]
+        );
+
+        let empty = VersionedMessage::Legacy(Message::new(&[], Some(&user)));
+        let Err(TransactionError::Parameters(_)) =
+            validate_jupiter_swap(&empty, empty.static_account_keys(), &quote, &user, None)
+        else {
+            panic!("A transaction without a route should fail");
+        };
+    }
+}
@@ -0,0 +1,200 @@
+//! Jupiter referral program, which holds the platform fees integrators collect
+//! on swaps in one token account per mint
+use std::str::FromStr;
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use super::associated_token_address_2022;
+use super::types::JupiterReferralAction;
+use crate::errors::*;
+
+pub(crate) const REFERRAL_PROGRAM_ID: Pubkey =
+    pubkey!("REFER4ZgmyYx9c6He5XfaTMiGfdLwRnkV4RPp9t9iF3");
+/// The project of Jupiter's own referral accounts
+pub(crate) const JUPITER_PROJECT: Pubkey = pubkey!("45ruCyfdRkWpRNGEqWzjCiXRHkZs8WXCLQ67Pnpye7Hp");
+const INITIALIZE_REFERRAL_TOKEN_ACCOUNT_DISCRIMINATOR: &[u8] =
+    &[125, 18, 70, 95, 86, 179, 221, 190];
+const CLAIM_DISCRIMINATOR: &[u8] = &[62, 198, 214, 193, 213, 159, 108, 210];
+
+/// The referral account a partner created under `project` with `name`
+pub(crate) fn referral_account_address(project: &Pubkey, name: &str) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"referral", project.as_ref(), name.as_bytes()],
+        &REFERRAL_PROGRAM_ID,
+    )
+    .0
+}
+
+/// The account collecting the fees in `mint` of `referral_account`, which is the
+/// platform fee account of swaps charging their fee in `mint`
+pub(crate) fn referral_token_account_address(referral_account: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"referral_ata", referral_account.as_ref(), mint.as_ref()],
+        &REFERRAL_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Returns the instructions of `action` and the account paying for them
+pub(crate) fn referral_instructions(
+    action: &JupiterReferralAction,
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    let pubkey = |key: &str| Pubkey::from_str(key).map_err(TransactionError::public_key);
+    let token_program = |program: &Option<String>| match program {
+        Some(program) => pubkey(program),
+        None => Ok(spl_token::id()),
+    };
+    let project = |project: &Option<String>| match project {
+        Some(project) => pubkey(project),
+        None => Ok(JUPITER_PROJECT),
+    };
+
+    match action {
+        JupiterReferralAction::InitializeReferralTokenAccount {
+            payer,
+            referral_account,
+            mint,
+            project: referral_project,
+            token_program: mint_token_program,
+        } => {
+            let payer = payer.to_solana_pubkey()?;
+            let referral_account = referral_account.to_solana_pubkey()?;
+            let mint = mint.to_solana_pubkey()?;
+
+            let instruction = Instruction {
+                program_id: REFERRAL_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(project(referral_project)?, false),
+                    AccountMeta::new_readonly(referral_account, false),
+                    AccountMeta::new(
+                        referral_token_account_address(&referral_account, &mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(token_program(mint_token_program)?, false),
+                ],
+                data: INITIALIZE_REFERRAL_TOKEN_ACCOUNT_DISCRIMINATOR.to_vec(),
+            };
+            Ok((vec![instruction], payer))
+        }
+        JupiterReferralAction::Claim {
+            payer,
+            referral_account,
+            partner,
+            mint,
+            project: referral_project,
+            project_admin,
+            token_program: mint_token_program,
+        } => {
+            let payer = payer.to_solana_pubkey()?;
+            let referral_account = referral_account.to_solana_pubkey()?;
+            let partner = partner.to_solana_pubkey()?;
+            let mint = mint.to_solana_pubkey()?;
+            let project_admin = project_admin.to_solana_pubkey()?;
+            let token_program = token_program(mint_token_program)?;
+
+            // The project takes its share of the fees while the partner claims them
+            let instruction = Instruction {
+                program_id: REFERRAL_PROGRAM_ID,
+                accounts: vec![
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(project(referral_project)?, false),
+                    AccountMeta::new_readonly(project_admin, false),
+                    AccountMeta::new(
+                        associated_token_address_2022(&project_admin, &token_program, &mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(referral_account, false),
+                    AccountMeta::new(
+                        referral_token_account_address(&referral_account, &mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(partner, false),
+                    AccountMeta::new(
+                        associated_token_address_2022(&partner, &token_program, &mint),
+                        false,
+                    ),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(token_program, false),
+                ],
+                data: CLAIM_DISCRIMINATOR.to_vec(),
+            };
+            Ok((vec![instruction], payer))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Blockchain, ChainPublicKey};
+
+    #[test]
+    fn test_referral_instructions() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let key = |key: &Pubkey| ChainPublicKey::new(key.to_string(), Blockchain::Solana);
+        let referral_account = referral_account_address(&JUPITER_PROJECT, "wallet");
+        let referral_token_account = referral_token_account_address(&referral_account, &mint);
+        assert_ne!(
+            referral_token_account,
+            referral_token_account_address(&referral_account, &Pubkey::new_unique())
+        );
+
+        let (instructions, fee_payer) =
+            referral_instructions(&JupiterReferralAction::InitializeReferralTokenAccount {
+                payer: key(&payer),
+                referral_account: key(&referral_account),
+                mint: key(&mint),
+                project: None,
+                token_program: None,
+            })
+            .unwrap();
+        assert_eq!(fee_payer, payer);
+        let accounts: Vec<_> = instructions[0]
+            .accounts
+            .iter()
+            .map(|account| account.pubkey)
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                payer,
+                JUPITER_PROJECT,
+                referral_account,
+                referral_token_account,
+                mint,
+                system_program::id(),
+                spl_token::id(),
+            ]
+        );
+        assert!(instructions[0].accounts[3].is_writable);
+
+        let partner = Pubkey::new_unique();
+        let (instructions, _) = referral_instructions(&JupiterReferralAction::Claim {
+            payer: key(&payer),
+            referral_account: key(&referral_account),
+            partner: key(&partner),
+            mint: key(&mint),
+            project: None,
+            project_admin: key(&Pubkey::new_unique()),
+            token_program: None,
+        })
+        .unwrap();
+        assert_eq!(instructions[0].data, CLAIM_DISCRIMINATOR);
+        assert_eq!(instructions[0].accounts[5].pubkey, referral_token_account);
+        assert_eq!(
+            instructions[0].accounts[7].pubkey,
+            associated_token_address_2022(&partner, &spl_token::id(), &mint)
+        );
+    }
+}
@@ -0,0 +1,560 @@
+//! Builds the instructions of a Jupiter v6 swap from a quote, without the swap API
+use std::str::FromStr;
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_instruction,
+};
+use spl_memo::build_memo;
+
+use super::jupiter_helpers::{
+    swap_name, EXACT_OUT_ROUTE_DISCRIMINATOR, JUPITER_V6_PROGRAM_ID, ROUTE_DISCRIMINATOR,
+};
+use super::jupiter_quote_helpers::{JupiterQuote, JupiterSwapMode};
+use super::jupiter_referral_helpers::referral_token_account_address;
+use super::types::{JupiterSwapLeg, JupiterSwapOptions};
+use super::{
+    associated_token_address_2022, close_token_account, compute_budget_instructions,
+    create_associated_token_account_idempotent_2022, TOKEN_2022_PROGRAM,
+};
+use crate::errors::*;
+use crate::types::TransactionParameters;
+use crate::utils::from_base64;
+
+/// Returns the compute budget, setup, swap and cleanup instructions swapping what
+/// `quote` quoted for `user`, with one leg for every step of its route plan.
+///
+/// Setup creates the output token account and the token accounts of the mints multi-hop
+/// routes pass through, and wraps the SOL the swap may spend. Cleanup closes the wrapped
+/// SOL account again, which unwraps the output of swaps to SOL.
+pub(crate) fn jupiter_swap_instructions(
+    quote: &JupiterQuote,
+    user: &Pubkey,
+    legs: &[JupiterSwapLeg],
+    options: &JupiterSwapOptions,
+    parameters: &TransactionParameters,
+) -> Result<Vec<Instruction>, TransactionError> {
+    let pubkey = |key: &str| Pubkey::from_str(key).map_err(TransactionError::public_key);
+    let token_program = |program: &Option<String>| match program {
+        Some(program) => pubkey(program),
+        None => Ok(spl_token::id()),
+    };
+    let input_mint = pubkey(&quote.input_mint)?;
+    let output_mint = pubkey(&quote.output_mint)?;
+    let input_token_program = token_program(&options.input_token_program)?;
+    let output_token_program = token_program(&options.output_token_program)?;
+    let token_2022_program = pubkey(TOKEN_2022_PROGRAM)?;
+    let destination_token_account = options
+        .destination_token_account
+        .as_deref()
+        .map(pubkey)
+        .transpose()?;
+    // Exact in swaps charge their fee in the output mint, exact out swaps in the input mint
+    let fee_account = match (&options.fee_account, &options.referral_account) {
+        (Some(_), Some(_)) => {
+            return Err(TransactionError::parameters(
+                "Either a fee account or a referral account can be given",
+            ))
+        }
+        (Some(fee_account), None) => Some(pubkey(fee_account)?),
+        (None, Some(referral_account)) => Some(referral_token_account_address(
+            &pubkey(referral_account)?,
+            match quote.swap_mode {
+                JupiterSwapMode::ExactIn => &output_mint,
+                JupiterSwapMode::ExactOut => &input_mint,
+            },
+        )),
+        (None, None) => None,
+    };
+
+    let platform_fee_bps = quote
+        .platform_fee
+        .as_ref()
+        .map_or(0, |platform_fee| platform_fee.fee_bps);
+    if platform_fee_bps > 0 && fee_account.is_none() {
+        return Err(TransactionError::parameters(
+            "The quote takes a platform fee, but no fee account is given",
+        ));
+    }
+
+    let source = associated_token_address_2022(user, &input_token_program, &input_mint);
+    let destination = associated_token_address_2022(user, &output_token_program, &output_mint);
+    let native_mint = spl_token::native_mint::id();
+    let wrap_sol = options.wrap_and_unwrap_sol && input_mint == native_mint;
+    let unwrap_sol = options.wrap_and_unwrap_sol
+        && output_mint == native_mint
+        && destination_token_account.is_none();
+
+    let mut instructions = compute_budget_instructions(parameters);
+    if wrap_sol {
+        // Exact out swaps spend at most the threshold, the rest stays wrapped until cleanup
+        let lamports = match quote.swap_mode {
+            JupiterSwapMode::ExactIn => quote.in_amount,
+            JupiterSwapMode::ExactOut => quote.other_amount_threshold,
+        };
+        instructions.push(create_associated_token_account_idempotent_2022(
+            user,
+            user,
+            &native_mint,
+            spl_token::id(),
+        ));
+        instructions.push(system_instruction::transfer(user, &source, lamports));
+        instructions.push(
+            spl_token::instruction::sync_native(&spl_token::id(), &source)
+                .map_err(TransactionError::instruction_error)?,
+        );
+    }
+    if destination_token_account.is_none() {
+        instructions.push(create_associated_token_account_idempotent_2022(
+            user,
+            user,
+            &output_mint,
+            output_token_program,
+        ));
+    }
+    // `route` passes every hop through a token account of the user
+    let mut intermediate_mints: Vec<&str> = Vec::new();
+    for step in &quote.route_plan {
+        for mint in [&step.swap_info.input_mint, &step.swap_info.output_mint] {
+            if ![&quote.input_mint, &quote.output_mint].contains(&mint)
+                && !intermediate_mints.contains(&mint.as_str())
+            {
+                intermediate_mints.push(mint);
+            }
+        }
+    }
+    for mint in intermediate_mints {
+        let token_program = if options
+            .intermediate_token_2022_mints
+            .iter()
+            .any(|token_2022_mint| token_2022_mint == mint)
+        {
+            token_2022_program
+        } else {
+            spl_token::id()
+        };
+        instructions.push(create_associated_token_account_idempotent_2022(
+            user,
+            user,
+            &pubkey(mint)?,
+            token_program,
+        ));
+    }
+    if let Some(memo) = parameters.memo() {
+        instructions.push(build_memo(memo.as_bytes(), &[user]));
+    }
+
+    instructions.push(route_instruction(
+        quote,
+        legs,
+        RouteAccounts {
+            token_program: input_token_program,
+            user: *user,
+            source,
+            destination,
+            destination_token_account,
+            source_mint: input_mint,
+            destination_mint: output_mint,
+            platform_fee_account: fee_account,
+            token_2022_program: [input_token_program, output_token_program]
+                .contains(&token_2022_program)
+                .then_some(token_2022_program),
+        },
+        platform_fee_bps,
+    )?);
+
+    if wrap_sol || unwrap_sol {
+        let wrapped_sol = if wrap_sol { source } else { destination };
+        instructions.push(close_token_account(
+            &spl_token::id(),
+            &wrapped_sol,
+            user,
+            user,
+            &[],
+        )?);
+    }
+
+    Ok(instructions)
+}
+
+/// Accounts of a route instruction, `None` for optional accounts that aren't passed
+struct RouteAccounts {
+    token_program: Pubkey,
+    user: Pubkey,
+    source: Pubkey,
+    destination: Pubkey,
+    destination_token_account: Option<Pubkey>,
+    source_mint: Pubkey,
+    destination_mint: Pubkey,
+    platform_fee_account: Option<Pubkey>,
+    token_2022_program: Option<Pubkey>,
+}
+
+/// Encodes `route` for exact in quotes and `exact_out_route` for exact out quotes
+fn route_instruction(
+    quote: &JupiterQuote,
+    legs: &[JupiterSwapLeg],
+    accounts: RouteAccounts,
+    platform_fee_bps: u8,
+) -> Result<Instruction, TransactionError> {
+    if legs.len() != quote.route_plan.len() {
+        return Err(TransactionError::parameters(format!(
+            "The route plan has {} steps, but {} legs are given",
+            quote.route_plan.len(),
+            legs.len()
+        )));
+    }
+
+    // Optional accounts Anchor doesn't get are passed as the program id
+    let optional = |account: Option<Pubkey>| match account {
+        Some(account) => AccountMeta::new(account, false),
+        None => AccountMeta::new_readonly(JUPITER_V6_PROGRAM_ID, false),
+    };
+    let event_authority =
+        Pubkey::find_program_address(&[b"__event_authority"], &JUPITER_V6_PROGRAM_ID).0;
+
+    let exact_out = quote.swap_mode == JupiterSwapMode::ExactOut;
+    let mut data = if exact_out {
+        EXACT_OUT_ROUTE_DISCRIMINATOR.to_vec()
+    } else {
+        ROUTE_DISCRIMINATOR.to_vec()
+    };
+    let mut metas = vec![
+        AccountMeta::new_readonly(accounts.token_program, false),
+        AccountMeta::new_readonly(accounts.user, true),
+        AccountMeta::new(accounts.source, false),
+        AccountMeta::new(accounts.destination, false),
+        optional(accounts.destination_token_account),
+    ];
+    if exact_out {
+        metas.push(AccountMeta::new_readonly(accounts.source_mint, false));
+    }
+    metas.push(AccountMeta::new_readonly(accounts.destination_mint, false));
+    metas.push(optional(accounts.platform_fee_account));
+    if exact_out {
+        metas.push(match accounts.token_2022_program {
+            Some(program) => AccountMeta::new_readonly(program, false),
+            None => optional(None),
+        });
+    }
+    metas.push(AccountMeta::new_readonly(event_authority, false));
+    metas.push(AccountMeta::new_readonly(JUPITER_V6_PROGRAM_ID, false));
+
+    // The steps refer to the mints they swap by their order of appearance in the route
+    let mut mints = vec![quote.input_mint.clone()];
+    let mut index = |mint: &str, insert: bool| -> Result<u8, TransactionError> {
+        let position = match mints.iter().position(|known| *known == mint) {
+            Some(position) => position,
+            None if insert => {
+                mints.push(mint.to_string());
+                mints.len() - 1
+            }
+            None => {
+                return Err(TransactionError::parameters(format!(
+                    "The route plan swaps {mint} before receiving it"
+                )))
+            }
+        };
+        u8::try_from(position).map_err(TransactionError::parsing_failure)
+    };
+
+    data.extend((legs.len() as u32).to_le_bytes());
+    for (step, leg) in quote.route_plan.iter().zip(legs) {
+        let swap = from_base64(&leg.swap).map_err(TransactionError::parsing_failure)?;
+        if swap_name(&swap).is_none() {
+            return Err(TransactionError::parameters(format!(
+                "Unknown swap {}",
+                leg.swap
+            )));
+        }
+        data.extend(swap);
+        data.push(step.percent);
+        data.push(index(&step.swap_info.input_mint, false)?);
+        data.push(index(&step.swap_info.output_mint, true)?);
+
+        for account in &leg.accounts {
+            let key = Pubkey::from_str(&account.address).map_err(TransactionError::public_key)?;
+            metas.push(AccountMeta {
+                pubkey: key,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            });
+        }
+    }
+
+    let (amount, quoted_amount) = if exact_out {
+        (quote.out_amount, quote.in_amount)
+    } else {
+        (quote.in_amount, quote.out_amount)
+    };
+    data.extend(amount.to_le_bytes());
+    data.extend(quoted_amount.to_le_bytes());
+    data.extend(quote.slippage_bps.to_le_bytes());
+    data.push(platform_fee_bps);
+
+    Ok(Instruction {
+        program_id: JUPITER_V6_PROGRAM_ID,
+        accounts: metas,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::jupiter_helpers::decode_route_instruction;
+    use crate::solana::jupiter_quote_helpers::validate_jupiter_swap;
+    use crate::solana::types::{JupiterRoutePlanStep, SolanaInstruction, TransactionAccount};
+    use crate::types::SolanaTransactionType;
+    use crate::utils::to_base64;
+    use solana_sdk::message::{Message, VersionedMessage};
+
+    #[test]
+    fn test_jupiter_swap_instructions() {
+        let user = Pubkey::new_unique();
+        let fee_account = Pubkey::new_unique();
+        let usdc = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let native_mint = spl_token::native_mint::id();
+        let quote = JupiterQuote::from_json(&format!(
+            r#"{{
+                "inputMint": "{native_mint}",
+                "inAmount": "1000000000",
+                "outputMint": "{usdc}",
+                "outAmount": "150000000",
+                "otherAmountThreshold": "149250000",
+                "swapMode": "ExactIn",
+                "slippageBps": 50,
+                "platformFee": {{ "amount": "150000", "feeBps": 10 }},
+                "routePlan": [{{
+                    "swapInfo": {{
+                        "ammKey": "{pool}",
+                        "label": "Meteora DLMM",
+                        "inputMint": "{native_mint}",
+                        "outputMint": "{usdc}"
+                    }},
+                    "percent": 100
+                }}]
+            }}"#
+        ))
+        .unwrap();
+        let legs = [JupiterSwapLeg {
+            // `Swap::MeteoraDlmm`
+            swap: to_base64([38]),
+            accounts: vec![TransactionAccount {
+                address: pool.to_string(),
+                is_signer: false,
+                is_writable: true,
+            }],
+        }];
+        let mut options = JupiterSwapOptions {
+            wrap_and_unwrap_sol: true,
+            destination_token_account: None,
+            fee_account: None,
+            referral_account: None,
+            input_token_program: None,
+            output_token_program: None,
+            intermediate_token_2022_mints: Vec::new(),
+        };
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Versioned,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: Some(10_000),
+            compute_budget_unit_limit: Some(300_000),
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let Err(TransactionError::Parameters(_)) =
+            jupiter_swap_instructions(&quote, &user, &legs, &options, &parameters)
+        else {
+            panic!("A platform fee needs a fee account");
+        };
+        options.fee_account = Some(fee_account.to_string());
+        options.referral_account = Some(Pubkey::new_unique().to_string());
+        let Err(TransactionError::Parameters(_)) =
+            jupiter_swap_instructions(&quote, &user, &legs, &options, &parameters)
+        else {
+            panic!("The fee account is either given or derived");
+        };
+        options.referral_account = None;
+
+        let instructions =
+            jupiter_swap_instructions(&quote, &user, &legs, &options, &parameters).unwrap();
+        let programs: Vec<_> = instructions
+            .iter()
+            .map(|instruction| instruction.program_id)
+            .collect();
+        assert_eq!(
+            programs,
+            vec![
+                solana_sdk::compute_budget::id(),
+                solana_sdk::compute_budget::id(),
+                spl_associated_token_account::id(),
+                solana_sdk::system_program::id(),
+                spl_token::id(),
+                spl_associated_token_account::id(),
+                JUPITER_V6_PROGRAM_ID,
+                spl_token::id(),
+            ]
+        );
+
+        let route = &instructions[6];
+        assert_eq!(route.accounts.last().unwrap().pubkey, pool);
+        assert_eq!(
+            decode_route_instruction(&route.data),
+            Some(SolanaInstruction::JupiterRoute {
+                name: "route".to_string(),
+                route_plan: Some(vec![JupiterRoutePlanStep {
+                    swap: "MeteoraDlmm".to_string(),
+                    percent: 100,
+                    input_index: 0,
+                    output_index: 1,
+                }]),
+                in_amount: Some(1_000_000_000),
+                quoted_in_amount: None,
+                out_amount: None,
+                quoted_out_amount: Some(150_000_000),
+                slippage_bps: 50,
+                platform_fee_bps: 10,
+            })
+        );
+
+        let message = VersionedMessage::Legacy(Message::new(&instructions, Some(&user)));
+        let mismatches = validate_jupiter_swap(
+            &message,
+            message.static_account_keys(),
+            &quote,
+            &user,
+            Some(&fee_account),
+        )
+        .unwrap();
+        assert_eq!(mismatches, Vec::new());
+    }
+
+    #[test]
+    fn test_jupiter_swap_instructions_with_two_legs() {
+        let user = Pubkey::new_unique();
+        let (input_mint, intermediate_mint, output_mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let pools = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let quote = JupiterQuote::from_json(&format!(
+            r#"{{
+                "inputMint": "{input_mint}",
+                "inAmount": "1000",
+                "outputMint": "{output_mint}",
+                "outAmount": "500",
+                "otherAmountThreshold": "497",
+                "swapMode": "ExactIn",
+                "slippageBps": 50,
+                "routePlan": [
+                    {{
+                        "swapInfo": {{
+                            "ammKey": "{}",
+                            "label": "Meteora DLMM",
+                            "inputMint": "{input_mint}",
+                            "outputMint": "{intermediate_mint}"
+                        }},
+                        "percent": 100
+                    }},
+                    {{
+                        "swapInfo": {{
+                            "ammKey": "{}",
+                            "label": "Meteora DLMM",
+                            "inputMint": "{intermediate_mint}",
+                            "outputMint": "{output_mint}"
+                        }},
+                        "percent": 100
+                    }}
+                ]
+            }}"#,
+            pools[0], pools[1]
+        ))
+        .unwrap();
+        let legs: Vec<_> = pools
+            .iter()
+            .map(|pool| JupiterSwapLeg {
+                swap: to_base64([38]),
+                accounts: vec![TransactionAccount {
+                    address: pool.to_string(),
+                    is_signer: false,
+                    is_writable: true,
+                }],
+            })
+            .collect();
+        let options = JupiterSwapOptions {
+            wrap_and_unwrap_sol: false,
+            destination_token_account: None,
+            fee_account: None,
+            referral_account: None,
+            input_token_program: None,
+            output_token_program: None,
+            intermediate_token_2022_mints: vec![intermediate_mint.to_string()],
+        };
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Versioned,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let instructions =
+            jupiter_swap_instructions(&quote, &user, &legs, &options, &parameters).unwrap();
+        let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+        assert_eq!(
+            instructions[..2],
+            [
+                create_associated_token_account_idempotent_2022(
+                    &user,
+                    &user,
+                    &output_mint,
+                    spl_token::id()
+                ),
+                // The user receives and spends the intermediate tokens in between
+                create_associated_token_account_idempotent_2022(
+                    &user,
+                    &user,
+                    &intermediate_mint,
+                    token_2022_program
+                ),
+            ]
+        );
+        assert_eq!(instructions.len(), 3);
+        let Some(SolanaInstruction::JupiterRoute {
+            route_plan: Some(route_plan),
+            ..
+        }) = decode_route_instruction(&instructions[2].data)
+        else {
+            panic!("Expected the route instruction");
+        };
+        let hops: Vec<_> = route_plan
+            .iter()
+            .map(|step| (step.input_index, step.output_index))
+            .collect();
+        assert_eq!(hops, vec![(0, 1), (1, 2)]);
+    }
+}
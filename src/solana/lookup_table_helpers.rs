@@ -0,0 +1,208 @@
+//! Resolution of the accounts a V0 message loads through address lookup tables
+use std::str::FromStr;
+
+use solana_sdk::{
+    address_lookup_table::{state::AddressLookupTable, AddressLookupTableAccount},
+    message::{
+        v0::{LoadedAddresses, LoadedMessage},
+        VersionedMessage,
+    },
+    pubkey::Pubkey,
+};
+
+use super::types::{AddressLookupTableData, TransactionAccount};
+use crate::errors::*;
+use crate::utils::from_base64;
+
+/// All account keys of a message, static keys first, followed by the writable
+/// and then the read-only keys loaded from lookup tables.
+pub(crate) struct ResolvedAccounts {
+    pub keys: Vec<Pubkey>,
+    pub accounts: Vec<TransactionAccount>,
+}
+
+/// Resolves the account keys of `message`. Without any `lookup_tables` only the
+/// static keys are returned; once tables are given, every table the message uses
+/// has to be among them.
+pub(crate) fn resolve_accounts(
+    message: &VersionedMessage,
+    lookup_tables: &[AddressLookupTableData],
+) -> Result<ResolvedAccounts, TransactionError> {
+    match message {
+        VersionedMessage::Legacy(legacy_message) => {
+            let accounts = legacy_message
+                .account_keys
+                .iter()
+                .enumerate()
+                .map(|(index, key)| TransactionAccount {
+                    address: key.to_string(),
+                    is_signer: legacy_message.is_signer(index),
+                    is_writable: legacy_message.is_writable(index),
+                })
+                .collect();
+            Ok(ResolvedAccounts {
+                keys: legacy_message.account_keys.clone(),
+                accounts,
+            })
+        }
+        VersionedMessage::V0(v0_message) => {
+            let loaded_addresses = if lookup_tables.is_empty() {
+                LoadedAddresses::default()
+            } else {
+                load_addresses(message, lookup_tables)?
+            };
+            let loaded_message = LoadedMessage::new_borrowed(v0_message, &loaded_addresses);
+            let keys: Vec<Pubkey> = loaded_message.account_keys().iter().copied().collect();
+            let accounts = keys
+                .iter()
+                .enumerate()
+                .map(|(index, key)| TransactionAccount {
+                    address: key.to_string(),
+                    is_signer: loaded_message.is_signer(index),
+                    is_writable: loaded_message.is_writable(index),
+                })
+                .collect();
+            Ok(ResolvedAccounts { keys, accounts })
+        }
+    }
+}
+
+/// Decodes the raw data of `lookup_tables`, e.g. to compile a V0 message against them
+pub(crate) fn lookup_table_accounts(
+    lookup_tables: &[AddressLookupTableData],
+) -> Result<Vec<AddressLookupTableAccount>, TransactionError> {
+    lookup_tables
+        .iter()
+        .map(|table| {
+            let key = Pubkey::from_str(&table.address).map_err(TransactionError::public_key)?;
+            let data = from_base64(&table.data).map_err(TransactionError::parsing_failure)?;
+            let table_state = AddressLookupTable::deserialize(&data)
+                .map_err(TransactionError::parsing_failure)?;
+            Ok(AddressLookupTableAccount {
+                key,
+                addresses: table_state.addresses.to_vec(),
+            })
+        })
+        .collect()
+}
+
+fn load_addresses(
+    message: &VersionedMessage,
+    lookup_tables: &[AddressLookupTableData],
+) -> Result<LoadedAddresses, TransactionError> {
+    let lookup_tables = lookup_table_accounts(lookup_tables)?;
+    let mut loaded_addresses = LoadedAddresses::default();
+
+    for lookup in message.address_table_lookups().unwrap_or_default() {
+        let table = lookup_tables
+            .iter()
+            .find(|table| table.key == lookup.account_key)
+            .ok_or_else(|| {
+                TransactionError::parameters(format!(
+                    "Missing address lookup table {}",
+                    lookup.account_key
+                ))
+            })?;
+
+        let lookup_address = |index: &u8| {
+            table
+                .addresses
+                .get(usize::from(*index))
+                .copied()
+                .ok_or_else(|| {
+                    TransactionError::parameters(format!(
+                        "Index {index} is out of bounds of address lookup table {}",
+                        table.key
+                    ))
+                })
+        };
+        for index in lookup.writable_indexes.iter() {
+            loaded_addresses.writable.push(lookup_address(index)?);
+        }
+        for index in lookup.readonly_indexes.iter() {
+            loaded_addresses.readonly.push(lookup_address(index)?);
+        }
+    }
+
+    Ok(loaded_addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::to_base64;
+    use solana_sdk::{address_lookup_table::state::LookupTableMeta, hash::Hash, message::v0};
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_resolve_accounts() {
+        let owner = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let table_address = Pubkey::new_unique();
+
+        let instruction = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            &mint,
+            &destination,
+            &owner,
+            &[],
+            1,
+            6,
+        )
+        .unwrap();
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &owner,
+                &[instruction],
+                &[AddressLookupTableAccount {
+                    key: table_address,
+                    addresses: vec![mint, destination],
+                }],
+                Hash::default(),
+            )
+            .unwrap(),
+        );
+        let table_data = AddressLookupTable {
+            meta: LookupTableMeta::default(),
+            addresses: Cow::Owned(vec![mint, destination]),
+        }
+        .serialize_for_tests()
+        .unwrap();
+        let lookup_tables = vec![AddressLookupTableData {
+            address: table_address.to_string(),
+            data: to_base64(table_data),
+        }];
+
+        // Only the static keys are known without the tables
+        let resolved = resolve_accounts(&message, &[]).unwrap();
+        assert_eq!(resolved.keys, message.static_account_keys());
+
+        let resolved = resolve_accounts(&message, &lookup_tables).unwrap();
+        assert_eq!(resolved.keys.len(), message.static_account_keys().len() + 2);
+        assert_eq!(
+            resolved.accounts[resolved.keys.len() - 2..],
+            [
+                TransactionAccount {
+                    address: destination.to_string(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                TransactionAccount {
+                    address: mint.to_string(),
+                    is_signer: false,
+                    is_writable: false,
+                },
+            ]
+        );
+        assert!(resolved.accounts[0].is_signer);
+
+        let other_table = vec![AddressLookupTableData {
+            address: Pubkey::new_unique().to_string(),
+            data: lookup_tables[0].data.clone(),
+        }];
+        assert!(resolve_accounts(&message, &other_table).is_err());
+    }
+}
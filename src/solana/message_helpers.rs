@@ -0,0 +1,210 @@
+//! In-place edits of compiled messages. Used when we have to change a transaction
+//! that somebody else built (e.g. a dApp or the Jupiter API) without recompiling it.
+use solana_sdk::{
+    instruction::{CompiledInstruction, Instruction},
+    message::{MessageHeader, VersionedMessage},
+    pubkey::Pubkey,
+};
+
+use crate::errors::*;
+
+/// Returns the index of `key` in the static account keys, inserting it if required.
+///
+/// New keys are never signers. A writable key is inserted at the end of the writable
+/// non-signer section, a read-only key at the end of the static keys. All compiled
+/// instruction indices behind the insertion point (including the ones pointing into
+/// address lookup tables) are shifted accordingly.
+///
+/// Keys that are only loaded through an address lookup table are not visible here,
+/// so callers should not use this for accounts that might come from a table.
+pub(crate) fn find_or_insert_account_key(
+    message: &mut VersionedMessage,
+    key: &Pubkey,
+    is_writable: bool,
+) -> Result<u8, TransactionError> {
+    let (header, account_keys, instructions) = message_parts_mut(message);
+
+    if let Some(index) = account_keys.iter().position(|k| k == key) {
+        if is_writable && !is_static_key_writable(header, account_keys.len(), index) {
+            return Err(TransactionError::Generic(format!(
+                "{key} is already a read-only account of the transaction"
+            )));
+        }
+        return Ok(index as u8);
+    }
+
+    if account_keys.len() >= usize::from(u8::MAX) {
+        return Err(TransactionError::Generic(
+            "Too many accounts in transaction".to_string(),
+        ));
+    }
+
+    let position = if is_writable {
+        account_keys.len() - usize::from(header.num_readonly_unsigned_accounts)
+    } else {
+        account_keys.len()
+    };
+
+    account_keys.insert(position, *key);
+    if !is_writable {
+        header.num_readonly_unsigned_accounts += 1;
+    }
+
+    for instruction in instructions.iter_mut() {
+        if usize::from(instruction.program_id_index) >= position {
+            instruction.program_id_index += 1;
+        }
+        for account in instruction.accounts.iter_mut() {
+            if usize::from(*account) >= position {
+                *account += 1;
+            }
+        }
+    }
+
+    Ok(position as u8)
+}
+
+/// Compiles `instruction` against the static account keys of `message`, adding the
+/// missing non-signer accounts. Signers have to be part of the message already,
+/// as adding one would require an additional signature.
+pub(crate) fn compile_instruction(
+    message: &mut VersionedMessage,
+    instruction: &Instruction,
+) -> Result<CompiledInstruction, TransactionError> {
+    // Insert everything first, as each insertion can shift the previous indices
+    for meta in instruction.accounts.iter() {
+        if meta.is_signer {
+            let num_signers = usize::from(message.header().num_required_signatures);
+            if !message.static_account_keys()[..num_signers].contains(&meta.pubkey) {
+                return Err(TransactionError::Generic(format!(
+                    "{} is not a signer of the transaction",
+                    meta.pubkey
+                )));
+            }
+        } else {
+            find_or_insert_account_key(message, &meta.pubkey, meta.is_writable)?;
+        }
+    }
+    find_or_insert_account_key(message, &instruction.program_id, false)?;
+
+    let account_keys = message.static_account_keys();
+    let index_of = |key: &Pubkey| -> u8 {
+        account_keys
+            .iter()
+            .position(|k| k == key)
+            .expect("Account key was inserted above") as u8
+    };
+
+    Ok(CompiledInstruction {
+        program_id_index: index_of(&instruction.program_id),
+        accounts: instruction
+            .accounts
+            .iter()
+            .map(|meta| index_of(&meta.pubkey))
+            .collect(),
+        data: instruction.data.clone(),
+    })
+}
+
+/// Compiles `instruction` and appends it to the end of the message
+pub(crate) fn push_instruction(
+    message: &mut VersionedMessage,
+    instruction: &Instruction,
+) -> Result<(), TransactionError> {
+    let compiled_instruction = compile_instruction(message, instruction)?;
+    message_parts_mut(message).2.push(compiled_instruction);
+    Ok(())
+}
+
+/// Appends `keys` as read-only, non-signer accounts to the instruction at `instruction_index`
+pub(crate) fn append_instruction_accounts(
+    message: &mut VersionedMessage,
+    instruction_index: usize,
+    keys: &[Pubkey],
+) -> Result<(), TransactionError> {
+    // Insert everything first, as each insertion can shift the previous indices
+    for key in keys.iter() {
+        find_or_insert_account_key(message, key, false)?;
+    }
+    let indices = keys
+        .iter()
+        .map(|key| find_or_insert_account_key(message, key, false))
+        .collect::<Result<Vec<u8>, TransactionError>>()?;
+
+    let instruction = message_parts_mut(message)
+        .2
+        .get_mut(instruction_index)
+        .ok_or_else(|| TransactionError::Generic("Instruction not found".to_string()))?;
+    instruction.accounts.extend(indices);
+    Ok(())
+}
+
+pub(crate) fn message_parts_mut(
+    message: &mut VersionedMessage,
+) -> (
+    &mut MessageHeader,
+    &mut Vec<Pubkey>,
+    &mut Vec<CompiledInstruction>,
+) {
+    match message {
+        VersionedMessage::Legacy(legacy_message) => (
+            &mut legacy_message.header,
+            &mut legacy_message.account_keys,
+            &mut legacy_message.instructions,
+        ),
+        VersionedMessage::V0(v0_message) => (
+            &mut v0_message.header,
+            &mut v0_message.account_keys,
+            &mut v0_message.instructions,
+        ),
+    }
+}
+
+fn is_static_key_writable(header: &MessageHeader, num_keys: usize, index: usize) -> bool {
+    let num_signers = usize::from(header.num_required_signatures);
+    if index < num_signers {
+        index < num_signers - usize::from(header.num_readonly_signed_accounts)
+    } else {
+        index < num_keys - usize::from(header.num_readonly_unsigned_accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{hash::Hash, message::Message, system_instruction};
+
+    #[test]
+    fn test_insert_account_key_shifts_indices() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let message = Message::new_with_blockhash(
+            &[system_instruction::transfer(&from, &to, 1)],
+            Some(&from),
+            &Hash::default(),
+        );
+        let mut message = VersionedMessage::Legacy(message);
+
+        // `to` is writable, the system program is read-only and last
+        let writable = Pubkey::new_unique();
+        let index = find_or_insert_account_key(&mut message, &writable, true).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(message.static_account_keys()[3], solana_sdk::system_program::id());
+        assert_eq!(message.instructions()[0].program_id_index, 3);
+        assert_eq!(message.instructions()[0].accounts, vec![0, 1]);
+
+        let readonly = Pubkey::new_unique();
+        let index = find_or_insert_account_key(&mut message, &readonly, false).unwrap();
+        assert_eq!(index, 4);
+        assert_eq!(message.header().num_readonly_unsigned_accounts, 2);
+        assert!(message.is_maybe_writable(2));
+        assert!(!message.is_maybe_writable(4));
+
+        // Existing keys are reused
+        assert_eq!(
+            find_or_insert_account_key(&mut message, &readonly, false).unwrap(),
+            4
+        );
+        assert!(find_or_insert_account_key(&mut message, &readonly, true).is_err());
+    }
+}
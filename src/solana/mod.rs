@@ -5,23 +5,28 @@ use rust_decimal::{prelude::FromPrimitive, Decimal, Error};
 use sha2::{Digest, Sha256};
 use solana_program::{instruction::Instruction, native_token::LAMPORTS_PER_SOL};
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction,
     hash,
     instruction::AccountMeta,
-    message::Message,
-    message::VersionedMessage,
+    message::{v0, Message, VersionedMessage},
     pubkey::Pubkey,
     signature::{
         keypair_from_seed, keypair_from_seed_and_derivation_path, Keypair, Signature, Signer,
     },
     signer::SignerError,
-    system_instruction,
+    system_instruction::{self, SystemInstruction},
     transaction::{Transaction, VersionedTransaction},
 };
 use spl_memo::build_memo;
-use spl_token::instruction::TokenInstruction;
+use spl_token::instruction::{AuthorityType, TokenInstruction};
 use std::str::FromStr;
-use types::ExternalAddress;
+use types::{
+    AddressLookupTableData, BalanceChange, DurableNonce, ExternalAddress, JitoTip, JupiterOrder,
+    JupiterOrderAccount, JupiterOrderAction, JupiterReferralAction, JupiterSwapFill, JupiterSwapLeg,
+    JupiterSwapMismatch, JupiterSwapOptions, NonceAccount, NonceAccountAction, StakeAccount,
+    StakeAccountAction, StakePoolAccount, StakePoolAction, StakePoolState, TokenAccountAction,
+    TokenAccountSnapshot, TokenExtensionAccounts, TransactionFee, TransactionSize,
+    TransactionWarning,
+};
 
 use super::types::*;
 use crate::errors::*;
@@ -30,11 +35,72 @@ use crate::utils::{from_base64, to_base64, from_base64_to_array_32};
 use rand::RngCore;
 use sodalite;
 
+mod analysis_helpers;
+use analysis_helpers::analyze_instructions;
+
+mod balance_helpers;
+
+mod batch_helpers;
+use batch_helpers::batch_transfer_transactions;
+
+mod fee_helpers;
+use fee_helpers::{transaction_fee, unit_price_for_priority_fee};
+
+mod instruction_helpers;
+use instruction_helpers::decode_instructions;
+
+mod jito_helpers;
+use jito_helpers::set_jito_tip;
+
 mod jupiter_helpers;
-use jupiter_helpers::mutate_transaction_slippage_bps;
+use jupiter_helpers::{
+    decode_swap_events, jupiter_order_instructions, mutate_transaction_slippage_bps,
+    parse_order_account,
+};
+
+mod jupiter_referral_helpers;
+use jupiter_referral_helpers::{
+    referral_account_address, referral_instructions, referral_token_account_address,
+    JUPITER_PROJECT,
+};
+
+mod jupiter_quote_helpers;
+use jupiter_quote_helpers::{validate_jupiter_swap, JupiterQuote};
+
+mod jupiter_swap_helpers;
+use jupiter_swap_helpers::jupiter_swap_instructions;
+
+mod lookup_table_helpers;
+use lookup_table_helpers::{lookup_table_accounts, resolve_accounts};
+
+mod message_helpers;
+use message_helpers::{append_instruction_accounts, push_instruction};
+
+mod nonce_helpers;
+use nonce_helpers::{nonce_account_instructions, parse_nonce_account_data, uses_durable_nonce};
 
 mod priority_fee_helpers;
-use priority_fee_helpers::{add_compute_unit_limit, add_compute_unit_price};
+use priority_fee_helpers::{compute_budget_instructions, set_compute_budget};
+
+mod size_helpers;
+use size_helpers::{check_transaction_size, transaction_size};
+
+mod sponsor_helpers;
+use sponsor_helpers::validate_sponsored_message;
+
+mod stake_helpers;
+use stake_helpers::{
+    parse_stake_account_data, stake_account_address_with_seed, stake_account_instructions,
+};
+
+mod stake_pool_helpers;
+use stake_pool_helpers::{stake_pool_instructions, StakePool};
+
+mod token_account_helpers;
+use token_account_helpers::token_account_instructions;
+
+mod token_2022_helpers;
+use token_2022_helpers::apply_transfer_extensions;
 
 pub mod types;
 
@@ -294,33 +360,9 @@ impl TransactionFactory for Factory {
             Pubkey::from_str(sender.contents.as_str()).map_err(TransactionError::keypair)?;
 
         let to_pubkey = &receiver.to_solana_pubkey()?;
-        let amount = Decimal::from_str_exact(&amount.value)
-            .map_err(|e| TransactionError::decimal(e, &amount))?;
-
-        let decimal_lamports_per_sol = Decimal::from_u64(LAMPORTS_PER_SOL).ok_or_else(|| {
-            TransactionError::parsing_failure(Error::ErrorString(
-                "failed to parse Decimal from LAMPORTS_PER_SOL".to_string(),
-            ))
-        })?;
-
-        let amount_lamports = amount
-            .checked_mul(decimal_lamports_per_sol)
-            .ok_or_else(|| {
-                TransactionError::Generic("Failed to calculate amount in LAMPORTS".to_string())
-            })?;
-
-        let mut instructions: Vec<Instruction> = Vec::new();
+        let amount_lamports = sol_to_lamports(&amount)?;
 
-        if let Some(unit_limit) = parameters.compute_budget_unit_limit() {
-            let compute_budget_instruction =
-                ComputeBudgetInstruction::set_compute_unit_limit(unit_limit);
-            instructions.push(compute_budget_instruction);
-        }
-        if let Some(unit_price) = parameters.compute_budget_unit_price() {
-            let compute_budget_instruction =
-                ComputeBudgetInstruction::set_compute_unit_price(unit_price);
-            instructions.push(compute_budget_instruction);
-        }
+        let mut instructions: Vec<Instruction> = compute_budget_instructions(&parameters);
 
         if let Some(memo) = parameters.memo() {
             instructions.push(build_memo(memo.as_bytes(), &[&from_pubkey]));
@@ -329,9 +371,7 @@ impl TransactionFactory for Factory {
         let mut instruction = solana_sdk::system_instruction::transfer(
             &from_pubkey,
             to_pubkey,
-            amount_lamports
-                .try_into()
-                .map_err(TransactionError::parsing_failure)?,
+            amount_lamports,
         );
         for reference in parameters.references().iter() {
             let pubkey = Pubkey::from_str(reference).map_err(TransactionError::public_key)?;
@@ -341,24 +381,7 @@ impl TransactionFactory for Factory {
         }
         instructions.push(instruction);
 
-        let transaction = Transaction::new_with_payer(&instructions, Some(&from_pubkey));
-        let mut versioned_transaction = VersionedTransaction::from(transaction);
-
-        if let Some(external_address) = parameters.external_address() {
-            let recent_blockhash = external_address
-                .recent_blockhash
-                .parse::<hash::Hash>()
-                .map_err(TransactionError::parsing_failure)?;
-
-            versioned_transaction
-                .message
-                .set_recent_blockhash(recent_blockhash);
-        }
-
-        let serialized_tx = bincode::serialize(&versioned_transaction)
-            .map_err(TransactionError::parsing_failure)?;
-
-        Ok(to_base64(serialized_tx))
+        build_transaction(&instructions, &from_pubkey, &parameters)
     }
 
     fn token_transaction(
@@ -399,34 +422,35 @@ impl TransactionFactory for Factory {
                     let destination = Pubkey::from_str(&transfer_destination)
                         .map_err(TransactionError::public_key)?;
 
-                    let mut instructions: Vec<Instruction> = Vec::new();
-
-                    if let Some(unit_limit) = parameters.compute_budget_unit_limit() {
-                        let compute_budget_instruction =
-                            ComputeBudgetInstruction::set_compute_unit_limit(unit_limit);
-                        instructions.push(compute_budget_instruction);
-                    }
-                    if let Some(unit_price) = parameters.compute_budget_unit_price() {
-                        let compute_budget_instruction =
-                            ComputeBudgetInstruction::set_compute_unit_price(unit_price);
-                        instructions.push(compute_budget_instruction);
-                    }
+                    let mut instructions: Vec<Instruction> =
+                        compute_budget_instructions(&parameters);
 
-                    if let Some(memo) = parameters.memo() {
-                        instructions.push(build_memo(memo.as_bytes(), &[&owner_pubkey]));
-                    };
-                    let transfer_instruction = transfer_2022(
+                    let amount = amount.to_u64()?;
+                    let mut transfer_instruction = transfer_2022(
                         &owner_program,
                         &source_token_account,
                         &destination,
                         &owner_pubkey,
                         &[&owner_pubkey],
                         &references?,
-                        amount.to_u64()?,
+                        amount,
                         decimals,
                         mint_pubkey,
                     )
                     .map_err(TransactionError::instruction_error)?;
+                    let memo_required = match parameters.token_extensions() {
+                        Some(token_extensions) => apply_transfer_extensions(
+                            &mut transfer_instruction,
+                            amount,
+                            decimals,
+                            &token_extensions,
+                        )?,
+                        None => false,
+                    };
+                    // A memo required by the destination has to come right before the transfer
+                    if let Some(memo) = parameters.memo().or(memo_required.then(String::new)) {
+                        instructions.push(build_memo(memo.as_bytes(), &[&owner_pubkey]));
+                    }
                     instructions.push(transfer_instruction);
 
                     if close_account {
@@ -442,67 +466,55 @@ impl TransactionFactory for Factory {
                         );
                     }
 
-                    let transaction =
-                        Transaction::new_with_payer(&instructions, Some(&owner_pubkey));
-                    let mut versioned_transaction = VersionedTransaction::from(transaction);
-
-                    if let Some(external_address) = parameters.external_address() {
-                        let recent_blockhash = external_address
-                            .recent_blockhash
-                            .parse::<hash::Hash>()
-                            .map_err(TransactionError::parsing_failure)?;
-
-                        versioned_transaction
-                            .message
-                            .set_recent_blockhash(recent_blockhash);
-                    }
-
-                    let serialized_tx = bincode::serialize(&versioned_transaction)
-                        .map_err(TransactionError::parsing_failure)?;
-                    Ok(to_base64(serialized_tx))
+                    build_transaction(&instructions, &owner_pubkey, &parameters)
                 }
-                TokenDestination::Wallet { public_key } => {
+                TokenDestination::Wallet {
+                    public_key,
+                    account_exists,
+                } => {
                     let receiver_pubkey = &public_key.to_solana_pubkey()?;
                     let destination =
                         associated_token_address_2022(receiver_pubkey, &owner_program, mint_pubkey);
 
-                    let mut instructions: Vec<Instruction> = Vec::new();
-
-                    if let Some(unit_limit) = parameters.compute_budget_unit_limit() {
-                        let compute_budget_instruction =
-                            ComputeBudgetInstruction::set_compute_unit_limit(unit_limit);
-                        instructions.push(compute_budget_instruction);
-                    }
-                    if let Some(unit_price) = parameters.compute_budget_unit_price() {
-                        let compute_budget_instruction =
-                            ComputeBudgetInstruction::set_compute_unit_price(unit_price);
-                        instructions.push(compute_budget_instruction);
+                    let mut instructions: Vec<Instruction> =
+                        compute_budget_instructions(&parameters);
+
+                    if !account_exists {
+                        let funder = parameters.fee_payer()?.unwrap_or(owner_pubkey);
+                        instructions.push(create_associated_token_account_idempotent_2022(
+                            &funder,
+                            receiver_pubkey,
+                            mint_pubkey,
+                            owner_program,
+                        ));
                     }
 
-                    let create_instruction = create_associated_token_account_2022(
-                        &owner_pubkey,
-                        receiver_pubkey,
-                        mint_pubkey,
-                        owner_program,
-                    );
-                    instructions.push(create_instruction);
-
-                    if let Some(memo) = parameters.memo() {
-                        instructions.push(build_memo(memo.as_bytes(), &[&owner_pubkey]));
-                    };
-
-                    let transfer_instruction = transfer_2022(
+                    let amount = amount.to_u64()?;
+                    let mut transfer_instruction = transfer_2022(
                         &owner_program,
                         &source_token_account,
                         &destination,
                         &owner_pubkey,
                         &[&owner_pubkey],
                         &references?,
-                        amount.to_u64()?,
+                        amount,
                         decimals,
                         mint_pubkey,
                     )
                     .map_err(TransactionError::instruction_error)?;
+                    let memo_required = match parameters.token_extensions() {
+                        Some(token_extensions) => apply_transfer_extensions(
+                            &mut transfer_instruction,
+                            amount,
+                            decimals,
+                            &token_extensions,
+                        )?,
+                        None => false,
+                    };
+                    // A memo required by the destination has to come right before the transfer
+                    if let Some(memo) = parameters.memo().or(memo_required.then(String::new)) {
+                        instructions.push(build_memo(memo.as_bytes(), &[&owner_pubkey]));
+                    }
                     instructions.push(transfer_instruction);
 
                     if close_account {
@@ -518,24 +530,7 @@ impl TransactionFactory for Factory {
                         );
                     }
 
-                    let transaction =
-                        Transaction::new_with_payer(&instructions, Some(&owner_pubkey));
-                    let mut versioned_transaction = VersionedTransaction::from(transaction);
-
-                    if let Some(external_address) = parameters.external_address() {
-                        let recent_blockhash = external_address
-                            .recent_blockhash
-                            .parse::<hash::Hash>()
-                            .map_err(TransactionError::parsing_failure)?;
-
-                        versioned_transaction
-                            .message
-                            .set_recent_blockhash(recent_blockhash);
-                    }
-
-                    let serialized_tx = bincode::serialize(&versioned_transaction)
-                        .map_err(TransactionError::parsing_failure)?;
-                    Ok(to_base64(serialized_tx))
+                    build_transaction(&instructions, &owner_pubkey, &parameters)
                 }
             },
             _ => Err(TransactionError::Generic(
@@ -544,6 +539,23 @@ impl TransactionFactory for Factory {
         }
     }
 
+    fn batch_transaction(
+        &self,
+        sender: ChainPublicKey,
+        transfers: Vec<BatchTransfer>,
+        parameters: TransactionParameters,
+    ) -> Result<Vec<String>, TransactionError> {
+        let sender = sender.to_solana_pubkey()?;
+        batch_transfer_transactions(&sender, &transfers, &parameters)?
+            .iter()
+            .map(|transaction| {
+                bincode::serialize(transaction)
+                    .map(to_base64)
+                    .map_err(TransactionError::parsing_failure)
+            })
+            .collect()
+    }
+
     fn sign_transaction(
         &self,
         transaction: String,
@@ -557,12 +569,16 @@ impl TransactionFactory for Factory {
         let transaction_bytes =
             from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
 
+        let compute_budget = parameters
+            .as_ref()
+            .map(compute_budget_instructions)
+            .unwrap_or_default();
         let (
             external_address,
             transaction_type,
             swap_slippage_bps,
-            compute_budget_unit_limit,
-            compute_budget_unit_price,
+            address_lookup_tables,
+            jito_tip,
         ) = match parameters {
             Some(TransactionParameters::Solana {
                 external_address,
@@ -572,14 +588,20 @@ impl TransactionFactory for Factory {
                 memo: _,
                 references: _,
                 swap_slippage_bps,
-                compute_budget_unit_limit,
-                compute_budget_unit_price,
+                compute_budget_unit_limit: _,
+                compute_budget_unit_price: _,
+                loaded_accounts_data_size_limit: _,
+                address_lookup_tables,
+                durable_nonce: _,
+                jito_tip,
+                fee_payer: _,
+                token_extensions: _,
             }) => (
                 external_address,
                 transaction_type,
                 swap_slippage_bps,
-                compute_budget_unit_limit,
-                compute_budget_unit_price,
+                address_lookup_tables,
+                jito_tip,
             ),
             None => return Err(TransactionError::parameters("No parameters were provided")),
         };
@@ -606,12 +628,11 @@ impl TransactionFactory for Factory {
             let _ = mutate_transaction_slippage_bps(&mut transaction, swap_slippage_bps);
         }
 
-        if let Some(compute_budget_unit_limit) = compute_budget_unit_limit {
-            let _ = add_compute_unit_limit(&mut transaction, compute_budget_unit_limit);
-        }
+        let compute_budget_changes =
+            set_compute_budget(&mut transaction.message, &compute_budget)?;
 
-        if let Some(compute_budget_unit_price) = compute_budget_unit_price {
-            let _ = add_compute_unit_price(&mut transaction, compute_budget_unit_price);
+        if let Some(jito_tip) = &jito_tip {
+            set_jito_tip(&mut transaction.message, jito_tip)?;
         }
 
         if let Some(external_address) = &external_address {
@@ -620,36 +641,31 @@ impl TransactionFactory for Factory {
                 .parse::<hash::Hash>()
                 .map_err(TransactionError::parsing_failure)?;
 
-            if transaction
-                .signatures
-                .iter()
-                .all(|&sig| sig == Signature::from([0u8; 64]) || sig == Signature::from([1u8; 64]))
+            if !uses_durable_nonce(&transaction.message)
+                && transaction.signatures.iter().all(|&sig| {
+                    sig == Signature::from([0u8; 64]) || sig == Signature::from([1u8; 64])
+                })
             {
                 transaction.message.set_recent_blockhash(recent_blockhash);
             }
         }
 
+        let signer_public_keys: Vec<ChainPublicKey> = signers
+            .iter()
+            .map(|signer| signer.public_key.clone())
+            .collect();
+
         let result: ChainTransaction =
-            match VersionedTransaction::try_new(transaction.message, &signer_keypairs) {
+            match VersionedTransaction::try_new(transaction.message.clone(), &signer_keypairs) {
                 Ok(versioned_tx) => match bincode::serialize(&versioned_tx) {
                     Ok(serialized_tx) => ChainTransaction {
-                        tx: to_base64(serialized_tx),
-                        signers: signers
-                            .iter()
-                            .map(|signer| signer.public_key.clone())
-                            .collect(),
-                        accounts: versioned_tx
-                            .message
-                            .static_account_keys()
-                            .iter()
-                            .map(|pubkey| ChainPublicKey {
-                                contents: bs58::encode(pubkey).into_string(),
-                                chain: Blockchain::Solana,
-                            })
-                            .collect(),
-                        full_signature: calculate_signature(&versioned_tx.signatures),
-                        signatures: signatures_to_base58(&versioned_tx.signatures),
-                        instruction_programs: get_instruction_programs(versioned_tx.message),
+                        compute_budget_changes,
+                        ..to_chain_transaction(
+                            to_base64(serialized_tx),
+                            signer_public_keys,
+                            versioned_tx,
+                            &address_lookup_tables,
+                        )?
                     },
                     Err(error) => return Err(TransactionError::parsing_failure(error)),
                 },
@@ -659,9 +675,16 @@ impl TransactionFactory for Factory {
                     // in those cases the solana-sdk wants to throw SignerError::NotEnoughSigners
                     // we catch those errors and do our own partial signing
                     SignerError::NotEnoughSigners => {
-                        let mut transaction: VersionedTransaction =
-                            bincode::deserialize(&transaction_bytes)
-                                .map_err(TransactionError::parsing_failure)?;
+                        // The signatures made before the tip, compute budget, slippage or
+                        // blockhash changed no longer match the message
+                        let valid_signatures = transaction.verify_with_results();
+                        for (signature, valid) in
+                            transaction.signatures.iter_mut().zip(valid_signatures)
+                        {
+                            if !valid {
+                                *signature = Signature::default();
+                            }
+                        }
 
                         let account_keys = transaction.message.static_account_keys();
                         let signer_pubkeys = signer_keypairs
@@ -705,23 +728,13 @@ impl TransactionFactory for Factory {
 
                         match serialized_tx {
                             Ok(serialized_tx) => ChainTransaction {
-                                tx: to_base64(serialized_tx),
-                                signers: signers
-                                    .iter()
-                                    .map(|signer| signer.public_key.clone())
-                                    .collect(),
-                                accounts: transaction
-                                    .message
-                                    .static_account_keys()
-                                    .iter()
-                                    .map(|pubkey| ChainPublicKey {
-                                        contents: bs58::encode(pubkey).into_string(),
-                                        chain: Blockchain::Solana,
-                                    })
-                                    .collect(),
-                                full_signature: calculate_signature(&transaction.signatures),
-                                signatures: signatures_to_base58(&transaction.signatures),
-                                instruction_programs: get_instruction_programs(transaction.message),
+                                compute_budget_changes,
+                                ..to_chain_transaction(
+                                    to_base64(serialized_tx),
+                                    signer_public_keys,
+                                    transaction,
+                                    &address_lookup_tables,
+                                )?
                             },
                             Err(error) => return Err(TransactionError::parsing_failure(error)),
                         }
@@ -733,6 +746,53 @@ impl TransactionFactory for Factory {
         Ok(result)
     }
 
+    fn analyze_transaction(
+        &self,
+        transaction: String,
+        owner: ChainPublicKey,
+        allowed_programs: Option<Vec<String>>,
+        trusted_delegates: Vec<String>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<TransactionWarning>, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        let owner = owner.to_solana_pubkey()?.to_string();
+
+        let resolved_accounts = resolve_accounts(&transaction.message, &address_lookup_tables)?;
+        let instructions = decode_instructions(&transaction.message, &resolved_accounts.keys);
+        let programs = get_instruction_programs(&transaction.message, &resolved_accounts.keys)?;
+
+        Ok(analyze_instructions(
+            &instructions,
+            &programs,
+            &owner,
+            allowed_programs.as_deref(),
+            &trusted_delegates,
+        ))
+    }
+
+    fn estimate_balance_changes(
+        &self,
+        transaction: String,
+        accounts: Vec<TokenAccountSnapshot>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<BalanceChange>, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        let resolved_accounts = resolve_accounts(&transaction.message, &address_lookup_tables)?;
+
+        let instructions = decode_instructions(&transaction.message, &resolved_accounts.keys);
+
+        balance_helpers::estimate_balance_changes(
+            &transaction.message,
+            &resolved_accounts.keys,
+            &instructions,
+            &accounts,
+        )
+    }
+
     fn sign_message(
         &self,
         message: String,
@@ -777,61 +837,119 @@ impl TransactionFactory for Factory {
 
     fn modify_transaction(
         &self,
-        _transaction: String,
-        _owner: ChainPrivateKey,
-        _parameters: TransactionParameters,
+        transaction: String,
+        owner: ChainPrivateKey,
+        parameters: TransactionParameters,
     ) -> Result<String, TransactionError> {
-        todo!()
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+
+        let mut transaction: VersionedTransaction = match parameters.transaction_type() {
+            SolanaTransactionType::Legacy => {
+                let transaction: Transaction = bincode::deserialize(&transaction_bytes)
+                    .map_err(TransactionError::parsing_failure)?;
+                VersionedTransaction::from(transaction)
+            }
+            SolanaTransactionType::Versioned => bincode::deserialize(&transaction_bytes)
+                .map_err(TransactionError::parsing_failure)?,
+        };
+
+        let owner_keypair = owner.signer_keypair()?;
+        let owner_pubkey = owner_keypair.pubkey();
+
+        if let Some(swap_slippage_bps) = parameters.swap_slippage_bps() {
+            mutate_transaction_slippage_bps(&mut transaction, swap_slippage_bps)?;
+        }
+
+        set_compute_budget(
+            &mut transaction.message,
+            &compute_budget_instructions(&parameters),
+        )?;
+
+        if let Some(jito_tip) = parameters.jito_tip() {
+            set_jito_tip(&mut transaction.message, &jito_tip)?;
+        }
+
+        if let Some(memo) = parameters.memo() {
+            push_instruction(
+                &mut transaction.message,
+                &build_memo(memo.as_bytes(), &[&owner_pubkey]),
+            )?;
+        }
+
+        let references = parameters
+            .references()
+            .iter()
+            .map(|r| Pubkey::from_str(r).map_err(TransactionError::public_key))
+            .collect::<Result<Vec<Pubkey>, TransactionError>>()?;
+        if !references.is_empty() {
+            // Like in `send_transaction` and `token_transaction`, references are
+            // attached to the transfer instruction
+            let transfer_index = find_transfer_instruction(&transaction.message).ok_or_else(|| {
+                TransactionError::parameters("No transfer instruction to attach references to")
+            })?;
+            append_instruction_accounts(&mut transaction.message, transfer_index, &references)?;
+        }
+
+        if let Some(external_address) = parameters.external_address() {
+            let recent_blockhash = external_address
+                .recent_blockhash
+                .parse::<hash::Hash>()
+                .map_err(TransactionError::parsing_failure)?;
+            if !uses_durable_nonce(&transaction.message) {
+                transaction.message.set_recent_blockhash(recent_blockhash);
+            }
+        }
+
+        // References can push the transaction over the limits
+        check_transaction_size(&transaction)?;
+
+        // Every existing signature is invalid now, so only the owner signs again
+        let num_required_signatures =
+            usize::from(transaction.message.header().num_required_signatures);
+        let owner_position = transaction.message.static_account_keys()
+            [..num_required_signatures]
+            .iter()
+            .position(|key| *key == owner_pubkey)
+            .ok_or(TransactionError::SignerMissing)?;
+
+        let mut signatures = vec![Signature::default(); num_required_signatures];
+        signatures[owner_position] = owner_keypair
+            .try_sign_message(&transaction.message.serialize())
+            .map_err(TransactionError::keypair)?;
+        transaction.signatures = signatures;
+
+        let serialized_tx =
+            bincode::serialize(&transaction).map_err(TransactionError::parsing_failure)?;
+        Ok(to_base64(serialized_tx))
     }
 
-    fn parse_transaction(&self, transaction: String) -> Result<ChainTransaction, TransactionError> {
+    fn parse_transaction(
+        &self,
+        transaction: String,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<ChainTransaction, TransactionError> {
         let transaction_bytes =
             from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
 
         // Try to parse as VersionedTransaction first
         if let Ok(versioned_tx) = bincode::deserialize::<VersionedTransaction>(&transaction_bytes) {
-            let accounts = versioned_tx
-                .message
-                .static_account_keys()
-                .iter()
-                .map(|pubkey| ChainPublicKey {
-                    contents: bs58::encode(pubkey).into_string(),
-                    chain: Blockchain::Solana,
-                })
-                .collect();
-
-            return Ok(ChainTransaction {
-                tx: transaction,
-                signers: vec![], // Not available from serialized tx
-                accounts,
-                full_signature: calculate_signature(&versioned_tx.signatures),
-                signatures: signatures_to_base58(&versioned_tx.signatures),
-                instruction_programs: get_instruction_programs(versioned_tx.message),
-            });
+            return to_chain_transaction(
+                transaction,
+                vec![], // Not available from serialized tx
+                versioned_tx,
+                &address_lookup_tables,
+            );
         }
 
         // Try to parse as legacy Transaction
         if let Ok(tx) = bincode::deserialize::<Transaction>(&transaction_bytes) {
-            let accounts = tx
-                .message
-                .account_keys
-                .iter()
-                .map(|pubkey| ChainPublicKey {
-                    contents: bs58::encode(pubkey).into_string(),
-                    chain: Blockchain::Solana,
-                })
-                .collect();
-
-            return Ok(ChainTransaction {
-                tx: transaction,
-                signers: vec![], // Not available from serialized tx
-                accounts,
-                full_signature: calculate_signature(&tx.signatures),
-                signatures: signatures_to_base58(&tx.signatures),
-                instruction_programs: get_instruction_programs(VersionedMessage::Legacy(
-                    tx.message,
-                )),
-            });
+            return to_chain_transaction(
+                transaction,
+                vec![], // Not available from serialized tx
+                VersionedTransaction::from(tx),
+                &address_lookup_tables,
+            );
         }
 
         Err(TransactionError::parsing_failure(
@@ -995,75 +1113,545 @@ impl TransactionFactory for Factory {
             "Failed to append signature to transaction".to_string(),
         ))
     }
-}
 
-fn calculate_signature(signatures: &Vec<Signature>) -> Option<String> {
-    if signatures.is_empty() {
-        return None;
+    fn estimate_transaction_size(
+        &self,
+        transaction: String,
+    ) -> Result<TransactionSize, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        transaction_size(&transaction.message)
     }
 
-    let concatenated: Vec<u8> = signatures
-        .iter()
-        .flat_map(|sig| sig.as_ref().to_vec())
-        .collect();
-
-    let encoded = bs58::encode(concatenated).into_string();
+    fn calculate_transaction_fee(
+        &self,
+        transaction: String,
+    ) -> Result<TransactionFee, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        let instructions = decode_instructions(
+            &transaction.message,
+            transaction.message.static_account_keys(),
+        );
+        transaction_fee(&transaction.message, &instructions)
+    }
 
-    Some(encoded)
-}
+    fn compute_unit_price_for_priority_fee(
+        &self,
+        priority_fee: u64,
+        compute_unit_limit: u32,
+    ) -> Result<u64, TransactionError> {
+        unit_price_for_priority_fee(priority_fee, compute_unit_limit)
+    }
 
-fn signatures_to_base58(signatures: &Vec<Signature>) -> Option<Vec<String>> {
-    if signatures.is_empty() {
-        return None;
+    fn nonce_account_transaction(
+        &self,
+        action: NonceAccountAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let (instructions, payer) = nonce_account_instructions(&action)?;
+        build_transaction(&instructions, &payer, &parameters)
     }
 
-    let encoded_signatures = signatures
-        .into_iter()
-        .map(|sig| bs58::encode(sig.as_ref().to_vec()).into_string()) // Convert each Signature to a Base58 String
-        .collect();
+    fn parse_nonce_account(&self, data: String) -> Result<NonceAccount, TransactionError> {
+        let data = from_base64(&data).map_err(TransactionError::parsing_failure)?;
+        parse_nonce_account_data(&data)
+    }
 
-    Some(encoded_signatures)
-}
+    fn stake_account_transaction(
+        &self,
+        action: StakeAccountAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let (instructions, payer) = stake_account_instructions(&action)?;
+        build_transaction(&instructions, &payer, &parameters)
+    }
 
-fn get_instruction_programs(message: VersionedMessage) -> Vec<String> {
-    let program_id_indexes: Vec<u8> = message
-        .instructions()
-        .iter()
-        .map(|i| i.program_id_index)
-        .collect();
+    fn get_stake_account_address_with_seed(
+        &self,
+        base: String,
+        seed: String,
+    ) -> Result<ChainPublicKey, TransactionError> {
+        let base = Pubkey::from_str(&base).map_err(TransactionError::public_key)?;
+        let address = stake_account_address_with_seed(&base, &seed)?;
+        Ok(ChainPublicKey::new(address.to_string(), Blockchain::Solana))
+    }
 
-    let account_keys = message.static_account_keys();
+    fn parse_stake_account(&self, data: String) -> Result<StakeAccount, TransactionError> {
+        let data = from_base64(&data).map_err(TransactionError::parsing_failure)?;
+        parse_stake_account_data(&data)
+    }
 
-    program_id_indexes
-        .iter()
-        .map(|index| account_keys[*index as usize])
-        .map(|pubkey| bs58::encode(pubkey).into_string())
-        .collect()
-}
+    fn stake_pool_transaction(
+        &self,
+        stake_pool: StakePoolAccount,
+        action: StakePoolAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let stake_pool = StakePool::from_account(&stake_pool)?;
+        let (instructions, payer) = stake_pool_instructions(&stake_pool, &action)?;
+        build_transaction(&instructions, &payer, &parameters)
+    }
 
-impl TransactionParameters {
-    fn decimals(&self) -> Option<u8> {
-        let TransactionParameters::Solana { decimals, .. } = &self;
-        *decimals
+    fn parse_stake_pool(
+        &self,
+        stake_pool: StakePoolAccount,
+    ) -> Result<StakePoolState, TransactionError> {
+        Ok(StakePool::from_account(&stake_pool)?.to_state())
     }
-    fn owner_program(&self) -> Option<String> {
-        let TransactionParameters::Solana { owner_program, .. } = &self;
-        owner_program.clone()
+
+    fn validate_jupiter_swap(
+        &self,
+        transaction: String,
+        quote: String,
+        user: ChainPublicKey,
+        fee_account: Option<String>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<JupiterSwapMismatch>, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        let quote = JupiterQuote::from_json(&quote)?;
+        let fee_account = fee_account
+            .map(|fee_account| Pubkey::from_str(&fee_account))
+            .transpose()
+            .map_err(TransactionError::public_key)?;
+        let resolved_accounts = resolve_accounts(&transaction.message, &address_lookup_tables)?;
+
+        validate_jupiter_swap(
+            &transaction.message,
+            &resolved_accounts.keys,
+            &quote,
+            &user.to_solana_pubkey()?,
+            fee_account.as_ref(),
+        )
     }
-    fn memo(&self) -> Option<String> {
-        let TransactionParameters::Solana { memo, .. } = &self;
-        memo.clone()
+
+    fn jupiter_swap_transaction(
+        &self,
+        quote: String,
+        user: ChainPublicKey,
+        legs: Vec<JupiterSwapLeg>,
+        options: JupiterSwapOptions,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        if let SolanaTransactionType::Legacy = parameters.transaction_type() {
+            return Err(TransactionError::parameters(
+                "Jupiter swaps are built as versioned transactions",
+            ));
+        }
+        let quote = JupiterQuote::from_json(&quote)?;
+        let user = user.to_solana_pubkey()?;
+        let instructions = jupiter_swap_instructions(&quote, &user, &legs, &options, &parameters)?;
+        build_transaction(&instructions, &user, &parameters)
     }
-    fn references(&self) -> Vec<String> {
-        let TransactionParameters::Solana { references, .. } = &self;
-        references.clone()
+
+    fn parse_jupiter_swap_events(
+        &self,
+        transaction: String,
+        meta: String,
+    ) -> Result<Option<JupiterSwapFill>, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let transaction = deserialize_any_transaction(&transaction_bytes)?;
+        decode_swap_events(&meta, transaction.message.static_account_keys())
     }
-    fn compute_budget_unit_price(&self) -> Option<u64> {
-        let TransactionParameters::Solana {
-            compute_budget_unit_price,
-            ..
-        } = &self;
-        *compute_budget_unit_price
+
+    fn jupiter_order_transaction(
+        &self,
+        action: JupiterOrderAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let (instructions, payer) = jupiter_order_instructions(&action)?;
+        build_transaction(&instructions, &payer, &parameters)
+    }
+
+    fn parse_jupiter_order(
+        &self,
+        account: JupiterOrderAccount,
+    ) -> Result<JupiterOrder, TransactionError> {
+        parse_order_account(&account)
+    }
+
+    fn get_jupiter_referral_account(
+        &self,
+        project: Option<String>,
+        name: String,
+    ) -> Result<ChainPublicKey, TransactionError> {
+        let project = match project {
+            Some(project) => Pubkey::from_str(&project).map_err(TransactionError::public_key)?,
+            None => JUPITER_PROJECT,
+        };
+        Ok(ChainPublicKey::new(
+            referral_account_address(&project, &name).to_string(),
+            Blockchain::Solana,
+        ))
+    }
+
+    fn get_jupiter_referral_token_account(
+        &self,
+        referral_account: String,
+        mint: String,
+    ) -> Result<ChainPublicKey, TransactionError> {
+        let referral_account =
+            Pubkey::from_str(&referral_account).map_err(TransactionError::public_key)?;
+        let mint = Pubkey::from_str(&mint).map_err(TransactionError::public_key)?;
+        Ok(ChainPublicKey::new(
+            referral_token_account_address(&referral_account, &mint).to_string(),
+            Blockchain::Solana,
+        ))
+    }
+
+    fn jupiter_referral_transaction(
+        &self,
+        action: JupiterReferralAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let (instructions, payer) = referral_instructions(&action)?;
+        build_transaction(&instructions, &payer, &parameters)
+    }
+
+    fn sign_sponsored_transaction(
+        &self,
+        transaction: String,
+        sponsor: ChainPrivateKey,
+        max_fee: u64,
+        allow_rent: bool,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<ChainTransaction, TransactionError> {
+        let transaction_bytes =
+            from_base64(&transaction).map_err(TransactionError::parsing_failure)?;
+        let message = deserialize_any_transaction(&transaction_bytes)?.message;
+        let resolved_accounts = resolve_accounts(&message, &address_lookup_tables)?;
+        let rent = validate_sponsored_message(
+            &message,
+            &resolved_accounts.keys,
+            &sponsor.public_key.to_solana_pubkey()?,
+            allow_rent,
+        )?;
+
+        let fee = transaction_fee(
+            &message,
+            &decode_instructions(&message, &resolved_accounts.keys),
+        )?;
+        let cost = fee
+            .total_fee
+            .checked_add(rent)
+            .ok_or_else(|| TransactionError::sponsorship("The sponsored cost overflows"))?;
+        if cost > max_fee {
+            return Err(TransactionError::sponsorship(format!(
+                "The fee and rent of {cost} lamports exceed {max_fee}"
+            )));
+        }
+
+        // Signs the message as it was checked, without touching its compute budget,
+        // blockhash or tip
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Versioned,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables,
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        self.sign_transaction(transaction, vec![sponsor], Some(parameters))
+    }
+
+    fn token_account_transaction(
+        &self,
+        actions: Vec<TokenAccountAction>,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError> {
+        let (instructions, payer) = token_account_instructions(&actions)?;
+        build_transaction(&instructions, &payer, &parameters)
+    }
+}
+
+fn calculate_signature(signatures: &Vec<Signature>) -> Option<String> {
+    if signatures.is_empty() {
+        return None;
+    }
+
+    let concatenated: Vec<u8> = signatures
+        .iter()
+        .flat_map(|sig| sig.as_ref().to_vec())
+        .collect();
+
+    let encoded = bs58::encode(concatenated).into_string();
+
+    Some(encoded)
+}
+
+fn signatures_to_base58(signatures: &Vec<Signature>) -> Option<Vec<String>> {
+    if signatures.is_empty() {
+        return None;
+    }
+
+    let encoded_signatures = signatures
+        .into_iter()
+        .map(|sig| bs58::encode(sig.as_ref().to_vec()).into_string()) // Convert each Signature to a Base58 String
+        .collect();
+
+    Some(encoded_signatures)
+}
+
+/// Converts an amount of SOL into lamports
+fn sol_to_lamports(amount: &DecimalNumber) -> Result<u64, TransactionError> {
+    let decimal_amount = Decimal::from_str_exact(&amount.value)
+        .map_err(|e| TransactionError::decimal(e, amount))?;
+
+    let decimal_lamports_per_sol = Decimal::from_u64(LAMPORTS_PER_SOL).ok_or_else(|| {
+        TransactionError::parsing_failure(Error::ErrorString(
+            "failed to parse Decimal from LAMPORTS_PER_SOL".to_string(),
+        ))
+    })?;
+
+    let amount_lamports = decimal_amount
+        .checked_mul(decimal_lamports_per_sol)
+        .ok_or_else(|| {
+            TransactionError::Generic("Failed to calculate amount in LAMPORTS".to_string())
+        })?;
+
+    amount_lamports
+        .try_into()
+        .map_err(TransactionError::parsing_failure)
+}
+
+/// Compiles `instructions` into an unsigned transaction of the requested type and
+/// returns it base64 encoded. Versioned transactions are compiled against the
+/// `address_lookup_tables` of the parameters. With a durable nonce, the nonce is
+/// advanced first and replaces the recent blockhash. The `fee_payer` of the
+/// parameters takes the place of `payer`.
+fn build_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    parameters: &TransactionParameters,
+) -> Result<String, TransactionError> {
+    let versioned_transaction = build_versioned_transaction(instructions, payer, parameters)?;
+    check_transaction_size(&versioned_transaction)?;
+    let serialized_tx =
+        bincode::serialize(&versioned_transaction).map_err(TransactionError::parsing_failure)?;
+    Ok(to_base64(serialized_tx))
+}
+
+/// Compiles the unsigned transaction of `build_transaction`
+fn build_versioned_transaction(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    parameters: &TransactionParameters,
+) -> Result<VersionedTransaction, TransactionError> {
+    let mut recent_blockhash = match parameters.external_address() {
+        Some(external_address) => external_address
+            .recent_blockhash
+            .parse::<hash::Hash>()
+            .map_err(TransactionError::parsing_failure)?,
+        None => hash::Hash::default(),
+    };
+
+    let mut instructions = instructions.to_vec();
+    if let Some(durable_nonce) = parameters.durable_nonce() {
+        let nonce_account =
+            Pubkey::from_str(&durable_nonce.nonce_account).map_err(TransactionError::public_key)?;
+        let nonce_authority = Pubkey::from_str(&durable_nonce.nonce_authority)
+            .map_err(TransactionError::public_key)?;
+        instructions.insert(
+            0,
+            system_instruction::advance_nonce_account(&nonce_account, &nonce_authority),
+        );
+        recent_blockhash = durable_nonce
+            .nonce
+            .parse::<hash::Hash>()
+            .map_err(TransactionError::parsing_failure)?;
+    }
+    let instructions = instructions.as_slice();
+    let payer = &parameters.fee_payer()?.unwrap_or(*payer);
+
+    let message = match parameters.transaction_type() {
+        SolanaTransactionType::Legacy => VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &recent_blockhash,
+        )),
+        SolanaTransactionType::Versioned => {
+            let lookup_tables = lookup_table_accounts(&parameters.address_lookup_tables())?;
+            VersionedMessage::V0(
+                v0::Message::try_compile(payer, instructions, &lookup_tables, recent_blockhash)
+                    .map_err(TransactionError::generic_error)?,
+            )
+        }
+    };
+
+    Ok(VersionedTransaction {
+        signatures: vec![
+            Signature::default();
+            usize::from(message.header().num_required_signatures)
+        ],
+        message,
+    })
+}
+
+/// Describes `transaction` with the accounts it loads from `address_lookup_tables` resolved
+fn to_chain_transaction(
+    tx: String,
+    signers: Vec<ChainPublicKey>,
+    transaction: VersionedTransaction,
+    address_lookup_tables: &[AddressLookupTableData],
+) -> Result<ChainTransaction, TransactionError> {
+    let resolved_accounts = resolve_accounts(&transaction.message, address_lookup_tables)?;
+
+    Ok(ChainTransaction {
+        tx,
+        signers,
+        accounts: resolved_accounts
+            .keys
+            .iter()
+            .map(|pubkey| ChainPublicKey {
+                contents: bs58::encode(pubkey).into_string(),
+                chain: Blockchain::Solana,
+            })
+            .collect(),
+        account_metas: resolved_accounts.accounts,
+        full_signature: calculate_signature(&transaction.signatures),
+        signatures: signatures_to_base58(&transaction.signatures),
+        instructions: decode_instructions(&transaction.message, &resolved_accounts.keys),
+        missing_signers: missing_signers(&transaction),
+        instruction_programs: get_instruction_programs(
+            &transaction.message,
+            &resolved_accounts.keys,
+        )?,
+        compute_budget_changes: Vec::new(),
+    })
+}
+
+/// The required signers of `transaction` that haven't signed it yet
+fn missing_signers(transaction: &VersionedTransaction) -> Vec<ChainPublicKey> {
+    let num_required_signatures = usize::from(transaction.message.header().num_required_signatures);
+    // The header of a parsed transaction isn't sanitized and may claim more signers
+    // than it has keys
+    transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .take(num_required_signatures)
+        .enumerate()
+        .filter(|(index, _)| {
+            transaction
+                .signatures
+                .get(*index)
+                .map_or(true, |signature| *signature == Signature::default())
+        })
+        .map(|(_, pubkey)| ChainPublicKey {
+            contents: pubkey.to_string(),
+            chain: Blockchain::Solana,
+        })
+        .collect()
+}
+
+/// Deserializes either a versioned or a legacy transaction
+fn deserialize_any_transaction(bytes: &[u8]) -> Result<VersionedTransaction, TransactionError> {
+    if let Ok(versioned_tx) = bincode::deserialize::<VersionedTransaction>(bytes) {
+        return Ok(versioned_tx);
+    }
+    let transaction =
+        bincode::deserialize::<Transaction>(bytes).map_err(TransactionError::parsing_failure)?;
+    Ok(VersionedTransaction::from(transaction))
+}
+
+/// Finds the first native SOL or SPL token transfer in the message
+fn find_transfer_instruction(message: &VersionedMessage) -> Option<usize> {
+    let account_keys = message.static_account_keys();
+    message.instructions().iter().position(|instruction| {
+        let Some(program_id) = account_keys.get(usize::from(instruction.program_id_index)) else {
+            return false;
+        };
+        if *program_id == solana_program::system_program::id() {
+            matches!(
+                bincode::deserialize::<SystemInstruction>(&instruction.data),
+                Ok(SystemInstruction::Transfer { .. })
+            )
+        } else if is_program_allowed(program_id) {
+            matches!(
+                TokenInstruction::unpack(&instruction.data),
+                Ok(TokenInstruction::Transfer { .. } | TokenInstruction::TransferChecked { .. })
+            )
+        } else {
+            false
+        }
+    })
+}
+
+fn get_instruction_programs(
+    message: &VersionedMessage,
+    account_keys: &[Pubkey],
+) -> Result<Vec<String>, TransactionError> {
+    message
+        .instructions()
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| {
+            account_keys
+                .get(usize::from(instruction.program_id_index))
+                .map(|pubkey| bs58::encode(pubkey).into_string())
+                .ok_or_else(|| {
+                    TransactionError::parameters(format!("Instruction {index} has no program"))
+                })
+        })
+        .collect()
+}
+
+impl TransactionParameters {
+    fn decimals(&self) -> Option<u8> {
+        let TransactionParameters::Solana { decimals, .. } = &self;
+        *decimals
+    }
+    fn owner_program(&self) -> Option<String> {
+        let TransactionParameters::Solana { owner_program, .. } = &self;
+        owner_program.clone()
+    }
+    fn memo(&self) -> Option<String> {
+        let TransactionParameters::Solana { memo, .. } = &self;
+        memo.clone()
+    }
+    fn references(&self) -> Vec<String> {
+        let TransactionParameters::Solana { references, .. } = &self;
+        references.clone()
+    }
+    fn loaded_accounts_data_size_limit(&self) -> Option<u32> {
+        let TransactionParameters::Solana {
+            loaded_accounts_data_size_limit,
+            ..
+        } = &self;
+        *loaded_accounts_data_size_limit
+    }
+    fn compute_budget_unit_price(&self) -> Option<u64> {
+        let TransactionParameters::Solana {
+            compute_budget_unit_price,
+            ..
+        } = &self;
+        *compute_budget_unit_price
+    }
+    fn transaction_type(&self) -> SolanaTransactionType {
+        let TransactionParameters::Solana {
+            transaction_type, ..
+        } = &self;
+        transaction_type.clone()
+    }
+    fn swap_slippage_bps(&self) -> Option<u16> {
+        let TransactionParameters::Solana {
+            swap_slippage_bps, ..
+        } = &self;
+        *swap_slippage_bps
     }
     fn compute_budget_unit_limit(&self) -> Option<u32> {
         let TransactionParameters::Solana {
@@ -1078,6 +1666,35 @@ impl TransactionParameters {
         } = &self;
         external_address.clone()
     }
+    fn token_extensions(&self) -> Option<TokenExtensionAccounts> {
+        let TransactionParameters::Solana {
+            token_extensions, ..
+        } = &self;
+        token_extensions.clone()
+    }
+    fn jito_tip(&self) -> Option<JitoTip> {
+        let TransactionParameters::Solana { jito_tip, .. } = &self;
+        jito_tip.clone()
+    }
+    fn durable_nonce(&self) -> Option<DurableNonce> {
+        let TransactionParameters::Solana { durable_nonce, .. } = &self;
+        durable_nonce.clone()
+    }
+    fn fee_payer(&self) -> Result<Option<Pubkey>, TransactionError> {
+        let TransactionParameters::Solana { fee_payer, .. } = &self;
+        fee_payer
+            .as_deref()
+            .map(Pubkey::from_str)
+            .transpose()
+            .map_err(TransactionError::public_key)
+    }
+    fn address_lookup_tables(&self) -> Vec<AddressLookupTableData> {
+        let TransactionParameters::Solana {
+            address_lookup_tables,
+            ..
+        } = &self;
+        address_lookup_tables.clone()
+    }
 }
 
 impl ChainPublicKey {
@@ -1154,6 +1771,24 @@ fn create_associated_token_account_2022(
     }
 }
 
+/// Same as `create_associated_token_account_2022`, but doesn't fail when the account already exists
+fn create_associated_token_account_idempotent_2022(
+    funding_address: &Pubkey,
+    wallet_address: &Pubkey,
+    spl_token_mint_address: &Pubkey,
+    owner_program: Pubkey,
+) -> Instruction {
+    Instruction {
+        data: vec![1],
+        ..create_associated_token_account_2022(
+            funding_address,
+            wallet_address,
+            spl_token_mint_address,
+            owner_program,
+        )
+    }
+}
+
 /// Creates a `Transfer` with new tokenz 2022 program also as option instruction.
 #[allow(clippy::too_many_arguments)]
 pub fn transfer_2022(
@@ -1231,6 +1866,166 @@ pub fn close_token_account(
     })
 }
 
+/// Creates an `Approve` letting `delegate_pubkey` transfer up to `amount` tokens of the account
+pub fn approve_delegate(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    delegate_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+) -> Result<Instruction, TransactionError> {
+    token_instruction(
+        token_program_id,
+        TokenInstruction::Approve { amount },
+        vec![
+            AccountMeta::new(*account_pubkey, false),
+            AccountMeta::new_readonly(*delegate_pubkey, false),
+        ],
+        owner_pubkey,
+        signer_pubkeys,
+    )
+}
+
+/// Creates a `Revoke` removing the delegate of the account
+pub fn revoke_delegate(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, TransactionError> {
+    token_instruction(
+        token_program_id,
+        TokenInstruction::Revoke,
+        vec![AccountMeta::new(*account_pubkey, false)],
+        owner_pubkey,
+        signer_pubkeys,
+    )
+}
+
+/// Creates a `BurnChecked` when the `decimals` of the mint are given, a `Burn` otherwise
+pub fn burn_token(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    amount: u64,
+    decimals: Option<u8>,
+) -> Result<Instruction, TransactionError> {
+    let data = match decimals {
+        Some(decimals) => TokenInstruction::BurnChecked { amount, decimals },
+        None => TokenInstruction::Burn { amount },
+    };
+    token_instruction(
+        token_program_id,
+        data,
+        vec![
+            AccountMeta::new(*account_pubkey, false),
+            AccountMeta::new(*mint_pubkey, false),
+        ],
+        owner_pubkey,
+        signer_pubkeys,
+    )
+}
+
+/// Creates a `FreezeAccount`, or a `ThawAccount` when `freeze` is false, signed by
+/// the freeze authority of the mint
+pub fn freeze_token_account(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+    mint_pubkey: &Pubkey,
+    freeze_authority_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+    freeze: bool,
+) -> Result<Instruction, TransactionError> {
+    let data = if freeze {
+        TokenInstruction::FreezeAccount
+    } else {
+        TokenInstruction::ThawAccount
+    };
+    token_instruction(
+        token_program_id,
+        data,
+        vec![
+            AccountMeta::new(*account_pubkey, false),
+            AccountMeta::new_readonly(*mint_pubkey, false),
+        ],
+        freeze_authority_pubkey,
+        signer_pubkeys,
+    )
+}
+
+/// Creates a `SetAuthority` of the account or mint, removing the authority without
+/// a `new_authority_pubkey`
+pub fn set_token_authority(
+    token_program_id: &Pubkey,
+    owned_pubkey: &Pubkey,
+    new_authority_pubkey: Option<&Pubkey>,
+    authority_type: AuthorityType,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, TransactionError> {
+    token_instruction(
+        token_program_id,
+        TokenInstruction::SetAuthority {
+            authority_type,
+            new_authority: new_authority_pubkey.copied().into(),
+        },
+        vec![AccountMeta::new(*owned_pubkey, false)],
+        owner_pubkey,
+        signer_pubkeys,
+    )
+}
+
+/// Creates a `SyncNative` updating the token amount of a wrapped SOL account to its lamports
+pub fn sync_native_token_account(
+    token_program_id: &Pubkey,
+    account_pubkey: &Pubkey,
+) -> Result<Instruction, TransactionError> {
+    if !is_program_allowed(token_program_id) {
+        return Err(TransactionError::InstructionError(
+            "wrong token program".to_string(),
+        ));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts: vec![AccountMeta::new(*account_pubkey, false)],
+        data: TokenInstruction::SyncNative.pack(),
+    })
+}
+
+/// Appends the authority `owner_pubkey` to `accounts`, followed by the `signer_pubkeys`
+/// when it's a multisig, and packs the instruction
+fn token_instruction(
+    token_program_id: &Pubkey,
+    instruction: TokenInstruction,
+    mut accounts: Vec<AccountMeta>,
+    owner_pubkey: &Pubkey,
+    signer_pubkeys: &[&Pubkey],
+) -> Result<Instruction, TransactionError> {
+    if !is_program_allowed(token_program_id) {
+        return Err(TransactionError::InstructionError(
+            "wrong token program".to_string(),
+        ));
+    }
+
+    accounts.push(AccountMeta::new_readonly(
+        *owner_pubkey,
+        signer_pubkeys.is_empty(),
+    ));
+    for signer_pubkey in signer_pubkeys.iter() {
+        accounts.push(AccountMeta::new_readonly(**signer_pubkey, true));
+    }
+
+    Ok(Instruction {
+        program_id: *token_program_id,
+        accounts,
+        data: instruction.pack(),
+    })
+}
+
 /// Checks that the supplied program ID is the correct one for SPL-token / Token2022
 fn is_program_allowed(spl_token_program_id: &Pubkey) -> bool {
     let to_compare = spl_token_program_id.to_string();
@@ -1240,7 +2035,13 @@ fn is_program_allowed(spl_token_program_id: &Pubkey) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::solana::types::ExternalAddress;
+    use crate::solana::types::{
+        AddressLookupTableData, BalanceChange, DurableNonce, ExternalAddress, NonceAccountAction,
+        SolanaInstruction, StakeAccountAction, TokenAccountSnapshot, TransactionAccount,
+        TransactionWarning,
+    };
+    use crate::solana::jito_helpers::JITO_TIP_ACCOUNTS;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
     use spl_associated_token_account::instruction::create_associated_token_account;
     use std::collections::HashSet;
 
@@ -1595,7 +2396,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_tx = Factory
@@ -1632,7 +2439,419 @@ mod tests {
             chain: Blockchain::Solana,
         };
 
-        let payload = TransactionParameters::Solana {
+        let payload = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let signed_tx = Factory
+            .send_transaction(
+                sender_key.public_key,
+                receiver,
+                DecimalNumber {
+                    value: "0.000001001".to_string(),
+                },
+                payload,
+            )
+            .unwrap();
+
+        assert_eq!(
+            signed_tx,
+            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAEDDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9h/wdX3dkomxgJlLY6GGcOFWkAa84Cr5zlHAzBgnBfY4QAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAcSF7ZDDOSxTErZg5qfh/hsKRZlwnN81vnzITudOZjnUBAgIAAQwCAAAA6QMAAAAAAAA="
+        )
+    }
+
+    #[test]
+    fn test_send_transaction_amount_one() {
+        let sender_key = generate_key_from_mnemonic(
+            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+        );
+
+        assert_eq!(
+            sender_key.public_key.contents,
+            "sDaZSSKL8BPeAduGRcTudB6Brz5EdxfqUDyVJHr5EAB"
+        );
+        assert_eq!(sender_key.contents, "4oMuZhcmihwvCpZXGbPbyXVgBzeo9FTvJRBDrkY52EyR1cupGPB1tzgokZV1b983F9c5NmW1v64xPsy59g1N86zK");
+
+        let receiver = ChainPublicKey {
+            contents: "9biD1JVeWCPQpWSAGdxGZaNd6VeUm5QYQu9hp2EMnfnp".to_string(),
+            chain: Blockchain::Solana,
+        };
+
+        let parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let signed_tx = Factory
+            .send_transaction(
+                sender_key.public_key,
+                receiver,
+                DecimalNumber {
+                    value: "1".to_string(),
+                },
+                parameters,
+            )
+            .unwrap();
+
+        assert_eq!(
+            signed_tx,
+            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAEDDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9h/wdX3dkomxgJlLY6GGcOFWkAa84Cr5zlHAzBgnBfY4QAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAcSF7ZDDOSxTErZg5qfh/hsKRZlwnN81vnzITudOZjnUBAgIAAQwCAAAAAMqaOwAAAAA="
+        )
+    }
+
+    #[test]
+    fn test_send_versioned_transaction_with_lookup_table() {
+        let sender = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let references: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let table_address = Pubkey::new_unique();
+        let table_data = solana_sdk::address_lookup_table::state::AddressLookupTable {
+            meta: Default::default(),
+            addresses: std::borrow::Cow::Owned(references.clone()),
+        }
+        .serialize_for_tests()
+        .unwrap();
+
+        let parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Versioned,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: references.iter().map(|r| r.to_string()).collect(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: vec![AddressLookupTableData {
+                address: table_address.to_string(),
+                data: to_base64(table_data),
+            }],
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let tx = Factory
+            .send_transaction(
+                ChainPublicKey::new(sender.to_string(), Blockchain::Solana),
+                ChainPublicKey::new(receiver.to_string(), Blockchain::Solana),
+                DecimalNumber {
+                    value: "1".to_string(),
+                },
+                parameters.clone(),
+            )
+            .unwrap();
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(&tx).unwrap()).unwrap();
+
+        let VersionedMessage::V0(message) = &transaction.message else {
+            panic!("Expected a V0 message");
+        };
+        assert_eq!(message.account_keys.len(), 3);
+        assert_eq!(message.address_table_lookups[0].account_key, table_address);
+        assert_eq!(message.address_table_lookups[0].readonly_indexes, vec![0, 1, 2, 3]);
+
+        // All references are resolved again when parsing
+        let parsed = Factory
+            .parse_transaction(tx, parameters.address_lookup_tables())
+            .unwrap();
+        assert_eq!(parsed.accounts.len(), 7);
+        assert_eq!(parsed.accounts[3].contents, references[0].to_string());
+    }
+
+    #[test]
+    fn test_send_transaction_with_durable_nonce() {
+        let sender = generate_key_from_mnemonic(
+            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+        );
+        let receiver = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = hash::Hash::new_unique();
+
+        let mut parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: Some(10_000),
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: Some(DurableNonce {
+                nonce_account: nonce_account.to_string(),
+                nonce_authority: sender.public_key.contents.clone(),
+                nonce: nonce.to_string(),
+            }),
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+
+        let tx = Factory
+            .send_transaction(
+                sender.public_key.clone(),
+                ChainPublicKey::new(receiver.to_string(), Blockchain::Solana),
+                DecimalNumber {
+                    value: "1".to_string(),
+                },
+                parameters.clone(),
+            )
+            .unwrap();
+        let parsed = Factory.parse_transaction(tx.clone(), vec![]).unwrap();
+        assert_eq!(
+            parsed.instructions[0],
+            SolanaInstruction::SystemAdvanceNonceAccount {
+                nonce_account: nonce_account.to_string(),
+                authority: sender.public_key.contents.clone(),
+            }
+        );
+        assert_eq!(
+            parsed.instructions[1],
+            SolanaInstruction::ComputeUnitLimit { units: 10_000 }
+        );
+
+        // Signing keeps the nonce instead of the external blockhash
+        let TransactionParameters::Solana {
+            durable_nonce,
+            jito_tip,
+            ..
+        } = &mut parameters;
+        *durable_nonce = None;
+        *jito_tip = Some(JitoTip {
+            lamports: 10_000,
+            tip_account: None,
+        });
+        let signed = Factory
+            .sign_transaction(tx, vec![sender], Some(parameters))
+            .unwrap();
+        assert!(signed.compute_budget_changes.is_empty());
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(&signed.tx).unwrap()).unwrap();
+        assert_eq!(*transaction.message.recent_blockhash(), nonce);
+        assert!(uses_durable_nonce(&transaction.message));
+        let tip = transaction.message.instructions().last().unwrap();
+        assert!(JITO_TIP_ACCOUNTS
+            .contains(&transaction.message.static_account_keys()[usize::from(tip.accounts[1])]));
+        assert!(transaction.verify_with_results().iter().all(|valid| *valid));
+    }
+
+    #[test]
+    fn test_token_transaction_with_fee_payer() {
+        let sender = generate_key_from_mnemonic(
+            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+        );
+        let receiver = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let sponsor = generate_key_from_mnemonic(
+            "coffee double wise share bridge bird raw light area exact spray dial",
+        );
+        let fee_payer = sponsor.public_key.to_solana_pubkey().unwrap();
+
+        let parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: Some(6),
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: Some(fee_payer.to_string()),
+            token_extensions: None,
+        };
+
+        let tx = Factory
+            .token_transaction(
+                TokenDestination::Wallet {
+                    public_key: ChainPublicKey::new(receiver.to_string(), Blockchain::Solana),
+                    account_exists: false,
+                },
+                sender.public_key.clone(),
+                ChainPublicKey::new(mint.to_string(), Blockchain::Solana),
+                TransactionKind::Token {
+                    amount: "1000000".into(),
+                    close_account: false,
+                },
+                parameters.clone(),
+            )
+            .unwrap();
+        let parsed = Factory.parse_transaction(tx.clone(), vec![]).unwrap();
+        assert_eq!(parsed.accounts[0].contents, fee_payer.to_string());
+        // The fee payer funds the recipient's token account as well
+        let SolanaInstruction::AssociatedTokenAccountCreate {
+            payer, idempotent, ..
+        } = &parsed.instructions[0]
+        else {
+            panic!("Expected the creation of the token account");
+        };
+        assert_eq!(*payer, fee_payer.to_string());
+        assert!(idempotent);
+        let missing_signers: Vec<_> = parsed
+            .missing_signers
+            .iter()
+            .map(|signer| signer.contents.clone())
+            .collect();
+        assert_eq!(
+            missing_signers,
+            vec![fee_payer.to_string(), sender.public_key.contents.clone()]
+        );
+
+        let signed = Factory
+            .sign_transaction(tx, vec![sender.clone()], Some(parameters))
+            .unwrap();
+        assert_eq!(signed.missing_signers.len(), 1);
+        assert_eq!(signed.missing_signers[0].contents, fee_payer.to_string());
+
+        let Err(TransactionError::Sponsorship(_)) = Factory.sign_sponsored_transaction(
+            signed.tx.clone(),
+            sponsor.clone(),
+            10_000,
+            true,
+            vec![],
+        ) else {
+            panic!("The fee and rent should exceed the maximum");
+        };
+        let Err(TransactionError::Sponsorship(_)) = Factory.sign_sponsored_transaction(
+            signed.tx.clone(),
+            sponsor.clone(),
+            u64::MAX,
+            false,
+            vec![],
+        ) else {
+            panic!("The rent should only be sponsored when allowed");
+        };
+        let Err(TransactionError::Sponsorship(_)) =
+            Factory.sign_sponsored_transaction(signed.tx.clone(), sender, u64::MAX, true, vec![])
+        else {
+            panic!("Only the fee payer should sponsor the transaction");
+        };
+        // Two signatures and the rent of a token account
+        let sponsored = Factory
+            .sign_sponsored_transaction(signed.tx, sponsor, 2_049_280, true, vec![])
+            .unwrap();
+        assert!(sponsored.missing_signers.is_empty());
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(&sponsored.tx).unwrap()).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|valid| *valid));
+    }
+
+    #[test]
+    fn test_token_transaction_with_required_memo() {
+        let owner = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        // A mint without extensions and a destination with the memo transfer extension
+        let mut mint_data = vec![0; 165];
+        mint_data.push(1);
+        let mut destination_data = vec![0; 165];
+        destination_data.extend_from_slice(&[2, 8, 0, 1, 0, 1]);
+
+        let parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: None,
+            owner_program: Some(TOKEN_2022_PROGRAM.to_string()),
+            decimals: Some(6),
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: Some(1_000),
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: Some(TokenExtensionAccounts {
+                mint_data: to_base64(mint_data),
+                destination_data: Some(to_base64(destination_data)),
+                extra_account_metas_data: None,
+                epoch: 0,
+            }),
+        };
+        let tx = Factory
+            .token_transaction(
+                TokenDestination::Account {
+                    transfer_destination: destination.to_string(),
+                },
+                ChainPublicKey::new(owner.to_string(), Blockchain::Solana),
+                ChainPublicKey::new(mint.to_string(), Blockchain::Solana),
+                TransactionKind::Token {
+                    amount: "1000000".into(),
+                    close_account: false,
+                },
+                parameters,
+            )
+            .unwrap();
+        let transaction: Transaction = bincode::deserialize(&from_base64(&tx).unwrap()).unwrap();
+        let instructions = &transaction.message.instructions;
+        let program = |index: usize| {
+            transaction.message.account_keys[usize::from(instructions[index].program_id_index)]
+        };
+        let memo = instructions.len() - 2;
+        assert_eq!(program(memo), spl_memo::id());
+        assert!(instructions[memo].data.is_empty());
+        assert_eq!(program(memo + 1).to_string(), TOKEN_2022_PROGRAM);
+    }
+
+    #[test]
+    fn test_partially_sign_transaction_with_jito_tip() {
+        let sender = generate_key_from_mnemonic(
+            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+        );
+        let sponsor = generate_key_from_mnemonic(
+            "coffee double wise share bridge bird raw light area exact spray dial",
+        );
+        let fee_payer = sponsor.public_key.to_solana_pubkey().unwrap();
+        let receiver = Pubkey::new_unique();
+
+        let mut parameters = TransactionParameters::Solana {
             transaction_type: SolanaTransactionType::Legacy,
             external_address: Some(ExternalAddress {
                 recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
@@ -1643,72 +2862,199 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: Some(fee_payer.to_string()),
+            token_extensions: None,
         };
-
-        let signed_tx = Factory
+        let tx = Factory
             .send_transaction(
-                sender_key.public_key,
-                receiver,
-                DecimalNumber {
-                    value: "0.000001001".to_string(),
-                },
-                payload,
+                sender.public_key.clone(),
+                ChainPublicKey::new(receiver.to_string(), Blockchain::Solana),
+                "1000".into(),
+                parameters.clone(),
             )
             .unwrap();
+        // The sponsor signs first, then the tip and compute budget change the message
+        let sponsored = Factory
+            .sign_transaction(tx, vec![sponsor], Some(parameters.clone()))
+            .unwrap();
+        assert_eq!(sponsored.missing_signers.len(), 1);
 
-        assert_eq!(
-            signed_tx,
-            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAEDDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9h/wdX3dkomxgJlLY6GGcOFWkAa84Cr5zlHAzBgnBfY4QAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAcSF7ZDDOSxTErZg5qfh/hsKRZlwnN81vnzITudOZjnUBAgIAAQwCAAAA6QMAAAAAAAA="
-        )
+        let TransactionParameters::Solana {
+            jito_tip,
+            compute_budget_unit_price,
+            ..
+        } = &mut parameters;
+        *jito_tip = Some(JitoTip {
+            lamports: 10_000,
+            tip_account: None,
+        });
+        *compute_budget_unit_price = Some(1_000);
+        let signed = Factory
+            .sign_transaction(sponsored.tx, vec![sender], Some(parameters))
+            .unwrap();
+        assert!(!signed.compute_budget_changes.is_empty());
+        let missing_signers: Vec<_> = signed
+            .missing_signers
+            .iter()
+            .map(|signer| signer.contents.clone())
+            .collect();
+        assert_eq!(missing_signers, vec![fee_payer.to_string()]);
+
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(&signed.tx).unwrap()).unwrap();
+        let tip = transaction.message.instructions().last().unwrap();
+        assert!(JITO_TIP_ACCOUNTS
+            .contains(&transaction.message.static_account_keys()[usize::from(tip.accounts[1])]));
+        assert_eq!(transaction.signatures[0], Signature::default());
+        assert_eq!(transaction.verify_with_results(), vec![false, true]);
     }
 
     #[test]
-    fn test_send_transaction_amount_one() {
-        let sender_key = generate_key_from_mnemonic(
-            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+    fn test_missing_signers_of_malformed_header() {
+        let payer = Pubkey::new_unique();
+        let mut message = Message::new(
+            &[system_instruction::transfer(&payer, &Pubkey::new_unique(), 1)],
+            Some(&payer),
         );
+        message.header.num_required_signatures = u8::MAX;
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let missing_signers: Vec<_> = missing_signers(&transaction)
+            .into_iter()
+            .map(|signer| signer.contents)
+            .collect();
+        assert_eq!(missing_signers.len(), 3);
+        assert_eq!(missing_signers[0], payer.to_string());
+    }
+
+    #[test]
+    fn test_create_nonce_account_transaction() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let tx = Factory
+            .nonce_account_transaction(
+                NonceAccountAction::Create {
+                    payer: ChainPublicKey::new(payer.to_string(), Blockchain::Solana),
+                    nonce_account: ChainPublicKey::new(nonce_account.to_string(), Blockchain::Solana),
+                    nonce_authority: ChainPublicKey::new(payer.to_string(), Blockchain::Solana),
+                },
+                TransactionParameters::Solana {
+                    transaction_type: SolanaTransactionType::Legacy,
+                    external_address: None,
+                    owner_program: None,
+                    decimals: None,
+                    memo: None,
+                    references: Vec::new(),
+                    swap_slippage_bps: None,
+                    compute_budget_unit_limit: None,
+                    loaded_accounts_data_size_limit: None,
+                    compute_budget_unit_price: None,
+                    address_lookup_tables: Vec::new(),
+                    durable_nonce: None,
+                    jito_tip: None,
+                    fee_payer: None,
+                    token_extensions: None,
+                },
+            )
+            .unwrap();
 
+        let parsed = Factory.parse_transaction(tx, vec![]).unwrap();
+        assert_eq!(parsed.signatures.unwrap().len(), 2);
         assert_eq!(
-            sender_key.public_key.contents,
-            "sDaZSSKL8BPeAduGRcTudB6Brz5EdxfqUDyVJHr5EAB"
+            parsed.instructions[0],
+            SolanaInstruction::SystemCreateAccount {
+                from: payer.to_string(),
+                new_account: nonce_account.to_string(),
+                lamports: 1_447_680,
+                space: 80,
+                owner: "11111111111111111111111111111111".to_string(),
+            }
         );
-        assert_eq!(sender_key.contents, "4oMuZhcmihwvCpZXGbPbyXVgBzeo9FTvJRBDrkY52EyR1cupGPB1tzgokZV1b983F9c5NmW1v64xPsy59g1N86zK");
-
-        let receiver = ChainPublicKey {
-            contents: "9biD1JVeWCPQpWSAGdxGZaNd6VeUm5QYQu9hp2EMnfnp".to_string(),
-            chain: Blockchain::Solana,
-        };
+    }
 
+    #[test]
+    fn test_stake_account_transaction() {
+        let funder = ChainPublicKey::new(Pubkey::new_unique().to_string(), Blockchain::Solana);
         let parameters = TransactionParameters::Solana {
             transaction_type: SolanaTransactionType::Legacy,
-            external_address: Some(ExternalAddress {
-                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
-            }),
+            external_address: None,
             owner_program: None,
             decimals: None,
             memo: None,
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        let stake_account = Factory
+            .get_stake_account_address_with_seed(funder.contents.clone(), "stake:0".to_string())
+            .unwrap();
+        let create = |stake_account: ChainPublicKey| StakeAccountAction::Create {
+            funder: funder.clone(),
+            stake_account,
+            seed: Some("stake:0".to_string()),
+            staker: funder.clone(),
+            withdrawer: funder.clone(),
+            amount: DecimalNumber {
+                value: "1.5".to_string(),
+            },
         };
 
-        let signed_tx = Factory
-            .send_transaction(
-                sender_key.public_key,
-                receiver,
-                DecimalNumber {
-                    value: "1".to_string(),
+        let tx = Factory
+            .stake_account_transaction(create(stake_account.clone()), parameters.clone())
+            .unwrap();
+        let parsed = Factory.parse_transaction(tx, vec![]).unwrap();
+        // Seeded accounts only need the signature of the funder
+        assert_eq!(parsed.signatures.unwrap().len(), 1);
+        assert_eq!(
+            parsed.instruction_programs,
+            vec![
+                "11111111111111111111111111111111".to_string(),
+                "Stake11111111111111111111111111111111111111".to_string(),
+            ]
+        );
+
+        let other_account = ChainPublicKey::new(Pubkey::new_unique().to_string(), Blockchain::Solana);
+        assert!(Factory
+            .stake_account_transaction(create(other_account.clone()), parameters.clone())
+            .is_err());
+
+        let tx = Factory
+            .stake_account_transaction(
+                StakeAccountAction::Split {
+                    stake_account,
+                    staker: funder.clone(),
+                    split_stake_account: other_account,
+                    amount: DecimalNumber {
+                        value: "0.5".to_string(),
+                    },
                 },
                 parameters,
             )
             .unwrap();
-
+        let parsed = Factory.parse_transaction(tx, vec![]).unwrap();
+        assert_eq!(parsed.signatures.unwrap().len(), 2);
         assert_eq!(
-            signed_tx,
-            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAEDDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9h/wdX3dkomxgJlLY6GGcOFWkAa84Cr5zlHAzBgnBfY4QAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAcSF7ZDDOSxTErZg5qfh/hsKRZlwnN81vnzITudOZjnUBAgIAAQwCAAAAAMqaOwAAAAA="
-        )
+            parsed.instructions[0],
+            SolanaInstruction::SystemTransfer {
+                from: funder.contents,
+                to: parsed.accounts[1].contents.clone(),
+                lamports: 2_282_880,
+            }
+        );
     }
 
     #[test]
@@ -1727,6 +3073,7 @@ mod tests {
                 contents: "sDaZSSKL8BPeAduGRcTudB6Brz5EdxfqUDyVJHr5EAB".to_string(),
                 chain: Blockchain::Solana,
             },
+            account_exists: false,
         };
 
         let token_mint = ChainPublicKey {
@@ -1750,18 +3097,49 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_tx = Factory
             .token_transaction(
                 receiver_wallet,
+                owner_key.public_key.clone(),
+                token_mint.clone(),
+                transaction_kind.clone(),
+                parameters.clone(),
+            )
+            .unwrap();
+
+        // A known account skips the creation of the associated token account
+        let existing_account = TokenDestination::Wallet {
+            public_key: ChainPublicKey {
+                contents: "sDaZSSKL8BPeAduGRcTudB6Brz5EdxfqUDyVJHr5EAB".to_string(),
+                chain: Blockchain::Solana,
+            },
+            account_exists: true,
+        };
+        let transaction = Factory
+            .token_transaction(
+                existing_account,
                 owner_key.public_key,
                 token_mint,
                 transaction_kind,
                 parameters,
             )
             .unwrap();
+        let transaction: Transaction =
+            bincode::deserialize(&from_base64(&transaction).unwrap()).unwrap();
+        assert_eq!(transaction.message.instructions.len(), 1);
+        assert!(!transaction
+            .message
+            .account_keys
+            .contains(&spl_associated_token_account::id()));
 
         // TODO
         // The output does differ but upon inspection still generates a valid signed TX
@@ -1775,7 +3153,7 @@ mod tests {
 
         assert_eq!(
             signed_tx,
-            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAUIf8HV93ZKJsYCZS2OhhnDhVpAGvOAq+c5RwMwYJwX2OHYwlbgG1W3PhF+t7bT+eF63FS+MFT8wfnf5PQkZLoo2eBnCBuplHILX5svG7vBAH64rRZxRrLeWp00k7XmAa4LAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFLszmR+Ru7+6ZZCcu/til5uK/NREBP7W84x7JjaqQAgbd9uHXZaGT2cvhRs7reawctIXtX1s3kTqM9YV+/wCpDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9iMlyWPTiSJ8bs9ECkUjg2DC1oTmdr/EIQEjnvY2+n4WakcpMKhhgAbsC4s1odQyWYl+CzpWbBhFKk81ABxqoyJAgcGAAIGBAMFAQAFBQEEAgAACgwBAAAAAAAAAAA="
+            "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABAAUIf8HV93ZKJsYCZS2OhhnDhVpAGvOAq+c5RwMwYJwX2OHYwlbgG1W3PhF+t7bT+eF63FS+MFT8wfnf5PQkZLoo2eBnCBuplHILX5svG7vBAH64rRZxRrLeWp00k7XmAa4LAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFLszmR+Ru7+6ZZCcu/til5uK/NREBP7W84x7JjaqQAgbd9uHXZaGT2cvhRs7reawctIXtX1s3kTqM9YV+/wCpDN1DXJdZtFaZvlIgEqCo94Hfe4zs/k+7zpFgpos4O9iMlyWPTiSJ8bs9ECkUjg2DC1oTmdr/EIQEjnvY2+n4WakcpMKhhgAbsC4s1odQyWYl+CzpWbBhFKk81ABxqoyJAgcGAAIGBAMFAQEFBQEEAgAACgwBAAAAAAAAAAA="
         )
     }
 
@@ -1815,7 +3193,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_tx = Factory
@@ -1870,7 +3254,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_base64_tx = Factory
@@ -1907,7 +3297,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_base64_tx = Factory
@@ -1944,7 +3340,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_base64_tx = Factory
@@ -1994,7 +3396,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_base64_tx = Factory
@@ -2044,7 +3452,13 @@ mod tests {
             references: Vec::new(),
             swap_slippage_bps: None,
             compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
             compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
         };
 
         let signed_base64_tx = Factory
@@ -2344,7 +3758,7 @@ mod tests {
 
         // Parse the transaction and check the result
         let parsed = Factory
-            .parse_transaction(base_64_signed_tx.clone())
+            .parse_transaction(base_64_signed_tx.clone(), vec![])
             .unwrap();
 
         // Check tx matches input
@@ -2371,6 +3785,574 @@ mod tests {
         assert!(parsed
             .instruction_programs
             .contains(&"11111111111111111111111111111111".to_string()));
+
+        // Check decoded instructions
+        assert_eq!(parsed.instructions.len(), parsed.instruction_programs.len());
+        assert_eq!(
+            parsed.instructions[0],
+            SolanaInstruction::ComputeUnitLimit { units: 300_000 }
+        );
+    }
+
+    #[test]
+    fn test_modify_transaction() {
+        let owner = generate_key_from_mnemonic(
+            "elegant flat lumber sibling peace convince manage logic crunch pair impact bench",
+        );
+        let receiver = ChainPublicKey {
+            contents: "9biD1JVeWCPQpWSAGdxGZaNd6VeUm5QYQu9hp2EMnfnp".to_string(),
+            chain: Blockchain::Solana,
+        };
+
+        let payload = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "8ccgXYvhnTaqz2uTcurv9x9PshA714QzqPSxCesyMgng".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        let unsigned_tx = Factory
+            .send_transaction(
+                owner.public_key.clone(),
+                receiver,
+                DecimalNumber::from("0.1"),
+                payload,
+            )
+            .unwrap();
+
+        let reference = "HhjkkWaHbMLLve8mmRsvpVkPQ8hz8Dt5BvXA5y7S92Hz";
+        let parameters = TransactionParameters::Solana {
+            transaction_type: SolanaTransactionType::Legacy,
+            external_address: Some(ExternalAddress {
+                recent_blockhash: "7tundXorVXYY2cjaBq8WDDLAz3X7AhNJsZpyGMPAbU7h".to_string(),
+            }),
+            owner_program: None,
+            decimals: None,
+            memo: Some("refreshed".to_string()),
+            references: vec![reference.to_string()],
+            swap_slippage_bps: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            compute_budget_unit_price: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        let modified_tx = Factory
+            .modify_transaction(unsigned_tx, owner, parameters)
+            .unwrap();
+
+        let transaction: VersionedTransaction =
+            bincode::deserialize(&from_base64(&modified_tx).unwrap()).unwrap();
+        assert!(transaction.verify_with_results().iter().all(|valid| *valid));
+        assert_eq!(
+            transaction.message.recent_blockhash().to_string(),
+            "7tundXorVXYY2cjaBq8WDDLAz3X7AhNJsZpyGMPAbU7h"
+        );
+
+        let account_keys = transaction.message.static_account_keys();
+        let instructions = transaction.message.instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            account_keys[instructions[1].program_id_index as usize],
+            spl_memo::id()
+        );
+        assert_eq!(instructions[1].data, b"refreshed".to_vec());
+
+        let transfer_accounts = &instructions[0].accounts;
+        assert_eq!(transfer_accounts.len(), 3);
+        assert_eq!(
+            account_keys[transfer_accounts[2] as usize].to_string(),
+            reference
+        );
+    }
+
+    #[test]
+    fn test_analyze_transaction() {
+        let owner = Pubkey::from_str("F7xVyQuLzvyUKbMQyrBHaqYGCzHWpmsocn8b7oRUyeC5").unwrap();
+        let token_account = Pubkey::new_unique();
+        let attacker = Pubkey::new_unique();
+        let trusted = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let unknown_program = Pubkey::new_unique();
+
+        let instructions = vec![
+            system_instruction::advance_nonce_account(&nonce_account, &owner),
+            spl_token::instruction::set_authority(
+                &spl_token::id(),
+                &token_account,
+                Some(&attacker),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                &owner,
+                &[],
+            )
+            .unwrap(),
+            spl_token::instruction::approve(
+                &spl_token::id(),
+                &token_account,
+                &attacker,
+                &owner,
+                &[],
+                1_000,
+            )
+            .unwrap(),
+            spl_token::instruction::approve(
+                &spl_token::id(),
+                &token_account,
+                &trusted,
+                &owner,
+                &[],
+                1_000,
+            )
+            .unwrap(),
+            spl_token::instruction::close_account(
+                &spl_token::id(),
+                &token_account,
+                &attacker,
+                &owner,
+                &[],
+            )
+            .unwrap(),
+            system_instruction::assign(&owner, &unknown_program),
+            Instruction::new_with_bytes(unknown_program, &[0], vec![]),
+        ];
+        let mut message = Message::new(&instructions, Some(&owner));
+        let transaction = Transaction::new_unsigned(message.clone());
+        let transaction = to_base64(bincode::serialize(&transaction).unwrap());
+
+        let allowed_programs = vec![
+            "11111111111111111111111111111111".to_string(),
+            SPL_PROGRAM.to_string(),
+        ];
+        let warnings = Factory
+            .analyze_transaction(
+                transaction,
+                ChainPublicKey::new(owner.to_string(), Blockchain::Solana),
+                Some(allowed_programs),
+                vec![trusted.to_string()],
+                vec![],
+            )
+            .unwrap();
+
+        assert_eq!(
+            warnings,
+            vec![
+                TransactionWarning::DurableNonce {
+                    nonce_account: nonce_account.to_string()
+                },
+                TransactionWarning::SetAuthority {
+                    account: token_account.to_string(),
+                    authority_type: "AccountOwner".to_string(),
+                    new_authority: Some(attacker.to_string()),
+                },
+                TransactionWarning::Approve {
+                    source: token_account.to_string(),
+                    delegate: attacker.to_string(),
+                    amount: 1_000,
+                },
+                TransactionWarning::CloseAccountToForeignWallet {
+                    account: token_account.to_string(),
+                    destination: attacker.to_string(),
+                },
+                TransactionWarning::AssignOwner {
+                    account: owner.to_string(),
+                    new_owner: unknown_program.to_string(),
+                },
+                TransactionWarning::UnknownProgram {
+                    program: unknown_program.to_string(),
+                },
+            ]
+        );
+
+        message.instructions[0].program_id_index = u8::MAX;
+        let transaction = Transaction::new_unsigned(message);
+        let Err(TransactionError::Parameters(_)) = Factory.analyze_transaction(
+            to_base64(bincode::serialize(&transaction).unwrap()),
+            ChainPublicKey::new(owner.to_string(), Blockchain::Solana),
+            None,
+            vec![],
+            vec![],
+        ) else {
+            panic!("A program outside of the account keys should fail");
+        };
+    }
+
+    #[test]
+    fn test_estimate_balance_changes() {
+        let owner = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination = spl_associated_token_account::get_associated_token_address(&receiver, &mint);
+
+        let instructions = vec![
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            system_instruction::transfer(&owner, &receiver, 1_000_000),
+            create_associated_token_account(&owner, &receiver, &mint),
+            spl_token::instruction::transfer_checked(
+                &spl_token::id(),
+                &source,
+                &mint,
+                &destination,
+                &owner,
+                &[],
+                500,
+                6,
+            )
+            .unwrap(),
+        ];
+        let transaction = Transaction::new_unsigned(Message::new(&instructions, Some(&owner)));
+        let transaction = to_base64(bincode::serialize(&transaction).unwrap());
+
+        let accounts = vec![TokenAccountSnapshot {
+            address: source.to_string(),
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            lamports: None,
+        }];
+        let changes = Factory
+            .estimate_balance_changes(transaction, accounts, vec![])
+            .unwrap();
+
+        // 3 instructions without a limit use 600_000 units at 1_000 micro lamports
+        let fee = 5_000 + 600;
+        assert_eq!(
+            changes,
+            vec![
+                BalanceChange {
+                    owner: owner.to_string(),
+                    mint: None,
+                    amount: -(fee + 1_000_000 + 2_039_280),
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: receiver.to_string(),
+                    mint: None,
+                    amount: 1_000_000,
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: owner.to_string(),
+                    mint: Some(mint.to_string()),
+                    amount: -500,
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: receiver.to_string(),
+                    mint: Some(mint.to_string()),
+                    amount: 500,
+                    is_estimate: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimate_balance_changes_with_transfer_fee() {
+        let owner = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let token_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+
+        // `TransferCheckedWithFee` of 500 tokens withholding 5 of them
+        let mut data = vec![26, 1];
+        data.extend_from_slice(&500u64.to_le_bytes());
+        data.push(6);
+        data.extend_from_slice(&5u64.to_le_bytes());
+        let transfer = Instruction::new_with_bytes(
+            token_program,
+            &data,
+            vec![
+                AccountMeta::new(source, false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(destination, false),
+                AccountMeta::new_readonly(owner, true),
+            ],
+        );
+        let transaction = Transaction::new_unsigned(Message::new(&[transfer], Some(&owner)));
+        let transaction = to_base64(bincode::serialize(&transaction).unwrap());
+
+        let account = |address: &Pubkey, owner: &Pubkey| TokenAccountSnapshot {
+            address: address.to_string(),
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            lamports: None,
+        };
+        let changes = Factory
+            .estimate_balance_changes(
+                transaction,
+                vec![account(&source, &owner), account(&destination, &receiver)],
+                vec![],
+            )
+            .unwrap();
+        let token_changes: Vec<_> = changes
+            .iter()
+            .filter(|change| change.mint.is_some())
+            .map(|change| (change.owner.clone(), change.amount))
+            .collect();
+        assert_eq!(
+            token_changes,
+            vec![(owner.to_string(), -500), (receiver.to_string(), 495)]
+        );
+    }
+
+    #[test]
+    fn test_estimate_balance_changes_with_lookup_table() {
+        let owner = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let table_address = Pubkey::new_unique();
+
+        let transfer = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            &mint,
+            &destination,
+            &owner,
+            &[],
+            500,
+            6,
+        )
+        .unwrap();
+        let lookup_table = solana_sdk::address_lookup_table::AddressLookupTableAccount {
+            key: table_address,
+            addresses: vec![source, mint, destination],
+        };
+        let message =
+            v0::Message::try_compile(&owner, &[transfer], &[lookup_table], hash::Hash::default())
+                .unwrap();
+        assert_eq!(message.account_keys.len(), 2);
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(message),
+        };
+        let transaction = to_base64(bincode::serialize(&transaction).unwrap());
+        let table_data = solana_sdk::address_lookup_table::state::AddressLookupTable {
+            meta: Default::default(),
+            addresses: std::borrow::Cow::Owned(vec![source, mint, destination]),
+        }
+        .serialize_for_tests()
+        .unwrap();
+
+        let account = |address: &Pubkey, owner: &Pubkey| TokenAccountSnapshot {
+            address: address.to_string(),
+            owner: owner.to_string(),
+            mint: mint.to_string(),
+            lamports: None,
+        };
+        let changes = Factory
+            .estimate_balance_changes(
+                transaction,
+                vec![account(&source, &owner), account(&destination, &receiver)],
+                vec![AddressLookupTableData {
+                    address: table_address.to_string(),
+                    data: to_base64(table_data),
+                }],
+            )
+            .unwrap();
+        let token_changes: Vec<_> = changes
+            .iter()
+            .filter(|change| change.mint.is_some())
+            .map(|change| (change.owner.clone(), change.amount))
+            .collect();
+        assert_eq!(
+            token_changes,
+            vec![(owner.to_string(), -500), (receiver.to_string(), 500)]
+        );
+    }
+
+    #[test]
+    fn test_estimate_balance_changes_when_closing_accounts() {
+        let owner = Pubkey::new_unique();
+        let native_mint = spl_token::native_mint::id();
+        let mint = Pubkey::new_unique();
+        let wrapped_account =
+            spl_associated_token_account::get_associated_token_address(&owner, &native_mint);
+        let token_account =
+            spl_associated_token_account::get_associated_token_address(&owner, &mint);
+
+        let instructions: Vec<_> = [wrapped_account, token_account]
+            .iter()
+            .map(|account| {
+                spl_token::instruction::close_account(
+                    &spl_token::id(),
+                    account,
+                    &owner,
+                    &owner,
+                    &[],
+                )
+                .unwrap()
+            })
+            .collect();
+        let transaction = Transaction::new_unsigned(Message::new(&instructions, Some(&owner)));
+        let transaction = to_base64(bincode::serialize(&transaction).unwrap());
+
+        let accounts = vec![
+            TokenAccountSnapshot {
+                address: wrapped_account.to_string(),
+                owner: owner.to_string(),
+                mint: native_mint.to_string(),
+                lamports: Some(2_039_280 + 1_000_000),
+            },
+            // Without its lamports the account is assumed to only hold its rent
+            TokenAccountSnapshot {
+                address: token_account.to_string(),
+                owner: owner.to_string(),
+                mint: mint.to_string(),
+                lamports: None,
+            },
+        ];
+        let changes = Factory
+            .estimate_balance_changes(transaction, accounts, vec![])
+            .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                BalanceChange {
+                    owner: owner.to_string(),
+                    mint: None,
+                    amount: -5_000 + 2_039_280 + 1_000_000 + 2_039_280,
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: owner.to_string(),
+                    mint: Some(native_mint.to_string()),
+                    amount: -1_000_000,
+                    is_estimate: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimate_balance_changes_of_jupiter_route() {
+        let user = Pubkey::new_unique();
+        let input_mint = Pubkey::new_unique();
+        let output_mint = Pubkey::new_unique();
+        let pool = Pubkey::new_unique();
+        let quote = format!(
+            r#"{{
+                "inputMint": "{input_mint}",
+                "inAmount": "1000",
+                "outputMint": "{output_mint}",
+                "outAmount": "500",
+                "otherAmountThreshold": "497",
+                "swapMode": "ExactIn",
+                "slippageBps": 50,
+                "routePlan": [{{
+                    "swapInfo": {{
+                        "ammKey": "{pool}",
+                        "label": "Meteora DLMM",
+                        "inputMint": "{input_mint}",
+                        "outputMint": "{output_mint}"
+                    }},
+                    "percent": 100
+                }}]
+            }}"#
+        );
+        let legs = vec![JupiterSwapLeg {
+            // `Swap::MeteoraDlmm`
+            swap: to_base64([38]),
+            accounts: vec![TransactionAccount {
+                address: pool.to_string(),
+                is_signer: false,
+                is_writable: true,
+            }],
+        }];
+        let options = JupiterSwapOptions {
+            wrap_and_unwrap_sol: false,
+            destination_token_account: None,
+            fee_account: None,
+            referral_account: None,
+            input_token_program: None,
+            output_token_program: None,
+            intermediate_token_2022_mints: Vec::new(),
+        };
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Versioned,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: None,
+            compute_budget_unit_limit: None,
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        let transaction = Factory
+            .jupiter_swap_transaction(
+                quote,
+                ChainPublicKey::new(user.to_string(), Blockchain::Solana),
+                legs,
+                options,
+                parameters,
+            )
+            .unwrap();
+
+        let account = |mint: &Pubkey| TokenAccountSnapshot {
+            address: spl_associated_token_account::get_associated_token_address(&user, mint)
+                .to_string(),
+            owner: user.to_string(),
+            mint: mint.to_string(),
+            lamports: None,
+        };
+        let changes = Factory
+            .estimate_balance_changes(
+                transaction,
+                vec![account(&input_mint), account(&output_mint)],
+                vec![],
+            )
+            .unwrap();
+
+        // The input is spent exactly, the output is the quote minus the slippage
+        assert_eq!(
+            changes,
+            vec![
+                BalanceChange {
+                    owner: user.to_string(),
+                    mint: None,
+                    amount: -5_000,
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: user.to_string(),
+                    mint: Some(input_mint.to_string()),
+                    amount: -1_000,
+                    is_estimate: false,
+                },
+                BalanceChange {
+                    owner: user.to_string(),
+                    mint: Some(output_mint.to_string()),
+                    amount: 497,
+                    is_estimate: true,
+                },
+            ]
+        );
     }
 
     #[test]
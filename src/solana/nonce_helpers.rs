@@ -0,0 +1,155 @@
+//! Durable transaction nonces, see https://solana.com/developers/guides/advanced/introduction-to-durable-nonces
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    nonce::state::{State, Versions},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::{self, SystemInstruction},
+    system_program, sysvar,
+};
+
+use super::sol_to_lamports;
+use super::types::{NonceAccount, NonceAccountAction};
+use crate::errors::*;
+
+/// Returns the instructions of `action` and the account paying for the transaction
+pub(crate) fn nonce_account_instructions(
+    action: &NonceAccountAction,
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    match action {
+        NonceAccountAction::Create {
+            payer,
+            nonce_account,
+            nonce_authority,
+        } => {
+            let payer = payer.to_solana_pubkey()?;
+            let instructions = system_instruction::create_nonce_account(
+                &payer,
+                &nonce_account.to_solana_pubkey()?,
+                &nonce_authority.to_solana_pubkey()?,
+                Rent::default().minimum_balance(State::size()),
+            );
+            Ok((instructions, payer))
+        }
+        NonceAccountAction::Initialize {
+            payer,
+            nonce_account,
+            nonce_authority,
+        } => {
+            // `system_instruction` only offers this combined with the account creation
+            #[allow(deprecated)]
+            let instruction = Instruction::new_with_bincode(
+                system_program::id(),
+                &SystemInstruction::InitializeNonceAccount(nonce_authority.to_solana_pubkey()?),
+                vec![
+                    AccountMeta::new(nonce_account.to_solana_pubkey()?, false),
+                    AccountMeta::new_readonly(sysvar::recent_blockhashes::id(), false),
+                    AccountMeta::new_readonly(sysvar::rent::id(), false),
+                ],
+            );
+            Ok((vec![instruction], payer.to_solana_pubkey()?))
+        }
+        NonceAccountAction::Authorize {
+            nonce_account,
+            nonce_authority,
+            new_authority,
+        } => {
+            let nonce_authority = nonce_authority.to_solana_pubkey()?;
+            let instruction = system_instruction::authorize_nonce_account(
+                &nonce_account.to_solana_pubkey()?,
+                &nonce_authority,
+                &new_authority.to_solana_pubkey()?,
+            );
+            Ok((vec![instruction], nonce_authority))
+        }
+        NonceAccountAction::Advance {
+            nonce_account,
+            nonce_authority,
+        } => {
+            let nonce_authority = nonce_authority.to_solana_pubkey()?;
+            let instruction = system_instruction::advance_nonce_account(
+                &nonce_account.to_solana_pubkey()?,
+                &nonce_authority,
+            );
+            Ok((vec![instruction], nonce_authority))
+        }
+        NonceAccountAction::Withdraw {
+            nonce_account,
+            nonce_authority,
+            recipient,
+            amount,
+        } => {
+            let nonce_authority = nonce_authority.to_solana_pubkey()?;
+            let instruction = system_instruction::withdraw_nonce_account(
+                &nonce_account.to_solana_pubkey()?,
+                &nonce_authority,
+                &recipient.to_solana_pubkey()?,
+                sol_to_lamports(amount)?,
+            );
+            Ok((vec![instruction], nonce_authority))
+        }
+    }
+}
+
+/// Decodes the data of a nonce account
+pub(crate) fn parse_nonce_account_data(data: &[u8]) -> Result<NonceAccount, TransactionError> {
+    let versions: Versions =
+        bincode::deserialize(data).map_err(TransactionError::parsing_failure)?;
+    match versions.state() {
+        State::Initialized(data) => Ok(NonceAccount {
+            authority: data.authority.to_string(),
+            nonce: data.blockhash().to_string(),
+            lamports_per_signature: data.get_lamports_per_signature(),
+        }),
+        State::Uninitialized => Err(TransactionError::parameters(
+            "Nonce account is not initialized",
+        )),
+    }
+}
+
+/// Whether the message uses a durable nonce, i.e. starts with `AdvanceNonceAccount`.
+/// The blockhash of such a message is the nonce and must not be replaced.
+pub(crate) fn uses_durable_nonce(message: &VersionedMessage) -> bool {
+    let Some(instruction) = message.instructions().first() else {
+        return false;
+    };
+    message
+        .static_account_keys()
+        .get(usize::from(instruction.program_id_index))
+        .is_some_and(|program_id| *program_id == system_program::id())
+        && matches!(
+            bincode::deserialize::<SystemInstruction>(&instruction.data),
+            Ok(SystemInstruction::AdvanceNonceAccount)
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{hash::Hash, nonce::state::DurableNonce};
+
+    #[test]
+    fn test_parse_nonce_account_data() {
+        let authority = Pubkey::new_unique();
+        let durable_nonce = DurableNonce::from_blockhash(&Hash::new_unique());
+        let data = bincode::serialize(&Versions::new(State::new_initialized(
+            &authority,
+            durable_nonce,
+            5_000,
+        )))
+        .unwrap();
+
+        assert_eq!(
+            parse_nonce_account_data(&data).unwrap(),
+            NonceAccount {
+                authority: authority.to_string(),
+                nonce: durable_nonce.as_hash().to_string(),
+                lamports_per_signature: 5_000,
+            }
+        );
+
+        let data = bincode::serialize(&Versions::new(State::Uninitialized)).unwrap();
+        assert!(parse_nonce_account_data(&data).is_err());
+    }
+}
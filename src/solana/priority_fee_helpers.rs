@@ -1,76 +1,169 @@
+//! Compute budget instructions of the transactions we build, and of the ones we only
+//! sign, which often come with their own (e.g. from the Jupiter API)
 use solana_sdk::{
-    compute_budget::{ ComputeBudgetInstruction, self },
+    compute_budget::{self, ComputeBudgetInstruction},
     instruction::Instruction,
-    instruction::CompiledInstruction,
     message::VersionedMessage,
-    pubkey::Pubkey,
-    transaction::VersionedTransaction,
 };
-use bincode::serialize;
 
-pub(crate) fn add_compute_unit_limit(transaction: &mut VersionedTransaction, units: u32) {
-    add(ComputeBudgetInstruction::SetComputeUnitLimit(units), transaction);
-}
+use super::instruction_helpers::decode_compute_budget_instruction;
+use super::message_helpers::{compile_instruction, message_parts_mut};
+use super::nonce_helpers::uses_durable_nonce;
+use super::types::ComputeBudgetChange;
+use crate::errors::*;
+use crate::types::TransactionParameters;
 
-pub(crate) fn add_compute_unit_price(transaction: &mut VersionedTransaction, price: u64) {
-    add(ComputeBudgetInstruction::SetComputeUnitPrice(price), transaction);
+/// Compute budget instructions requested by `parameters`, to put at the start of a new transaction
+pub(crate) fn compute_budget_instructions(parameters: &TransactionParameters) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    if let Some(unit_limit) = parameters.compute_budget_unit_limit() {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(unit_limit));
+    }
+    if let Some(unit_price) = parameters.compute_budget_unit_price() {
+        instructions.push(ComputeBudgetInstruction::set_compute_unit_price(unit_price));
+    }
+    if let Some(bytes) = parameters.loaded_accounts_data_size_limit() {
+        instructions.push(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(bytes));
+    }
+    instructions
 }
 
-fn add(compute_budget_instruction: ComputeBudgetInstruction, transaction: &mut VersionedTransaction) {
-    // Convert ComputeBudgetInstruction to Instruction
-    let instruction = Instruction::new_with_bincode(
-        compute_budget::id(),
-        &compute_budget_instruction,
-        vec![],
-    );
-
-    // Extract account keys and find or add the program ID index
-    let (account_keys, program_id_index) = match &mut transaction.message {
-        VersionedMessage::Legacy(legacy_message) => {
-            if let Some(index) = find_account_key_index(&legacy_message.account_keys, &compute_budget::id()) {
-                (&legacy_message.account_keys, index)
-            } else {
-                legacy_message.account_keys.push(compute_budget::id());
-                let index = (legacy_message.account_keys.len() - 1) as u8;
-                (&legacy_message.account_keys, index)
-            }
+/// Adds the compute budget `instructions` to an existing message. Instructions the
+/// message already has are replaced, as the runtime rejects duplicates.
+pub(crate) fn set_compute_budget(
+    message: &mut VersionedMessage,
+    instructions: &[Instruction],
+) -> Result<Vec<ComputeBudgetChange>, TransactionError> {
+    let mut changes = Vec::new();
+    for instruction in instructions {
+        if let Some(change) = upsert(message, instruction)? {
+            changes.push(change);
         }
-        VersionedMessage::V0(v0_message) => {
-            if let Some(index) = find_account_key_index(&v0_message.account_keys, &compute_budget::id()) {
-                (&v0_message.account_keys, index)
-            } else {
-                v0_message.account_keys.push(compute_budget::id());
-                let index = (v0_message.account_keys.len() - 1) as u8;
-                (&v0_message.account_keys, index)
-            }
-        }
-    };
+    }
+    Ok(changes)
+}
+
+fn upsert(
+    message: &mut VersionedMessage,
+    instruction: &Instruction,
+) -> Result<Option<ComputeBudgetChange>, TransactionError> {
+    let current = decode_compute_budget_instruction(&instruction.data)
+        .ok_or_else(|| TransactionError::generic("Unsupported compute budget instruction"))?;
 
-    // Manually convert Instruction to CompiledInstruction
-    let compiled_instruction = CompiledInstruction {
-        program_id_index,
-        accounts: instruction
-            .accounts
-            .iter()
-            .map(|meta| {
-                find_account_key_index(account_keys, &meta.pubkey)
-                    .expect("Account key not found in account keys")
-            })
-            .collect(),
-        data: serialize(&instruction.data).unwrap(),
+    let account_keys = message.static_account_keys().to_vec();
+    let is_compute_budget = |program_id_index: u8| {
+        account_keys.get(usize::from(program_id_index)) == Some(&compute_budget::id())
     };
+    let compiled_instructions = message_parts_mut(message).2;
 
-    match &mut transaction.message {
-        VersionedMessage::Legacy(legacy_message) => {
-            legacy_message.instructions.insert(0, compiled_instruction);
-        }
-        VersionedMessage::V0(v0_message) => {
-            v0_message.instructions.insert(0, compiled_instruction);
+    let existing = compiled_instructions.iter_mut().find(|compiled| {
+        is_compute_budget(compiled.program_id_index)
+            && compiled.data.first() == instruction.data.first()
+    });
+    if let Some(existing) = existing {
+        if existing.data == instruction.data {
+            return Ok(None);
         }
+        let previous = decode_compute_budget_instruction(&existing.data);
+        existing.data = instruction.data.clone();
+        return Ok(Some(ComputeBudgetChange { previous, current }));
     }
+
+    // Behind the other compute budget instructions, but never in front of the nonce advance
+    let position = match compiled_instructions
+        .iter()
+        .rposition(|compiled| is_compute_budget(compiled.program_id_index))
+    {
+        Some(index) => index + 1,
+        None => usize::from(uses_durable_nonce(message)),
+    };
+    let compiled_instruction = compile_instruction(message, instruction)?;
+    message_parts_mut(message)
+        .2
+        .insert(position, compiled_instruction);
+
+    Ok(Some(ComputeBudgetChange {
+        previous: None,
+        current,
+    }))
 }
 
-// Helper function to find the index of a public key in account keys
-fn find_account_key_index(account_keys: &[Pubkey], key: &Pubkey) -> Option<u8> {
-    account_keys.iter().position(|&k| k == *key).map(|i| i as u8)
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::types::SolanaInstruction;
+    use crate::types::SolanaTransactionType;
+    use solana_sdk::{hash::Hash, message::Message, pubkey::Pubkey, system_instruction};
+
+    #[test]
+    fn test_set_compute_budget() {
+        let payer = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let message = Message::new_with_blockhash(
+            &[
+                system_instruction::advance_nonce_account(&nonce_account, &payer),
+                ComputeBudgetInstruction::set_compute_unit_price(1_000),
+                system_instruction::transfer(&payer, &Pubkey::new_unique(), 1),
+            ],
+            Some(&payer),
+            &Hash::default(),
+        );
+        let mut message = VersionedMessage::Legacy(message);
+
+        let parameters = TransactionParameters::Solana {
+            external_address: None,
+            transaction_type: SolanaTransactionType::Legacy,
+            owner_program: None,
+            decimals: None,
+            memo: None,
+            references: Vec::new(),
+            swap_slippage_bps: None,
+            compute_budget_unit_price: Some(5_000),
+            compute_budget_unit_limit: Some(50_000),
+            loaded_accounts_data_size_limit: None,
+            address_lookup_tables: Vec::new(),
+            durable_nonce: None,
+            jito_tip: None,
+            fee_payer: None,
+            token_extensions: None,
+        };
+        let compute_budget = compute_budget_instructions(&parameters);
+        let changes = set_compute_budget(&mut message, &compute_budget).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                ComputeBudgetChange {
+                    previous: None,
+                    current: SolanaInstruction::ComputeUnitLimit { units: 50_000 },
+                },
+                ComputeBudgetChange {
+                    previous: Some(SolanaInstruction::ComputeUnitPrice {
+                        micro_lamports: 1_000
+                    }),
+                    current: SolanaInstruction::ComputeUnitPrice {
+                        micro_lamports: 5_000
+                    },
+                },
+            ]
+        );
+
+        // The nonce advance stays first and nothing is duplicated
+        let instructions = message.instructions();
+        assert_eq!(instructions.len(), 4);
+        assert!(uses_durable_nonce(&message));
+        let program = |index: usize| {
+            message.static_account_keys()[usize::from(instructions[index].program_id_index)]
+        };
+        assert_eq!(program(1), compute_budget::id());
+        assert_eq!(program(2), compute_budget::id());
+        assert_eq!(
+            instructions[2].data,
+            ComputeBudgetInstruction::set_compute_unit_limit(50_000).data
+        );
+
+        // Applying the same budget again changes nothing
+        assert!(set_compute_budget(&mut message, &compute_budget)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! Checks transactions against the limits the cluster enforces before executing them
+use solana_sdk::{
+    message::VersionedMessage, packet::PACKET_DATA_SIZE, transaction::VersionedTransaction,
+};
+
+use super::types::TransactionSize;
+use crate::errors::*;
+
+/// Most accounts a transaction can lock, including the ones loaded from lookup tables
+pub(crate) const MAX_TRANSACTION_ACCOUNTS: usize = 64;
+
+/// Size of `message` once it's signed by every required signer
+pub(crate) fn transaction_size(
+    message: &VersionedMessage,
+) -> Result<TransactionSize, TransactionError> {
+    let num_signatures = usize::from(message.header().num_required_signatures);
+    let signatures_size = short_vec_length(num_signatures) + num_signatures * 64;
+    let message_size =
+        bincode::serialized_size(message).map_err(TransactionError::parsing_failure)? as usize;
+
+    let loaded_accounts = message.address_table_lookups().map_or(0, |lookups| {
+        lookups
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+            .sum()
+    });
+
+    Ok(TransactionSize {
+        size: (signatures_size + message_size) as u64,
+        account_count: (message.static_account_keys().len() + loaded_accounts) as u64,
+        max_size: PACKET_DATA_SIZE as u64,
+        max_account_count: MAX_TRANSACTION_ACCOUNTS as u64,
+    })
+}
+
+/// Fails with `TransactionError::Oversize` when the transaction can't be sent
+pub(crate) fn check_transaction_size(
+    transaction: &VersionedTransaction,
+) -> Result<TransactionSize, TransactionError> {
+    let size = transaction_size(&transaction.message)?;
+    if size.fits() {
+        Ok(size)
+    } else {
+        Err(TransactionError::oversize(size.size, size.account_count))
+    }
+}
+
+/// Bytes the compact-u16 encoding of `len` takes
+fn short_vec_length(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+impl TransactionSize {
+    pub(crate) fn fits(&self) -> bool {
+        self.size <= self.max_size && self.account_count <= self.max_account_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::{v0, Message},
+        pubkey::Pubkey,
+        signature::Signature,
+    };
+
+    #[test]
+    fn test_transaction_size() {
+        let payer = Pubkey::new_unique();
+        let accounts: Vec<Pubkey> = (0..70).map(|_| Pubkey::new_unique()).collect();
+        let instruction = |accounts: &[Pubkey]| {
+            Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[1, 2, 3],
+                accounts
+                    .iter()
+                    .map(|key| AccountMeta::new_readonly(*key, false))
+                    .collect(),
+            )
+        };
+
+        let message = Message::new(&[instruction(&accounts[..10])], Some(&payer));
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let size = check_transaction_size(&transaction).unwrap();
+        assert_eq!(size.size, bincode::serialized_size(&transaction).unwrap());
+        assert_eq!(size.account_count, 12);
+
+        // 34 accounts don't fit into a legacy transaction
+        let message = Message::new(&[instruction(&accounts[..34])], Some(&payer));
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let Err(TransactionError::Oversize(size, 36)) = check_transaction_size(&transaction) else {
+            panic!("The transaction should be too large");
+        };
+        assert!(size > 1232);
+
+        // With a lookup table they do, but 70 exceed the account limit
+        let lookup_table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: accounts.clone(),
+        };
+        let compile = |accounts: &[Pubkey]| {
+            let message = v0::Message::try_compile(
+                &payer,
+                &[instruction(accounts)],
+                &[lookup_table.clone()],
+                Hash::default(),
+            )
+            .unwrap();
+            VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::V0(message),
+            }
+        };
+        let size = check_transaction_size(&compile(&accounts[..34])).unwrap();
+        assert_eq!(size.account_count, 36);
+        assert!(matches!(
+            check_transaction_size(&compile(&accounts)),
+            Err(TransactionError::Oversize(_, 72))
+        ));
+    }
+}
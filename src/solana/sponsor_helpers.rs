@@ -0,0 +1,283 @@
+//! Checks of the transactions a sponsor, like a relayer of gasless transfers, is
+//! asked to pay the fee of
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use solana_sdk::{message::VersionedMessage, pubkey::Pubkey};
+
+use super::balance_helpers::TOKEN_ACCOUNT_RENT;
+use super::TOKEN_2022_PROGRAM;
+use crate::errors::*;
+
+/// Data of the `Create` and `CreateIdempotent` instructions of the associated token
+/// account program, `Create` having no data in its first version
+const CREATE_ASSOCIATED_TOKEN_ACCOUNT_DATA: [&[u8]; 3] = [&[], &[0], &[1]];
+/// Tags of the token instructions that could hand the rent of an account over to
+/// someone else: `SetAuthority` and `CloseAccount`
+const RENT_RECLAIMING_TOKEN_INSTRUCTIONS: [u8; 2] = [6, 9];
+/// Accounts a sponsor pays the rent of in a single transaction at most
+const MAX_SPONSORED_ACCOUNTS: usize = 2;
+
+/// Fails unless `sponsor` only pays the fee of `message`, and the rent of the SPL
+/// token associated token accounts it creates when `allow_rent` is set. Returns the
+/// rent the sponsor pays. `account_keys` are the keys of the message, including the ones
+/// loaded from lookup tables.
+pub(crate) fn validate_sponsored_message(
+    message: &VersionedMessage,
+    account_keys: &[Pubkey],
+    sponsor: &Pubkey,
+    allow_rent: bool,
+) -> Result<u64, TransactionError> {
+    if message.static_account_keys().first() != Some(sponsor) {
+        return Err(TransactionError::sponsorship(
+            "The sponsor isn't the fee payer",
+        ));
+    }
+
+    let key = |index: u8| account_keys.get(usize::from(index));
+    let is_account_creation = |program_id: &Pubkey, data: &[u8]| {
+        *program_id == spl_associated_token_account::id()
+            && CREATE_ASSOCIATED_TOKEN_ACCOUNT_DATA.contains(&data)
+    };
+    let created_accounts: HashSet<&Pubkey> = message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            key(instruction.program_id_index)
+                .is_some_and(|program_id| is_account_creation(program_id, &instruction.data))
+        })
+        .filter_map(|instruction| key(*instruction.accounts.get(1)?))
+        .collect();
+
+    let token_2022_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+    let is_token_program =
+        |program_id: &Pubkey| *program_id == spl_token::id() || *program_id == token_2022_program;
+
+    let mut sponsored_rent = Vec::new();
+    for (index, instruction) in message.instructions().iter().enumerate() {
+        let program_id = key(instruction.program_id_index).ok_or_else(|| {
+            TransactionError::sponsorship(format!("Instruction {index} has no program"))
+        })?;
+        if program_id == sponsor {
+            return Err(TransactionError::sponsorship(format!(
+                "Instruction {index} invokes the sponsor"
+            )));
+        }
+
+        // Otherwise the sponsored rent of a created account goes straight back out
+        let reclaims_rent = is_token_program(program_id)
+            && instruction
+                .data
+                .first()
+                .is_some_and(|tag| RENT_RECLAIMING_TOKEN_INSTRUCTIONS.contains(tag))
+            && instruction
+                .accounts
+                .first()
+                .and_then(|account| key(*account))
+                .is_some_and(|account| created_accounts.contains(account));
+        if reclaims_rent {
+            return Err(TransactionError::sponsorship(format!(
+                "Instruction {index} closes or hands over an account the transaction creates"
+            )));
+        }
+
+        // The fee payer is a writable signer of the whole message, so any instruction
+        // it's passed to can spend its lamports
+        let sponsor_positions: Vec<usize> = instruction
+            .accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, account)| key(**account) == Some(sponsor))
+            .map(|(position, _)| position)
+            .collect();
+        if sponsor_positions.is_empty() {
+            continue;
+        }
+        let pays_rent = allow_rent
+            && is_account_creation(program_id, &instruction.data)
+            && sponsor_positions == [0];
+        if !pays_rent {
+            return Err(TransactionError::sponsorship(format!(
+                "Instruction {index} of {program_id} uses the sponsor"
+            )));
+        }
+        // The associated token account program sizes the account by asking the token
+        // program, any other program could make the sponsor fund an account of its
+        // own. Token-2022 accounts grow with the extensions of the mint, so only the
+        // fixed size of SPL token accounts is sponsored.
+        let token_program = instruction
+            .accounts
+            .get(5)
+            .and_then(|account| key(*account));
+        if token_program != Some(&spl_token::id()) {
+            return Err(TransactionError::sponsorship(format!(
+                "Instruction {index} creates an account outside of the SPL token program"
+            )));
+        }
+        sponsored_rent.push(TOKEN_ACCOUNT_RENT);
+    }
+
+    if sponsored_rent.len() > MAX_SPONSORED_ACCOUNTS {
+        return Err(TransactionError::sponsorship(format!(
+            "The sponsor would pay the rent of {} accounts, at most {MAX_SPONSORED_ACCOUNTS} are allowed",
+            sponsored_rent.len()
+        )));
+    }
+    Ok(sponsored_rent.iter().sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::create_associated_token_account_idempotent_2022;
+    use solana_sdk::{instruction::Instruction, message::Message, system_instruction};
+
+    #[test]
+    fn test_validate_sponsored_message() {
+        let sponsor = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let receiver = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let transfer = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            &mint,
+            &destination,
+            &owner,
+            &[],
+            1_000_000,
+            6,
+        )
+        .unwrap();
+        let validate_with = |instructions: &[Instruction], payer: &Pubkey, allow_rent| {
+            let message = VersionedMessage::Legacy(Message::new(instructions, Some(payer)));
+            validate_sponsored_message(
+                &message,
+                message.static_account_keys(),
+                &sponsor,
+                allow_rent,
+            )
+        };
+        let validate =
+            |instructions: &[Instruction], payer: &Pubkey| validate_with(instructions, payer, true);
+
+        assert_eq!(validate(&[transfer.clone()], &sponsor).unwrap(), 0);
+        let create_account = create_associated_token_account_idempotent_2022(
+            &sponsor,
+            &receiver,
+            &mint,
+            spl_token::id(),
+        );
+        assert_eq!(
+            validate(&[create_account.clone(), transfer.clone()], &sponsor).unwrap(),
+            2_039_280
+        );
+        let Err(TransactionError::Sponsorship(_)) =
+            validate_with(&[create_account.clone(), transfer.clone()], &sponsor, false)
+        else {
+            panic!("The rent should only be sponsored when allowed");
+        };
+
+        let receiver_account =
+            spl_associated_token_account::get_associated_token_address(&receiver, &mint);
+        let close_account = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &receiver_account,
+            &receiver,
+            &receiver,
+            &[],
+        )
+        .unwrap();
+        let Err(TransactionError::Sponsorship(_)) =
+            validate(&[create_account.clone(), close_account], &sponsor)
+        else {
+            panic!("The sponsored account shouldn't be closed");
+        };
+        let hand_over = spl_token::instruction::set_authority(
+            &spl_token::id(),
+            &receiver_account,
+            Some(&owner),
+            spl_token::instruction::AuthorityType::CloseAccount,
+            &receiver,
+            &[],
+        )
+        .unwrap();
+        let Err(TransactionError::Sponsorship(_)) =
+            validate(&[create_account.clone(), hand_over], &sponsor)
+        else {
+            panic!("The sponsored account shouldn't change hands");
+        };
+
+        // A foreign program would size, own and later release the funded account
+        for token_program in [
+            Pubkey::new_unique(),
+            Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap(),
+        ] {
+            let create_account = create_associated_token_account_idempotent_2022(
+                &sponsor,
+                &receiver,
+                &mint,
+                token_program,
+            );
+            let Err(TransactionError::Sponsorship(_)) = validate(&[create_account], &sponsor)
+            else {
+                panic!("Only the rent of SPL token accounts should be sponsored");
+            };
+        }
+
+        let create_accounts: Vec<_> = (0..=MAX_SPONSORED_ACCOUNTS)
+            .map(|_| {
+                create_associated_token_account_idempotent_2022(
+                    &sponsor,
+                    &Pubkey::new_unique(),
+                    &mint,
+                    spl_token::id(),
+                )
+            })
+            .collect();
+        let Err(TransactionError::Sponsorship(_)) = validate(&create_accounts, &sponsor) else {
+            panic!("The number of sponsored accounts should be capped");
+        };
+
+        let Err(TransactionError::Sponsorship(_)) = validate(&[transfer.clone()], &owner) else {
+            panic!("The sponsor should be the fee payer");
+        };
+
+        let drain = system_instruction::transfer(&sponsor, &owner, 1_000_000_000);
+        let Err(TransactionError::Sponsorship(_)) = validate(&[transfer.clone(), drain], &sponsor)
+        else {
+            panic!("The sponsor shouldn't transfer SOL");
+        };
+
+        let sponsor_tokens = spl_token::instruction::transfer_checked(
+            &spl_token::id(),
+            &source,
+            &mint,
+            &destination,
+            &sponsor,
+            &[],
+            1_000_000,
+            6,
+        )
+        .unwrap();
+        let Err(TransactionError::Sponsorship(_)) = validate(&[sponsor_tokens], &sponsor) else {
+            panic!("The sponsor shouldn't authorize token transfers");
+        };
+
+        // Owning the created account isn't paying its rent
+        let sponsor_account = create_associated_token_account_idempotent_2022(
+            &sponsor,
+            &sponsor,
+            &mint,
+            spl_token::id(),
+        );
+        let Err(TransactionError::Sponsorship(_)) =
+            validate(&[sponsor_account, transfer], &sponsor)
+        else {
+            panic!("The sponsor should only pay the rent");
+        };
+    }
+}
@@ -0,0 +1,229 @@
+//! Native stake program accounts
+use solana_sdk::{
+    clock::Epoch,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    rent::Rent,
+    stake::{
+        self,
+        state::{Authorized, Lockup, Meta, StakeStateV2},
+    },
+    system_instruction,
+};
+
+use super::sol_to_lamports;
+use super::types::{StakeAccount, StakeAccountAction, StakeAccountState, StakeLockup};
+use crate::errors::*;
+
+/// Returns the instructions of `action` and the account paying for the transaction
+pub(crate) fn stake_account_instructions(
+    action: &StakeAccountAction,
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    match action {
+        StakeAccountAction::Create {
+            funder,
+            stake_account,
+            seed,
+            staker,
+            withdrawer,
+            amount,
+        } => {
+            let funder = funder.to_solana_pubkey()?;
+            let stake_account = stake_account.to_solana_pubkey()?;
+            let authorized = Authorized {
+                staker: staker.to_solana_pubkey()?,
+                withdrawer: withdrawer.to_solana_pubkey()?,
+            };
+            let lamports = sol_to_lamports(amount)?;
+
+            let instructions = match seed {
+                Some(seed) => {
+                    if stake_account_address_with_seed(&funder, seed)? != stake_account {
+                        return Err(TransactionError::parameters(
+                            "Stake account is not derived from the funder and seed",
+                        ));
+                    }
+                    stake::instruction::create_account_with_seed(
+                        &funder,
+                        &stake_account,
+                        &funder,
+                        seed,
+                        &authorized,
+                        &Lockup::default(),
+                        lamports,
+                    )
+                }
+                None => stake::instruction::create_account(
+                    &funder,
+                    &stake_account,
+                    &authorized,
+                    &Lockup::default(),
+                    lamports,
+                ),
+            };
+            Ok((instructions, funder))
+        }
+        StakeAccountAction::Delegate {
+            stake_account,
+            staker,
+            vote_account,
+        } => {
+            let staker = staker.to_solana_pubkey()?;
+            let instruction = stake::instruction::delegate_stake(
+                &stake_account.to_solana_pubkey()?,
+                &staker,
+                &vote_account.to_solana_pubkey()?,
+            );
+            Ok((vec![instruction], staker))
+        }
+        StakeAccountAction::Deactivate {
+            stake_account,
+            staker,
+        } => {
+            let staker = staker.to_solana_pubkey()?;
+            let instruction =
+                stake::instruction::deactivate_stake(&stake_account.to_solana_pubkey()?, &staker);
+            Ok((vec![instruction], staker))
+        }
+        StakeAccountAction::Withdraw {
+            stake_account,
+            withdrawer,
+            recipient,
+            amount,
+        } => {
+            let withdrawer = withdrawer.to_solana_pubkey()?;
+            let instruction = stake::instruction::withdraw(
+                &stake_account.to_solana_pubkey()?,
+                &withdrawer,
+                &recipient.to_solana_pubkey()?,
+                sol_to_lamports(amount)?,
+                None,
+            );
+            Ok((vec![instruction], withdrawer))
+        }
+        StakeAccountAction::Split {
+            stake_account,
+            staker,
+            split_stake_account,
+            amount,
+        } => {
+            let staker = staker.to_solana_pubkey()?;
+            let split_stake_account = split_stake_account.to_solana_pubkey()?;
+            // The split destination has to be rent exempt before the split
+            let mut instructions = vec![system_instruction::transfer(
+                &staker,
+                &split_stake_account,
+                Rent::default().minimum_balance(StakeStateV2::size_of()),
+            )];
+            instructions.extend(stake::instruction::split(
+                &stake_account.to_solana_pubkey()?,
+                &staker,
+                sol_to_lamports(amount)?,
+                &split_stake_account,
+            ));
+            Ok((instructions, staker))
+        }
+        StakeAccountAction::Merge {
+            stake_account,
+            staker,
+            source_stake_account,
+        } => {
+            let staker = staker.to_solana_pubkey()?;
+            let instructions = stake::instruction::merge(
+                &stake_account.to_solana_pubkey()?,
+                &source_stake_account.to_solana_pubkey()?,
+                &staker,
+            );
+            Ok((instructions, staker))
+        }
+    }
+}
+
+/// Address of a stake account created with `seed` by `base`
+pub(crate) fn stake_account_address_with_seed(
+    base: &Pubkey,
+    seed: &str,
+) -> Result<Pubkey, TransactionError> {
+    Pubkey::create_with_seed(base, seed, &stake::program::id())
+        .map_err(TransactionError::public_key)
+}
+
+/// Decodes the data of a stake account
+pub(crate) fn parse_stake_account_data(data: &[u8]) -> Result<StakeAccount, TransactionError> {
+    let stake_state: StakeStateV2 =
+        bincode::deserialize(data).map_err(TransactionError::parsing_failure)?;
+
+    let state = match stake_state {
+        StakeStateV2::Uninitialized => StakeAccountState::Uninitialized,
+        StakeStateV2::Initialized(_) => StakeAccountState::Initialized,
+        StakeStateV2::Stake(..) => StakeAccountState::Delegated,
+        StakeStateV2::RewardsPool => StakeAccountState::RewardsPool,
+    };
+    let meta = stake_state.meta();
+    let delegation = stake_state.delegation();
+    let epoch = |epoch: Epoch| (epoch != Epoch::MAX).then_some(epoch);
+
+    Ok(StakeAccount {
+        state,
+        staker: meta.map(|meta| meta.authorized.staker.to_string()),
+        withdrawer: meta.map(|meta| meta.authorized.withdrawer.to_string()),
+        rent_exempt_reserve: meta.map(|meta| meta.rent_exempt_reserve),
+        lockup: meta.and_then(|Meta { lockup, .. }| {
+            (lockup != Lockup::default()).then(|| StakeLockup {
+                unix_timestamp: lockup.unix_timestamp,
+                epoch: lockup.epoch,
+                custodian: lockup.custodian.to_string(),
+            })
+        }),
+        voter: delegation.map(|delegation| delegation.voter_pubkey.to_string()),
+        delegated_stake: delegation.map(|delegation| delegation.stake),
+        activation_epoch: delegation.and_then(|delegation| epoch(delegation.activation_epoch)),
+        deactivation_epoch: delegation.and_then(|delegation| epoch(delegation.deactivation_epoch)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::stake::{
+        stake_flags::StakeFlags,
+        state::{Delegation, Stake},
+    };
+
+    #[test]
+    fn test_parse_stake_account_data() {
+        let staker = Pubkey::new_unique();
+        let voter = Pubkey::new_unique();
+        let meta = Meta {
+            rent_exempt_reserve: 2_282_880,
+            authorized: Authorized::auto(&staker),
+            lockup: Lockup::default(),
+        };
+        let stake = Stake {
+            delegation: Delegation::new(&voter, 1_000_000_000, 600),
+            credits_observed: 0,
+        };
+        let data =
+            bincode::serialize(&StakeStateV2::Stake(meta, stake, StakeFlags::empty())).unwrap();
+
+        assert_eq!(
+            parse_stake_account_data(&data).unwrap(),
+            StakeAccount {
+                state: StakeAccountState::Delegated,
+                staker: Some(staker.to_string()),
+                withdrawer: Some(staker.to_string()),
+                rent_exempt_reserve: Some(2_282_880),
+                lockup: None,
+                voter: Some(voter.to_string()),
+                delegated_stake: Some(1_000_000_000),
+                activation_epoch: Some(600),
+                deactivation_epoch: None,
+            }
+        );
+
+        let data = bincode::serialize(&StakeStateV2::Initialized(meta)).unwrap();
+        let stake_account = parse_stake_account_data(&data).unwrap();
+        assert_eq!(stake_account.state, StakeAccountState::Initialized);
+        assert_eq!(stake_account.voter, None);
+    }
+}
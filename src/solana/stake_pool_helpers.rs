@@ -0,0 +1,675 @@
+//! SPL stake pool program (liquid staking tokens like JitoSOL or JupSOL).
+//! The program is not a dependency, so the few instructions and the pool
+//! state we need are encoded by hand.
+use std::str::FromStr;
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey,
+    pubkey::Pubkey,
+    rent::Rent,
+    stake::{self, state::StakeStateV2},
+    system_instruction, system_program, sysvar,
+};
+
+use super::types::{StakePoolAccount, StakePoolAction, StakePoolFee, StakePoolState};
+use super::{
+    associated_token_address_2022, create_associated_token_account_idempotent_2022, sol_to_lamports,
+};
+use crate::errors::*;
+use crate::utils::from_base64;
+
+pub(crate) const STAKE_POOL_PROGRAM_ID: Pubkey =
+    pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+const DEPOSIT_STAKE_TAG: u8 = 9;
+const WITHDRAW_STAKE_TAG: u8 = 10;
+const DEPOSIT_SOL_TAG: u8 = 14;
+const WITHDRAW_SOL_TAG: u8 = 16;
+const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+
+/// A decoded stake pool with the program that owns it
+pub(crate) struct StakePool {
+    pub address: Pubkey,
+    pub program: Pubkey,
+    pub manager: Pubkey,
+    pub staker: Pubkey,
+    pub stake_deposit_authority: Pubkey,
+    pub validator_list: Pubkey,
+    pub reserve_stake: Pubkey,
+    pub pool_mint: Pubkey,
+    pub manager_fee_account: Pubkey,
+    pub token_program: Pubkey,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+    pub sol_deposit_authority: Option<Pubkey>,
+    pub sol_withdraw_authority: Option<Pubkey>,
+    pub sol_deposit_fee: StakePoolFee,
+    pub sol_withdrawal_fee: StakePoolFee,
+    pub stake_deposit_fee: StakePoolFee,
+    pub stake_withdrawal_fee: StakePoolFee,
+}
+
+impl StakePool {
+    pub(crate) fn from_account(account: &StakePoolAccount) -> Result<Self, TransactionError> {
+        let address = Pubkey::from_str(&account.address).map_err(TransactionError::public_key)?;
+        let program = match &account.program {
+            Some(program) => Pubkey::from_str(program).map_err(TransactionError::public_key)?,
+            None => STAKE_POOL_PROGRAM_ID,
+        };
+        let data = from_base64(&account.data).map_err(TransactionError::parsing_failure)?;
+        let mut reader = Reader(&data);
+
+        if reader.u8()? != STAKE_POOL_ACCOUNT_TYPE {
+            return Err(TransactionError::parameters("Account is not a stake pool"));
+        }
+        let manager = reader.pubkey()?;
+        let staker = reader.pubkey()?;
+        let stake_deposit_authority = reader.pubkey()?;
+        let _stake_withdraw_bump_seed = reader.u8()?;
+        let validator_list = reader.pubkey()?;
+        let reserve_stake = reader.pubkey()?;
+        let pool_mint = reader.pubkey()?;
+        let manager_fee_account = reader.pubkey()?;
+        let token_program = reader.pubkey()?;
+        let total_lamports = reader.u64()?;
+        let pool_token_supply = reader.u64()?;
+        let last_update_epoch = reader.u64()?;
+        // Lockup: unix timestamp, epoch and custodian
+        reader.skip(8 + 8 + 32)?;
+        let _epoch_fee = reader.fee()?;
+        reader.future_fee()?;
+        let _preferred_deposit_validator = reader.option_pubkey()?;
+        let _preferred_withdraw_validator = reader.option_pubkey()?;
+        let stake_deposit_fee = reader.fee()?;
+        let stake_withdrawal_fee = reader.fee()?;
+        reader.future_fee()?;
+        let _stake_referral_fee = reader.u8()?;
+        let sol_deposit_authority = reader.option_pubkey()?;
+        let sol_deposit_fee = reader.fee()?;
+        let _sol_referral_fee = reader.u8()?;
+        let sol_withdraw_authority = reader.option_pubkey()?;
+        let sol_withdrawal_fee = reader.fee()?;
+
+        Ok(Self {
+            address,
+            program,
+            manager,
+            staker,
+            stake_deposit_authority,
+            validator_list,
+            reserve_stake,
+            pool_mint,
+            manager_fee_account,
+            token_program,
+            total_lamports,
+            pool_token_supply,
+            last_update_epoch,
+            sol_deposit_authority,
+            sol_withdraw_authority,
+            sol_deposit_fee,
+            sol_withdrawal_fee,
+            stake_deposit_fee,
+            stake_withdrawal_fee,
+        })
+    }
+
+    /// PDA that owns the stake accounts and mints the pool tokens
+    pub(crate) fn withdraw_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.address.as_ref(), b"withdraw"], &self.program).0
+    }
+
+    /// PDA used as stake deposit authority, unless the pool sets its own
+    pub(crate) fn default_deposit_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(&[self.address.as_ref(), b"deposit"], &self.program).0
+    }
+
+    /// PDA of the stake account the pool delegates to `vote_account`
+    pub(crate) fn validator_stake_account(
+        &self,
+        vote_account: &Pubkey,
+        seed: Option<u32>,
+    ) -> Pubkey {
+        let seed = seed.filter(|seed| *seed != 0).map(u32::to_le_bytes);
+        let mut seeds = vec![vote_account.as_ref(), self.address.as_ref()];
+        if let Some(seed) = seed.as_ref() {
+            seeds.push(seed);
+        }
+        Pubkey::find_program_address(&seeds, &self.program).0
+    }
+
+    pub(crate) fn to_state(&self) -> StakePoolState {
+        StakePoolState {
+            manager: self.manager.to_string(),
+            staker: self.staker.to_string(),
+            stake_deposit_authority: self.stake_deposit_authority.to_string(),
+            withdraw_authority: self.withdraw_authority().to_string(),
+            validator_list: self.validator_list.to_string(),
+            reserve_stake: self.reserve_stake.to_string(),
+            pool_mint: self.pool_mint.to_string(),
+            manager_fee_account: self.manager_fee_account.to_string(),
+            token_program: self.token_program.to_string(),
+            total_lamports: self.total_lamports,
+            pool_token_supply: self.pool_token_supply,
+            last_update_epoch: self.last_update_epoch,
+            sol_deposit_authority: self.sol_deposit_authority.map(|key| key.to_string()),
+            sol_withdraw_authority: self.sol_withdraw_authority.map(|key| key.to_string()),
+            sol_deposit_fee: self.sol_deposit_fee.clone(),
+            sol_withdrawal_fee: self.sol_withdrawal_fee.clone(),
+            stake_deposit_fee: self.stake_deposit_fee.clone(),
+            stake_withdrawal_fee: self.stake_withdrawal_fee.clone(),
+        }
+    }
+}
+
+/// Returns the instructions of `action` and the account paying for the transaction
+pub(crate) fn stake_pool_instructions(
+    stake_pool: &StakePool,
+    action: &StakePoolAction,
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    let withdraw_authority = stake_pool.withdraw_authority();
+
+    match action {
+        StakePoolAction::DepositSol { owner, amount } => {
+            let owner = owner.to_solana_pubkey()?;
+            let pool_token_account = associated_token_address_2022(
+                &owner,
+                &stake_pool.token_program,
+                &stake_pool.pool_mint,
+            );
+
+            let mut accounts = vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new(stake_pool.reserve_stake, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                // The depositor is its own referrer
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(stake_pool.token_program, false),
+            ];
+            if let Some(sol_deposit_authority) = stake_pool.sol_deposit_authority {
+                accounts.push(AccountMeta::new_readonly(sol_deposit_authority, true));
+            }
+
+            let instructions = vec![
+                create_associated_token_account_idempotent_2022(
+                    &owner,
+                    &owner,
+                    &stake_pool.pool_mint,
+                    stake_pool.token_program,
+                ),
+                Instruction::new_with_bytes(
+                    stake_pool.program,
+                    &tagged_amount(DEPOSIT_SOL_TAG, sol_to_lamports(amount)?),
+                    accounts,
+                ),
+            ];
+            Ok((instructions, owner))
+        }
+        StakePoolAction::WithdrawSol { owner, pool_tokens } => {
+            let owner = owner.to_solana_pubkey()?;
+            let pool_token_account = associated_token_address_2022(
+                &owner,
+                &stake_pool.token_program,
+                &stake_pool.pool_mint,
+            );
+
+            let mut accounts = vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.reserve_stake, false),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+                AccountMeta::new_readonly(stake::program::id(), false),
+                AccountMeta::new_readonly(stake_pool.token_program, false),
+            ];
+            if let Some(sol_withdraw_authority) = stake_pool.sol_withdraw_authority {
+                accounts.push(AccountMeta::new_readonly(sol_withdraw_authority, true));
+            }
+
+            let instruction = Instruction::new_with_bytes(
+                stake_pool.program,
+                &tagged_amount(WITHDRAW_SOL_TAG, pool_tokens.to_u64()?),
+                accounts,
+            );
+            Ok((vec![instruction], owner))
+        }
+        StakePoolAction::DepositStake {
+            owner,
+            stake_account,
+            validator_vote_account,
+            validator_seed,
+        } => {
+            let owner = owner.to_solana_pubkey()?;
+            let stake_account = stake_account.to_solana_pubkey()?;
+            let validator_stake_account = stake_pool.validator_stake_account(
+                &validator_vote_account.to_solana_pubkey()?,
+                *validator_seed,
+            );
+            let pool_token_account = associated_token_address_2022(
+                &owner,
+                &stake_pool.token_program,
+                &stake_pool.pool_mint,
+            );
+            // A deposit authority set by the pool has to sign, the default one is a PDA
+            let deposit_authority = stake_pool.stake_deposit_authority;
+            let deposit_authority_signs =
+                deposit_authority != stake_pool.default_deposit_authority();
+
+            let mut instructions = vec![create_associated_token_account_idempotent_2022(
+                &owner,
+                &owner,
+                &stake_pool.pool_mint,
+                stake_pool.token_program,
+            )];
+            // The pool takes over the stake account
+            for stake_authorize in [
+                stake::state::StakeAuthorize::Staker,
+                stake::state::StakeAuthorize::Withdrawer,
+            ] {
+                instructions.push(stake::instruction::authorize(
+                    &stake_account,
+                    &owner,
+                    &deposit_authority,
+                    stake_authorize,
+                    None,
+                ));
+            }
+            instructions.push(Instruction::new_with_bytes(
+                stake_pool.program,
+                &[DEPOSIT_STAKE_TAG],
+                vec![
+                    AccountMeta::new(stake_pool.address, false),
+                    AccountMeta::new(stake_pool.validator_list, false),
+                    AccountMeta::new_readonly(deposit_authority, deposit_authority_signs),
+                    AccountMeta::new_readonly(withdraw_authority, false),
+                    AccountMeta::new(stake_account, false),
+                    AccountMeta::new(validator_stake_account, false),
+                    AccountMeta::new(stake_pool.reserve_stake, false),
+                    AccountMeta::new(pool_token_account, false),
+                    AccountMeta::new(stake_pool.manager_fee_account, false),
+                    // The depositor is its own referrer
+                    AccountMeta::new(pool_token_account, false),
+                    AccountMeta::new(stake_pool.pool_mint, false),
+                    AccountMeta::new_readonly(sysvar::clock::id(), false),
+                    AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+                    AccountMeta::new_readonly(stake_pool.token_program, false),
+                    AccountMeta::new_readonly(stake::program::id(), false),
+                ],
+            ));
+            Ok((instructions, owner))
+        }
+        StakePoolAction::WithdrawStake {
+            owner,
+            pool_tokens,
+            new_stake_account,
+            validator_vote_account,
+            validator_seed,
+        } => {
+            let owner = owner.to_solana_pubkey()?;
+            let new_stake_account = new_stake_account.to_solana_pubkey()?;
+            let stake_to_split = match validator_vote_account {
+                Some(vote_account) => stake_pool
+                    .validator_stake_account(&vote_account.to_solana_pubkey()?, *validator_seed),
+                None => stake_pool.reserve_stake,
+            };
+            let pool_token_account = associated_token_address_2022(
+                &owner,
+                &stake_pool.token_program,
+                &stake_pool.pool_mint,
+            );
+
+            let instructions = vec![
+                system_instruction::create_account(
+                    &owner,
+                    &new_stake_account,
+                    Rent::default().minimum_balance(StakeStateV2::size_of()),
+                    StakeStateV2::size_of() as u64,
+                    &stake::program::id(),
+                ),
+                Instruction::new_with_bytes(
+                    stake_pool.program,
+                    &tagged_amount(WITHDRAW_STAKE_TAG, pool_tokens.to_u64()?),
+                    vec![
+                        AccountMeta::new(stake_pool.address, false),
+                        AccountMeta::new(stake_pool.validator_list, false),
+                        AccountMeta::new_readonly(withdraw_authority, false),
+                        AccountMeta::new(stake_to_split, false),
+                        AccountMeta::new(new_stake_account, false),
+                        // The owner becomes the authority of the new stake account
+                        AccountMeta::new_readonly(owner, false),
+                        AccountMeta::new_readonly(owner, true),
+                        AccountMeta::new(pool_token_account, false),
+                        AccountMeta::new(stake_pool.manager_fee_account, false),
+                        AccountMeta::new(stake_pool.pool_mint, false),
+                        AccountMeta::new_readonly(sysvar::clock::id(), false),
+                        AccountMeta::new_readonly(stake_pool.token_program, false),
+                        AccountMeta::new_readonly(stake::program::id(), false),
+                    ],
+                ),
+            ];
+            Ok((instructions, owner))
+        }
+    }
+}
+
+fn tagged_amount(tag: u8, amount: u64) -> Vec<u8> {
+    let mut data = vec![tag];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data
+}
+
+/// Minimal borsh reader for the stake pool account
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], TransactionError> {
+        let (bytes, rest) = self
+            .0
+            .split_at_checked(len)
+            .ok_or_else(|| TransactionError::parameters("Stake pool account data is too short"))?;
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), TransactionError> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, TransactionError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, TransactionError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, TransactionError> {
+        let bytes = self.take(32)?;
+        Ok(Pubkey::try_from(bytes).expect("32 bytes"))
+    }
+
+    fn option_pubkey(&mut self) -> Result<Option<Pubkey>, TransactionError> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => self.pubkey().map(Some),
+        }
+    }
+
+    fn fee(&mut self) -> Result<StakePoolFee, TransactionError> {
+        let denominator = self.u64()?;
+        let numerator = self.u64()?;
+        Ok(StakePoolFee {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Skips a fee that only applies from one of the next epochs on
+    fn future_fee(&mut self) -> Result<(), TransactionError> {
+        match self.u8()? {
+            0 => Ok(()),
+            _ => self.fee().map(|_| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Blockchain, ChainPublicKey, DecimalNumber};
+    use crate::utils::to_base64;
+
+    #[test]
+    fn test_stake_pool_from_account() {
+        let keys: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+        let fee = |numerator: u64, denominator: u64| {
+            [denominator.to_le_bytes(), numerator.to_le_bytes()].concat()
+        };
+
+        let mut data = vec![STAKE_POOL_ACCOUNT_TYPE];
+        data.extend(keys[0].to_bytes()); // manager
+        data.extend(keys[1].to_bytes()); // staker
+        data.extend(keys[2].to_bytes()); // stake deposit authority
+        data.push(255); // withdraw bump seed
+        data.extend(keys[3].to_bytes()); // validator list
+        data.extend(keys[4].to_bytes()); // reserve
+        data.extend(keys[5].to_bytes()); // pool mint
+        data.extend(keys[6].to_bytes()); // manager fee account
+        data.extend(spl_token::id().to_bytes());
+        data.extend(1_100u64.to_le_bytes()); // total lamports
+        data.extend(1_000u64.to_le_bytes()); // pool token supply
+        data.extend(600u64.to_le_bytes()); // last update epoch
+        data.extend([0; 48]); // lockup
+        data.extend(fee(5, 100)); // epoch fee
+        data.extend([1].iter().chain(fee(4, 100).iter())); // next epoch fee
+        data.push(0); // preferred deposit validator
+        data.extend([1].iter().chain(keys[7].to_bytes().iter())); // preferred withdraw validator
+        data.extend(fee(0, 100)); // stake deposit fee
+        data.extend(fee(1, 1_000)); // stake withdrawal fee
+        data.push(0); // next stake withdrawal fee
+        data.push(0); // stake referral fee
+        data.push(0); // sol deposit authority
+        data.extend(fee(0, 0)); // sol deposit fee
+        data.push(0); // sol referral fee
+        data.push(0); // sol withdraw authority
+        data.extend(fee(1, 1_000)); // sol withdrawal fee
+
+        let address = Pubkey::new_unique();
+        let stake_pool = StakePool::from_account(&StakePoolAccount {
+            address: address.to_string(),
+            program: None,
+            data: to_base64(&data),
+        })
+        .unwrap();
+
+        assert_eq!(stake_pool.reserve_stake, keys[4]);
+        assert_eq!(stake_pool.pool_mint, keys[5]);
+        assert_eq!(stake_pool.token_program, spl_token::id());
+        assert_eq!(stake_pool.total_lamports, 1_100);
+        assert_eq!(stake_pool.pool_token_supply, 1_000);
+        assert_eq!(stake_pool.sol_deposit_authority, None);
+        assert_eq!(
+            stake_pool.sol_withdrawal_fee,
+            StakePoolFee {
+                numerator: 1,
+                denominator: 1_000
+            }
+        );
+        assert_eq!(
+            stake_pool.withdraw_authority(),
+            Pubkey::find_program_address(&[address.as_ref(), b"withdraw"], &STAKE_POOL_PROGRAM_ID)
+                .0
+        );
+
+        let truncated = StakePoolAccount {
+            address: address.to_string(),
+            program: None,
+            data: to_base64(&data[..100]),
+        };
+        assert!(StakePool::from_account(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_stake_pool_instructions() {
+        let no_fee = StakePoolFee {
+            numerator: 0,
+            denominator: 0,
+        };
+        let mut stake_pool = StakePool {
+            address: Pubkey::new_unique(),
+            program: STAKE_POOL_PROGRAM_ID,
+            manager: Pubkey::new_unique(),
+            staker: Pubkey::new_unique(),
+            stake_deposit_authority: Pubkey::new_unique(),
+            validator_list: Pubkey::new_unique(),
+            reserve_stake: Pubkey::new_unique(),
+            pool_mint: Pubkey::new_unique(),
+            manager_fee_account: Pubkey::new_unique(),
+            token_program: spl_token::id(),
+            total_lamports: 1_100,
+            pool_token_supply: 1_000,
+            last_update_epoch: 600,
+            sol_deposit_authority: None,
+            sol_withdraw_authority: None,
+            sol_deposit_fee: no_fee.clone(),
+            sol_withdrawal_fee: no_fee.clone(),
+            stake_deposit_fee: no_fee.clone(),
+            stake_withdrawal_fee: no_fee,
+        };
+        let owner = Pubkey::new_unique();
+        let stake_account = Pubkey::new_unique();
+        let vote_account = Pubkey::new_unique();
+        let key = |key: &Pubkey| ChainPublicKey::new(key.to_string(), Blockchain::Solana);
+        let amount = |value: &str| DecimalNumber {
+            value: value.to_string(),
+        };
+        let data = |tag: u8, amount: u64| [&[tag][..], &amount.to_le_bytes()].concat();
+        let withdraw_authority = stake_pool.withdraw_authority();
+        let pool_token_account =
+            associated_token_address_2022(&owner, &spl_token::id(), &stake_pool.pool_mint);
+        let validator_stake_account = stake_pool.validator_stake_account(&vote_account, Some(3));
+
+        let sol_deposit_authority = Pubkey::new_unique();
+        stake_pool.sol_deposit_authority = Some(sol_deposit_authority);
+        let (instructions, payer) = stake_pool_instructions(
+            &stake_pool,
+            &StakePoolAction::DepositSol {
+                owner: key(&owner),
+                amount: amount("1.5"),
+            },
+        )
+        .unwrap();
+        assert_eq!(payer, owner);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].program_id, STAKE_POOL_PROGRAM_ID);
+        assert_eq!(instructions[1].data, data(14, 1_500_000_000));
+        assert_eq!(
+            instructions[1].accounts,
+            vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new(stake_pool.reserve_stake, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(sol_deposit_authority, true),
+            ]
+        );
+
+        let (instructions, _) = stake_pool_instructions(
+            &stake_pool,
+            &StakePoolAction::WithdrawSol {
+                owner: key(&owner),
+                pool_tokens: amount("1000"),
+            },
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].data, data(16, 1_000));
+        assert_eq!(
+            instructions[0].accounts,
+            vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.reserve_stake, false),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+                AccountMeta::new_readonly(stake::program::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ]
+        );
+
+        // The pool sets its own stake deposit authority, which has to sign
+        let (instructions, _) = stake_pool_instructions(
+            &stake_pool,
+            &StakePoolAction::DepositStake {
+                owner: key(&owner),
+                stake_account: key(&stake_account),
+                validator_vote_account: key(&vote_account),
+                validator_seed: Some(3),
+            },
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 4);
+        for authorize in &instructions[1..3] {
+            assert_eq!(authorize.program_id, stake::program::id());
+            assert_eq!(authorize.accounts[0].pubkey, stake_account);
+        }
+        assert_eq!(instructions[3].data, vec![9]);
+        assert_eq!(
+            instructions[3].accounts,
+            vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new(stake_pool.validator_list, false),
+                AccountMeta::new_readonly(stake_pool.stake_deposit_authority, true),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new(stake_account, false),
+                AccountMeta::new(validator_stake_account, false),
+                AccountMeta::new(stake_pool.reserve_stake, false),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(stake::program::id(), false),
+            ]
+        );
+
+        let new_stake_account = Pubkey::new_unique();
+        let (instructions, _) = stake_pool_instructions(
+            &stake_pool,
+            &StakePoolAction::WithdrawStake {
+                owner: key(&owner),
+                pool_tokens: amount("500"),
+                new_stake_account: key(&new_stake_account),
+                validator_vote_account: Some(key(&vote_account)),
+                validator_seed: Some(3),
+            },
+        )
+        .unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, system_program::id());
+        assert_eq!(instructions[1].data, data(10, 500));
+        assert_eq!(
+            instructions[1].accounts,
+            vec![
+                AccountMeta::new(stake_pool.address, false),
+                AccountMeta::new(stake_pool.validator_list, false),
+                AccountMeta::new_readonly(withdraw_authority, false),
+                AccountMeta::new(validator_stake_account, false),
+                AccountMeta::new(new_stake_account, false),
+                AccountMeta::new_readonly(owner, false),
+                AccountMeta::new_readonly(owner, true),
+                AccountMeta::new(pool_token_account, false),
+                AccountMeta::new(stake_pool.manager_fee_account, false),
+                AccountMeta::new(stake_pool.pool_mint, false),
+                AccountMeta::new_readonly(sysvar::clock::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(stake::program::id(), false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Token-2022 extensions changing how tokens are transferred: transfer fees, required
+//! memos and transfer hooks, see https://spl.solana.com/token-2022/extensions
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use super::types::TokenExtensionAccounts;
+use crate::errors::*;
+use crate::utils::from_base64;
+
+/// Mints and token accounts share the base size of a token account, followed by the
+/// account type and the extensions
+const BASE_ACCOUNT_LENGTH: usize = 165;
+const MINT_ACCOUNT_TYPE: u8 = 1;
+const TOKEN_ACCOUNT_TYPE: u8 = 2;
+const TRANSFER_FEE_CONFIG_EXTENSION: u16 = 1;
+const MEMO_TRANSFER_EXTENSION: u16 = 8;
+const TRANSFER_HOOK_EXTENSION: u16 = 14;
+/// `TransferFeeExtension` instruction followed by its `TransferCheckedWithFee` instruction
+const TRANSFER_CHECKED_WITH_FEE: [u8; 2] = [26, 1];
+/// Discriminator of the transfer hook `Execute` instruction, which also identifies
+/// its extra account metas in the validation account
+const EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+const EXTRA_ACCOUNT_META_LENGTH: usize = 35;
+const MAX_BASIS_POINTS: u128 = 10_000;
+
+/// Adapts the `TransferChecked` `instruction` of `amount` to the extensions of the
+/// accounts it transfers with. Returns whether the destination requires a memo
+/// instruction right before the transfer.
+pub(crate) fn apply_transfer_extensions(
+    instruction: &mut Instruction,
+    amount: u64,
+    decimals: u8,
+    accounts: &TokenExtensionAccounts,
+) -> Result<bool, TransactionError> {
+    let mint_data = from_base64(&accounts.mint_data).map_err(TransactionError::parsing_failure)?;
+    let mint_extensions = extensions(&mint_data, MINT_ACCOUNT_TYPE)?;
+
+    if let Some(config) = find_extension(&mint_extensions, TRANSFER_FEE_CONFIG_EXTENSION) {
+        let fee = transfer_fee(config, accounts.epoch, amount)?;
+        let mut data = TRANSFER_CHECKED_WITH_FEE.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.push(decimals);
+        data.extend_from_slice(&fee.to_le_bytes());
+        instruction.data = data;
+    }
+
+    let mut memo_required = false;
+    if let Some(destination_data) = &accounts.destination_data {
+        let destination_data =
+            from_base64(destination_data).map_err(TransactionError::parsing_failure)?;
+        let destination_extensions = extensions(&destination_data, TOKEN_ACCOUNT_TYPE)?;
+        memo_required = find_extension(&destination_extensions, MEMO_TRANSFER_EXTENSION)
+            .is_some_and(|memo_transfer| memo_transfer.first() == Some(&1));
+    }
+
+    let hook_program = find_extension(&mint_extensions, TRANSFER_HOOK_EXTENSION)
+        .and_then(|hook| hook.get(32..64))
+        .map(|program_id| Pubkey::try_from(program_id).unwrap_or_default())
+        .filter(|program_id| *program_id != Pubkey::default());
+    if let Some(hook_program) = hook_program {
+        let metas_data = accounts.extra_account_metas_data.as_ref().ok_or_else(|| {
+            TransactionError::parameters("The transfer hook needs its extra account metas")
+        })?;
+        let metas_data = from_base64(metas_data).map_err(TransactionError::parsing_failure)?;
+        append_transfer_hook_accounts(instruction, amount, &hook_program, &metas_data)?;
+    }
+    Ok(memo_required)
+}
+
+/// Account holding the extra accounts the transfer hook `program_id` of `mint` needs
+fn extra_account_metas_address(mint: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], program_id).0
+}
+
+/// Splits the extensions of a mint or token account into their type and value
+fn extensions(data: &[u8], account_type: u8) -> Result<Vec<(u16, &[u8])>, TransactionError> {
+    let mut extensions = Vec::new();
+    // Accounts without extensions stop at their base size
+    let Some(tlv_data) = data.get(BASE_ACCOUNT_LENGTH..) else {
+        return Ok(extensions);
+    };
+    let Some((&actual_type, mut tlv_data)) = tlv_data.split_first() else {
+        return Ok(extensions);
+    };
+    if actual_type != account_type {
+        return Err(TransactionError::parameters(format!(
+            "Expected a Token-2022 account of type {account_type}, found {actual_type}"
+        )));
+    }
+
+    while tlv_data.len() >= 4 {
+        let extension_type = u16::from_le_bytes([tlv_data[0], tlv_data[1]]);
+        let length = usize::from(u16::from_le_bytes([tlv_data[2], tlv_data[3]]));
+        let value = tlv_data.get(4..4 + length).ok_or_else(|| {
+            TransactionError::parameters(format!("Extension {extension_type} is truncated"))
+        })?;
+        // Uninitialized space after the last extension
+        if extension_type == 0 {
+            break;
+        }
+        extensions.push((extension_type, value));
+        tlv_data = &tlv_data[4 + length..];
+    }
+    Ok(extensions)
+}
+
+fn find_extension<'a>(extensions: &[(u16, &'a [u8])], extension_type: u16) -> Option<&'a [u8]> {
+    extensions
+        .iter()
+        .find(|(actual_type, _)| *actual_type == extension_type)
+        .map(|(_, value)| *value)
+}
+
+/// Fee withheld on a transfer of `amount`, according to the fee of the transfer fee
+/// `config` in effect at `epoch`
+fn transfer_fee(config: &[u8], epoch: u64, amount: u64) -> Result<u64, TransactionError> {
+    // The authorities and the withheld amount precede the older and the newer fee
+    let fee = |offset: usize| -> Option<(u64, u64, u16)> {
+        let fee = config.get(offset..offset + 18)?;
+        Some((
+            u64::from_le_bytes(fee[0..8].try_into().ok()?),
+            u64::from_le_bytes(fee[8..16].try_into().ok()?),
+            u16::from_le_bytes(fee[16..18].try_into().ok()?),
+        ))
+    };
+    let (older_fee, newer_fee) = fee(72)
+        .zip(fee(90))
+        .ok_or_else(|| TransactionError::parameters("The transfer fee config is truncated"))?;
+    let (_, maximum_fee, basis_points) = if epoch >= newer_fee.0 {
+        newer_fee
+    } else {
+        older_fee
+    };
+
+    let fee = (u128::from(amount) * u128::from(basis_points)).div_ceil(MAX_BASIS_POINTS);
+    Ok(u64::try_from(fee).unwrap_or(u64::MAX).min(maximum_fee))
+}
+
+/// Appends the extra accounts of the transfer hook `program_id` to the transfer
+/// `instruction`, followed by the program and its validation account
+fn append_transfer_hook_accounts(
+    instruction: &mut Instruction,
+    amount: u64,
+    program_id: &Pubkey,
+    metas_data: &[u8],
+) -> Result<(), TransactionError> {
+    let invalid =
+        |reason: &str| TransactionError::parameters(format!("Extra account metas: {reason}"));
+    let [source, mint, destination, authority, ..] = instruction.accounts.as_slice() else {
+        return Err(invalid("the transfer lacks accounts"));
+    };
+    let validation_account = extra_account_metas_address(&mint.pubkey, program_id);
+
+    // The hook resolves its accounts against the accounts and data of `Execute`
+    let mut execute_data = EXECUTE_DISCRIMINATOR.to_vec();
+    execute_data.extend_from_slice(&amount.to_le_bytes());
+    let mut execute_accounts = vec![
+        source.clone(),
+        mint.clone(),
+        destination.clone(),
+        authority.clone(),
+        AccountMeta::new_readonly(validation_account, false),
+    ];
+
+    for meta in extra_account_metas(metas_data)? {
+        let (discriminator, config) = meta.split_first().ok_or_else(|| invalid("empty meta"))?;
+        let address_config: &[u8; 32] =
+            config[..32].try_into().map_err(|_| invalid("short meta"))?;
+        let (is_signer, is_writable) = (config[32] == 1, config[33] == 1);
+
+        let pubkey = match discriminator {
+            0 => Pubkey::new_from_array(*address_config),
+            1 => pda(address_config, program_id, &execute_data, &execute_accounts)?,
+            128.. => {
+                let program = execute_accounts
+                    .get(usize::from(discriminator - 128))
+                    .ok_or_else(|| invalid("unknown program account"))?
+                    .pubkey;
+                pda(address_config, &program, &execute_data, &execute_accounts)?
+            }
+            _ => return Err(invalid("unsupported meta")),
+        };
+        execute_accounts.push(AccountMeta {
+            pubkey,
+            is_signer,
+            is_writable,
+        });
+    }
+
+    instruction
+        .accounts
+        .extend_from_slice(&execute_accounts[5..]);
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*program_id, false));
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(validation_account, false));
+    Ok(())
+}
+
+/// Extra account metas of the `Execute` instruction stored in a validation account
+fn extra_account_metas(data: &[u8]) -> Result<Vec<&[u8]>, TransactionError> {
+    let invalid = || TransactionError::parameters("Extra account metas: invalid account data");
+    let mut data = data;
+    while data.len() >= 12 {
+        let length = u32::from_le_bytes(data[8..12].try_into().map_err(|_| invalid())?);
+        let value = data.get(12..12 + length as usize).ok_or_else(invalid)?;
+        if data[..8] == EXECUTE_DISCRIMINATOR {
+            let count = u32::from_le_bytes(
+                value
+                    .get(..4)
+                    .ok_or_else(invalid)?
+                    .try_into()
+                    .map_err(|_| invalid())?,
+            );
+            return value[4..]
+                .chunks_exact(EXTRA_ACCOUNT_META_LENGTH)
+                .take(count as usize)
+                .map(Ok)
+                .collect();
+        }
+        data = &data[12 + length as usize..];
+    }
+    Err(invalid())
+}
+
+/// Derives the address of `program_id` from the seeds packed in `address_config`
+fn pda(
+    address_config: &[u8; 32],
+    program_id: &Pubkey,
+    instruction_data: &[u8],
+    accounts: &[AccountMeta],
+) -> Result<Pubkey, TransactionError> {
+    let invalid =
+        |reason: &str| TransactionError::parameters(format!("Extra account metas: {reason}"));
+    let mut seeds: Vec<Vec<u8>> = Vec::new();
+    let mut config = address_config.as_slice();
+
+    while let Some((&seed_type, rest)) = config.split_first() {
+        match (seed_type, rest) {
+            // The remaining space is unused
+            (0, _) => break,
+            (1, [length, rest @ ..]) => {
+                let literal = rest
+                    .get(..usize::from(*length))
+                    .ok_or_else(|| invalid("short literal"))?;
+                seeds.push(literal.to_vec());
+                config = &rest[usize::from(*length)..];
+            }
+            (2, [index, length, rest @ ..]) => {
+                let start = usize::from(*index);
+                let data = instruction_data
+                    .get(start..start + usize::from(*length))
+                    .ok_or_else(|| invalid("seed out of the instruction data"))?;
+                seeds.push(data.to_vec());
+                config = rest;
+            }
+            (3, [index, rest @ ..]) => {
+                let account = accounts
+                    .get(usize::from(*index))
+                    .ok_or_else(|| invalid("seed of an unknown account"))?;
+                seeds.push(account.pubkey.to_bytes().to_vec());
+                config = rest;
+            }
+            // Seeds from account data would need the accounts themselves
+            _ => return Err(invalid("unsupported seed")),
+        }
+    }
+
+    let seeds: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    Ok(Pubkey::find_program_address(&seeds, program_id).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::instruction_helpers::decode_instructions;
+    use crate::solana::types::SolanaInstruction;
+    use crate::solana::{transfer_2022, TOKEN_2022_PROGRAM};
+    use crate::utils::to_base64;
+    use solana_sdk::message::{Message, VersionedMessage};
+    use std::str::FromStr;
+
+    fn account_data(account_type: u8, extensions: &[(u16, Vec<u8>)]) -> String {
+        let mut data = vec![0; BASE_ACCOUNT_LENGTH];
+        data.push(account_type);
+        for (extension_type, value) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        to_base64(data)
+    }
+
+    #[test]
+    fn test_apply_transfer_extensions() {
+        let token_program = Pubkey::from_str(TOKEN_2022_PROGRAM).unwrap();
+        let (source, destination, owner, mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let hook_program = Pubkey::new_unique();
+        let fixed_account = Pubkey::new_unique();
+
+        // 1% capped at 5_000 until epoch 100, 0.5% capped at 2_000 after
+        let mut fee_config = vec![0; 72];
+        for (epoch, maximum_fee, basis_points) in [(0u64, 5_000u64, 100u16), (100, 2_000, 50)] {
+            fee_config.extend_from_slice(&epoch.to_le_bytes());
+            fee_config.extend_from_slice(&maximum_fee.to_le_bytes());
+            fee_config.extend_from_slice(&basis_points.to_le_bytes());
+        }
+        let mut hook = vec![0; 32];
+        hook.extend_from_slice(hook_program.as_ref());
+
+        // A fixed account and a PDA of the hook seeded with "counter" and the owner
+        let mut metas = Vec::new();
+        let mut push_meta = |discriminator: u8, config: &[u8], is_writable: bool| {
+            metas.push(discriminator);
+            let mut address_config = [0; 32];
+            address_config[..config.len()].copy_from_slice(config);
+            metas.extend_from_slice(&address_config);
+            metas.extend_from_slice(&[0, u8::from(is_writable)]);
+        };
+        push_meta(0, fixed_account.as_ref(), false);
+        push_meta(
+            1,
+            &[1, 7, b'c', b'o', b'u', b'n', b't', b'e', b'r', 3, 3],
+            true,
+        );
+        let mut metas_data = EXECUTE_DISCRIMINATOR.to_vec();
+        metas_data.extend_from_slice(&(4 + metas.len() as u32).to_le_bytes());
+        metas_data.extend_from_slice(&2u32.to_le_bytes());
+        metas_data.extend_from_slice(&metas);
+
+        let mut accounts = TokenExtensionAccounts {
+            mint_data: account_data(
+                MINT_ACCOUNT_TYPE,
+                &[
+                    (TRANSFER_FEE_CONFIG_EXTENSION, fee_config),
+                    (TRANSFER_HOOK_EXTENSION, hook),
+                ],
+            ),
+            destination_data: Some(account_data(
+                TOKEN_ACCOUNT_TYPE,
+                &[(MEMO_TRANSFER_EXTENSION, vec![1])],
+            )),
+            extra_account_metas_data: Some(to_base64(metas_data)),
+            epoch: 10,
+        };
+        let transfer = transfer_2022(
+            &token_program,
+            &source,
+            &destination,
+            &owner,
+            &[],
+            &[],
+            1_000_000,
+            6,
+            &mint,
+        )
+        .unwrap();
+
+        let mut instruction = transfer.clone();
+        let memo_required =
+            apply_transfer_extensions(&mut instruction, 1_000_000, 6, &accounts).unwrap();
+        assert!(memo_required);
+        assert_eq!(instruction.data[..2], TRANSFER_CHECKED_WITH_FEE);
+        assert_eq!(instruction.data[10], 6);
+        assert_eq!(instruction.data[11..], 5_000u64.to_le_bytes());
+        let counter = Pubkey::find_program_address(&[b"counter", owner.as_ref()], &hook_program).0;
+        let appended: Vec<_> = instruction.accounts[4..]
+            .iter()
+            .map(|account| (account.pubkey, account.is_writable))
+            .collect();
+        assert_eq!(
+            appended,
+            vec![
+                (fixed_account, false),
+                (counter, true),
+                (hook_program, false),
+                (extra_account_metas_address(&mint, &hook_program), false),
+            ]
+        );
+
+        // The newer fee is in effect
+        accounts.epoch = 100;
+        accounts.destination_data = None;
+        let mut instruction = transfer;
+        let memo_required =
+            apply_transfer_extensions(&mut instruction, 1_000_000, 6, &accounts).unwrap();
+        assert!(!memo_required);
+        assert_eq!(instruction.data[11..], 2_000u64.to_le_bytes());
+
+        let message = VersionedMessage::Legacy(Message::new(&[instruction], Some(&owner)));
+        let SolanaInstruction::TokenTransferCheckedWithFee {
+            amount,
+            decimals,
+            fee,
+            ..
+        } = &decode_instructions(&message, message.static_account_keys())[0]
+        else {
+            panic!("Expected the transfer with fee to be decoded");
+        };
+        assert_eq!((*amount, *decimals, *fee), (1_000_000, 6, 2_000));
+    }
+}
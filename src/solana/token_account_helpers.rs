@@ -0,0 +1,229 @@
+//! Management of token accounts of the SPL token and Token-2022 programs
+use std::str::FromStr;
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use spl_token::instruction::AuthorityType;
+
+use super::types::{TokenAccountAction, TokenAuthorityType};
+use super::{
+    approve_delegate, burn_token, freeze_token_account, revoke_delegate, set_token_authority,
+    sync_native_token_account,
+};
+use crate::errors::*;
+
+/// Returns the instructions of `actions` and the account paying for them, which is
+/// the authority of the first action
+pub(crate) fn token_account_instructions(
+    actions: &[TokenAccountAction],
+) -> Result<(Vec<Instruction>, Pubkey), TransactionError> {
+    let mut instructions = Vec::with_capacity(actions.len());
+    let mut payer = None;
+    for action in actions {
+        let (instruction, authority) = token_account_instruction(action)?;
+        instructions.push(instruction);
+        payer.get_or_insert(authority);
+    }
+    let payer = payer.ok_or_else(|| TransactionError::parameters("No actions were provided"))?;
+    Ok((instructions, payer))
+}
+
+/// Returns the instruction of `action` and its authority
+fn token_account_instruction(
+    action: &TokenAccountAction,
+) -> Result<(Instruction, Pubkey), TransactionError> {
+    let token_program = |program: &Option<String>| match program {
+        Some(program) => Pubkey::from_str(program).map_err(TransactionError::public_key),
+        None => Ok(spl_token::id()),
+    };
+
+    match action {
+        TokenAccountAction::Approve {
+            account,
+            delegate,
+            owner,
+            amount,
+            token_program: program,
+        } => {
+            let owner = owner.to_solana_pubkey()?;
+            let instruction = approve_delegate(
+                &token_program(program)?,
+                &account.to_solana_pubkey()?,
+                &delegate.to_solana_pubkey()?,
+                &owner,
+                &[],
+                *amount,
+            )?;
+            Ok((instruction, owner))
+        }
+        TokenAccountAction::Revoke {
+            account,
+            owner,
+            token_program: program,
+        } => {
+            let owner = owner.to_solana_pubkey()?;
+            let instruction = revoke_delegate(
+                &token_program(program)?,
+                &account.to_solana_pubkey()?,
+                &owner,
+                &[],
+            )?;
+            Ok((instruction, owner))
+        }
+        TokenAccountAction::Burn {
+            account,
+            mint,
+            owner,
+            amount,
+            decimals,
+            token_program: program,
+        } => {
+            let owner = owner.to_solana_pubkey()?;
+            let instruction = burn_token(
+                &token_program(program)?,
+                &account.to_solana_pubkey()?,
+                &mint.to_solana_pubkey()?,
+                &owner,
+                &[],
+                *amount,
+                *decimals,
+            )?;
+            Ok((instruction, owner))
+        }
+        TokenAccountAction::Freeze {
+            account,
+            mint,
+            freeze_authority,
+            token_program: program,
+        }
+        | TokenAccountAction::Thaw {
+            account,
+            mint,
+            freeze_authority,
+            token_program: program,
+        } => {
+            let freeze_authority = freeze_authority.to_solana_pubkey()?;
+            let instruction = freeze_token_account(
+                &token_program(program)?,
+                &account.to_solana_pubkey()?,
+                &mint.to_solana_pubkey()?,
+                &freeze_authority,
+                &[],
+                matches!(action, TokenAccountAction::Freeze { .. }),
+            )?;
+            Ok((instruction, freeze_authority))
+        }
+        TokenAccountAction::SetAuthority {
+            account,
+            current_authority,
+            authority_type,
+            new_authority,
+            token_program: program,
+        } => {
+            let current_authority = current_authority.to_solana_pubkey()?;
+            let new_authority = new_authority
+                .as_ref()
+                .map(|new_authority| new_authority.to_solana_pubkey())
+                .transpose()?;
+            let authority_type = match authority_type {
+                TokenAuthorityType::MintTokens => AuthorityType::MintTokens,
+                TokenAuthorityType::FreezeAccount => AuthorityType::FreezeAccount,
+                TokenAuthorityType::AccountOwner => AuthorityType::AccountOwner,
+                TokenAuthorityType::CloseAccount => AuthorityType::CloseAccount,
+            };
+            let instruction = set_token_authority(
+                &token_program(program)?,
+                &account.to_solana_pubkey()?,
+                new_authority.as_ref(),
+                authority_type,
+                &current_authority,
+                &[],
+            )?;
+            Ok((instruction, current_authority))
+        }
+        TokenAccountAction::SyncNative {
+            account,
+            payer,
+            token_program: program,
+        } => {
+            let instruction =
+                sync_native_token_account(&token_program(program)?, &account.to_solana_pubkey()?)?;
+            Ok((instruction, payer.to_solana_pubkey()?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solana::TOKEN_2022_PROGRAM;
+    use crate::types::{Blockchain, ChainPublicKey};
+    use spl_token::instruction::TokenInstruction;
+
+    #[test]
+    fn test_token_account_instructions() {
+        let owner = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let key = |key: &Pubkey| ChainPublicKey::new(key.to_string(), Blockchain::Solana);
+
+        let (instructions, payer) = token_account_instructions(&[
+            TokenAccountAction::Revoke {
+                account: key(&account),
+                owner: key(&owner),
+                token_program: Some(TOKEN_2022_PROGRAM.to_string()),
+            },
+            TokenAccountAction::Burn {
+                account: key(&account),
+                mint: key(&mint),
+                owner: key(&owner),
+                amount: 500,
+                decimals: Some(6),
+                token_program: None,
+            },
+            TokenAccountAction::SetAuthority {
+                account: key(&account),
+                current_authority: key(&owner),
+                authority_type: TokenAuthorityType::CloseAccount,
+                new_authority: None,
+                token_program: None,
+            },
+        ])
+        .unwrap();
+        assert_eq!(payer, owner);
+        assert_eq!(instructions[0].program_id.to_string(), TOKEN_2022_PROGRAM);
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[0].data).unwrap(),
+            TokenInstruction::Revoke
+        );
+        assert!(instructions[0].accounts[1].is_signer);
+        assert_eq!(instructions[1].program_id, spl_token::id());
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[1].data).unwrap(),
+            TokenInstruction::BurnChecked {
+                amount: 500,
+                decimals: 6
+            }
+        );
+        assert!(instructions[1].accounts[1].is_writable);
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[2].data).unwrap(),
+            TokenInstruction::SetAuthority {
+                authority_type: AuthorityType::CloseAccount,
+                new_authority: None.into(),
+            }
+        );
+
+        let Err(TransactionError::InstructionError(_)) =
+            token_account_instructions(&[TokenAccountAction::SyncNative {
+                account: key(&account),
+                payer: key(&owner),
+                token_program: Some(Pubkey::new_unique().to_string()),
+            }])
+        else {
+            panic!("Only the token programs should be allowed");
+        };
+        let Err(TransactionError::Parameters(_)) = token_account_instructions(&[]) else {
+            panic!("An empty list of actions should fail");
+        };
+    }
+}
@@ -1,6 +1,744 @@
 use serde::{Deserialize, Serialize};
 
+use crate::types::{ChainPublicKey, DecimalNumber};
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExternalAddress {
     pub recent_blockhash: String,
 }
+
+/// A compiled instruction decoded into the action it performs.
+/// Addresses are base58 encoded, amounts are in the smallest unit of the asset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SolanaInstruction {
+    SystemTransfer {
+        from: String,
+        to: String,
+        lamports: u64,
+    },
+    SystemCreateAccount {
+        from: String,
+        new_account: String,
+        lamports: u64,
+        space: u64,
+        owner: String,
+    },
+    SystemAssign {
+        account: String,
+        owner: String,
+    },
+    SystemAdvanceNonceAccount {
+        nonce_account: String,
+        authority: String,
+    },
+    /// Unchecked transfer of the SPL Token or Token-2022 program
+    TokenTransfer {
+        program: String,
+        source: String,
+        destination: String,
+        authority: String,
+        amount: u64,
+    },
+    TokenTransferChecked {
+        program: String,
+        source: String,
+        mint: String,
+        destination: String,
+        authority: String,
+        amount: u64,
+        decimals: u8,
+    },
+    /// `TransferCheckedWithFee` of the Token-2022 transfer fee extension, `fee` being
+    /// withheld from the amount the destination receives
+    TokenTransferCheckedWithFee {
+        program: String,
+        source: String,
+        mint: String,
+        destination: String,
+        authority: String,
+        amount: u64,
+        decimals: u8,
+        fee: u64,
+    },
+    TokenCloseAccount {
+        program: String,
+        account: String,
+        destination: String,
+        owner: String,
+    },
+    /// `Approve` and `ApproveChecked`
+    TokenApprove {
+        program: String,
+        source: String,
+        delegate: String,
+        owner: String,
+        amount: u64,
+    },
+    TokenSetAuthority {
+        program: String,
+        account: String,
+        current_authority: String,
+        authority_type: String,
+        new_authority: Option<String>,
+    },
+    AssociatedTokenAccountCreate {
+        payer: String,
+        account: String,
+        wallet: String,
+        mint: String,
+        token_program: String,
+        idempotent: bool,
+    },
+    Memo {
+        memo: String,
+    },
+    ComputeUnitLimit {
+        units: u32,
+    },
+    ComputeUnitPrice {
+        micro_lamports: u64,
+    },
+    ComputeLoadedAccountsDataSizeLimit {
+        bytes: u32,
+    },
+    /// One of the Jupiter v6 route instructions. Exact in routes carry `in_amount`
+    /// and `quoted_out_amount`, exact out routes `out_amount` and `quoted_in_amount`.
+    /// Token ledger routes take the input amount from the ledger, so it is `None`.
+    /// `route_plan` is `None` when it goes through an AMM we can't decode.
+    JupiterRoute {
+        name: String,
+        route_plan: Option<Vec<JupiterRoutePlanStep>>,
+        in_amount: Option<u64>,
+        quoted_in_amount: Option<u64>,
+        out_amount: Option<u64>,
+        quoted_out_amount: Option<u64>,
+        slippage_bps: u16,
+        platform_fee_bps: u8,
+    },
+    /// A limit order of the Jupiter limit order program, selling `making_amount` of
+    /// the input for `taking_amount` of the output. `expired_at` is a unix timestamp.
+    JupiterLimitOrderCreate {
+        maker: String,
+        order: String,
+        input_mint: String,
+        output_mint: String,
+        making_amount: u64,
+        taking_amount: u64,
+        expired_at: Option<i64>,
+    },
+    /// Cancels a limit order and returns what's left of the input to the maker
+    JupiterLimitOrderCancel {
+        maker: String,
+        order: String,
+    },
+    /// Opens a DCA of the Jupiter DCA program, swapping `in_amount_per_cycle` of the
+    /// input every `cycle_frequency` seconds until `in_amount` is used up
+    JupiterDcaOpen {
+        user: String,
+        dca: String,
+        input_mint: String,
+        output_mint: String,
+        in_amount: u64,
+        in_amount_per_cycle: u64,
+        cycle_frequency: i64,
+        min_out_amount: Option<u64>,
+        max_out_amount: Option<u64>,
+        start_at: Option<i64>,
+    },
+    /// Closes a DCA and returns its remaining input and output to the user
+    JupiterDcaClose {
+        user: String,
+        dca: String,
+    },
+    /// Anything we don't know how to decode. `data` is base64 encoded.
+    Unknown {
+        program: String,
+        data: String,
+    },
+}
+
+/// A leg of a Jupiter route. `percent` of the amount at `input_index` is swapped
+/// through the AMM `swap` into the amount at `output_index`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterRoutePlanStep {
+    pub swap: String,
+    pub percent: u8,
+    pub input_index: u8,
+    pub output_index: u8,
+}
+
+/// Something in a transaction the user should be aware of before signing it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionWarning {
+    /// The authority of one of the user's token accounts or mints changes
+    SetAuthority {
+        account: String,
+        authority_type: String,
+        new_authority: Option<String>,
+    },
+    /// A delegate that is not trusted may spend the user's tokens
+    Approve {
+        source: String,
+        delegate: String,
+        amount: u64,
+    },
+    /// One of the user's token accounts is closed and the rent goes to another wallet
+    CloseAccountToForeignWallet {
+        account: String,
+        destination: String,
+    },
+    /// The user's wallet is assigned to another program
+    AssignOwner {
+        account: String,
+        new_owner: String,
+    },
+    /// The transaction uses a durable nonce and stays valid until the nonce advances
+    DurableNonce {
+        nonce_account: String,
+    },
+    /// The transaction calls a program outside of the allowlist
+    UnknownProgram {
+        program: String,
+    },
+}
+
+/// State of a token account a transaction depends on, as fetched by the caller.
+/// `lamports` are only needed for accounts that get closed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenAccountSnapshot {
+    pub address: String,
+    pub owner: String,
+    pub mint: String,
+    pub lamports: Option<u64>,
+}
+
+/// Expected change of the balance of `owner`. `mint` is `None` for SOL,
+/// `amount` is in the smallest unit and negative for outgoing funds.
+/// `is_estimate` is set for amounts that depend on slippage, in which case
+/// `amount` is the worst case: at least this much is received, at most this much is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub owner: String,
+    pub mint: Option<String>,
+    pub amount: i64,
+    pub is_estimate: bool,
+}
+
+/// Raw account data of an address lookup table, as returned by `getAccountInfo`
+/// with base64 encoding. Needed to resolve the accounts a V0 transaction loads.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AddressLookupTableData {
+    pub address: String,
+    pub data: String,
+}
+
+/// An account of a transaction with the way it is accessed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionAccount {
+    pub address: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// A durable nonce used instead of a recent blockhash, so that the transaction
+/// doesn't expire. `nonce` is the value currently stored in `nonce_account`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DurableNonce {
+    pub nonce_account: String,
+    pub nonce_authority: String,
+    pub nonce: String,
+}
+
+/// State of an initialized nonce account
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceAccount {
+    pub authority: String,
+    pub nonce: String,
+    pub lamports_per_signature: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum NonceAccountAction {
+    /// Creates a rent exempt nonce account and initializes it.
+    /// The `nonce_account` has to sign the transaction as well.
+    Create {
+        payer: ChainPublicKey,
+        nonce_account: ChainPublicKey,
+        nonce_authority: ChainPublicKey,
+    },
+    /// Initializes an already allocated nonce account
+    Initialize {
+        payer: ChainPublicKey,
+        nonce_account: ChainPublicKey,
+        nonce_authority: ChainPublicKey,
+    },
+    Authorize {
+        nonce_account: ChainPublicKey,
+        nonce_authority: ChainPublicKey,
+        new_authority: ChainPublicKey,
+    },
+    Advance {
+        nonce_account: ChainPublicKey,
+        nonce_authority: ChainPublicKey,
+    },
+    /// Withdraws `amount` SOL from the nonce account
+    Withdraw {
+        nonce_account: ChainPublicKey,
+        nonce_authority: ChainPublicKey,
+        recipient: ChainPublicKey,
+        amount: DecimalNumber,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum StakeAccountAction {
+    /// Creates a stake account holding `amount` SOL and initializes it. Without a
+    /// `seed` the `stake_account` is a fresh keypair that has to sign the transaction,
+    /// with a `seed` it is the address derived from `funder` and `seed`.
+    Create {
+        funder: ChainPublicKey,
+        stake_account: ChainPublicKey,
+        seed: Option<String>,
+        staker: ChainPublicKey,
+        withdrawer: ChainPublicKey,
+        amount: DecimalNumber,
+    },
+    Delegate {
+        stake_account: ChainPublicKey,
+        staker: ChainPublicKey,
+        vote_account: ChainPublicKey,
+    },
+    Deactivate {
+        stake_account: ChainPublicKey,
+        staker: ChainPublicKey,
+    },
+    /// Withdraws `amount` SOL from an inactive stake account
+    Withdraw {
+        stake_account: ChainPublicKey,
+        withdrawer: ChainPublicKey,
+        recipient: ChainPublicKey,
+        amount: DecimalNumber,
+    },
+    /// Moves `amount` SOL into the fresh `split_stake_account`, which has to sign the
+    /// transaction. The staker funds the rent exemption of the new account.
+    Split {
+        stake_account: ChainPublicKey,
+        staker: ChainPublicKey,
+        split_stake_account: ChainPublicKey,
+        amount: DecimalNumber,
+    },
+    /// Merges `source_stake_account` into `stake_account`
+    Merge {
+        stake_account: ChainPublicKey,
+        staker: ChainPublicKey,
+        source_stake_account: ChainPublicKey,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StakeAccountState {
+    Uninitialized,
+    Initialized,
+    Delegated,
+    RewardsPool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeLockup {
+    pub unix_timestamp: i64,
+    pub epoch: u64,
+    pub custodian: String,
+}
+
+/// Decoded stake account. Authorities are only set once the account is initialized,
+/// the delegation only once it is delegated. `lockup` is `None` without a lockup.
+/// Epochs are `None` while the stake hasn't been activated or deactivated.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeAccount {
+    pub state: StakeAccountState,
+    pub staker: Option<String>,
+    pub withdrawer: Option<String>,
+    pub rent_exempt_reserve: Option<u64>,
+    pub lockup: Option<StakeLockup>,
+    pub voter: Option<String>,
+    pub delegated_stake: Option<u64>,
+    pub activation_epoch: Option<u64>,
+    pub deactivation_epoch: Option<u64>,
+}
+
+/// Account of an SPL stake pool. `program` defaults to the SPL stake pool program,
+/// forks like Sanctum's use their own. `data` is the base64 encoded account data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StakePoolAccount {
+    pub address: String,
+    pub program: Option<String>,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakePoolFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+/// Decoded state of a stake pool, including the derived `withdraw_authority`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakePoolState {
+    pub manager: String,
+    pub staker: String,
+    pub stake_deposit_authority: String,
+    pub withdraw_authority: String,
+    pub validator_list: String,
+    pub reserve_stake: String,
+    pub pool_mint: String,
+    pub manager_fee_account: String,
+    pub token_program: String,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+    pub sol_deposit_authority: Option<String>,
+    pub sol_withdraw_authority: Option<String>,
+    pub sol_deposit_fee: StakePoolFee,
+    pub sol_withdrawal_fee: StakePoolFee,
+    pub stake_deposit_fee: StakePoolFee,
+    pub stake_withdrawal_fee: StakePoolFee,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum StakePoolAction {
+    /// Deposits `amount` SOL into the reserve and mints pool tokens to the
+    /// associated token account of `owner`, which is created if needed
+    DepositSol {
+        owner: ChainPublicKey,
+        amount: DecimalNumber,
+    },
+    /// Burns `pool_tokens` (in the smallest unit) and withdraws SOL from the reserve
+    WithdrawSol {
+        owner: ChainPublicKey,
+        pool_tokens: DecimalNumber,
+    },
+    /// Deposits an active `stake_account` of `owner` delegated to a validator of the pool
+    DepositStake {
+        owner: ChainPublicKey,
+        stake_account: ChainPublicKey,
+        validator_vote_account: ChainPublicKey,
+        validator_seed: Option<u32>,
+    },
+    /// Burns `pool_tokens` and splits them off a validator stake account into the fresh
+    /// `new_stake_account`, which has to sign. Without a validator the stake comes from the reserve.
+    WithdrawStake {
+        owner: ChainPublicKey,
+        pool_tokens: DecimalNumber,
+        new_stake_account: ChainPublicKey,
+        validator_vote_account: Option<ChainPublicKey>,
+        validator_seed: Option<u32>,
+    },
+}
+
+/// Serialized size of a signed transaction and the number of accounts it locks,
+/// next to the limits a transaction has to stay within
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionSize {
+    pub size: u64,
+    pub account_count: u64,
+    pub max_size: u64,
+    pub max_account_count: u64,
+}
+
+/// A compute budget instruction that was added to a transaction, or that replaced
+/// the `previous` one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeBudgetChange {
+    pub previous: Option<SolanaInstruction>,
+    pub current: SolanaInstruction,
+}
+
+/// Fee of a transaction in lamports. The priority fee is `compute_unit_price`
+/// micro-lamports for each unit of the `compute_unit_limit`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionFee {
+    pub signatures: u64,
+    pub base_fee: u64,
+    pub compute_unit_limit: u32,
+    pub compute_unit_price: u64,
+    pub priority_fee: u64,
+    pub total_fee: u64,
+}
+
+/// Tip for Jito validators, paid by the fee payer. Without a `tip_account` one of
+/// the known Jito tip accounts is used.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JitoTip {
+    pub lamports: u64,
+    pub tip_account: Option<String>,
+}
+
+/// Base64 data of the accounts a Token-2022 transfer depends on, to apply the transfer
+/// fee and transfer hook of the mint and the required memo of the destination
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenExtensionAccounts {
+    pub mint_data: String,
+    /// Missing when the transfer creates the destination account
+    pub destination_data: Option<String>,
+    /// Extra account metas of the mint's transfer hook program, if it has one
+    pub extra_account_metas_data: Option<String>,
+    /// Current epoch, which selects the transfer fee in effect
+    pub epoch: u64,
+}
+
+/// Part of a Jupiter swap transaction that was compared with its quote
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JupiterSwapField {
+    SwapMode,
+    InputMint,
+    OutputMint,
+    Amount,
+    QuotedAmount,
+    SlippageBps,
+    PlatformFeeBps,
+    UserTransferAuthority,
+    SourceTokenAccount,
+    DestinationTokenAccount,
+    FeeAccount,
+    RoutePlan,
+}
+
+/// A value of the swap transaction that differs from the quote. `actual` is `None`
+/// when the transaction doesn't carry the value, like the input amount of token ledger routes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterSwapMismatch {
+    pub field: JupiterSwapField,
+    pub expected: String,
+    pub actual: Option<String>,
+}
+
+/// A step of the route plan of a Jupiter quote, as the route instruction needs it.
+/// The quote doesn't carry these, they come from the AMM the step swaps through.
+/// `swap` is the base64 encoded `Swap` of the Jupiter v6 IDL and `accounts` are the
+/// accounts the AMM needs, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterSwapLeg {
+    pub swap: String,
+    pub accounts: Vec<TransactionAccount>,
+}
+
+/// Options of a Jupiter swap built from a quote. Token programs default to the SPL
+/// token program. Without a `destination_token_account` the output goes to the
+/// associated token account of the user, which is created if needed. The platform
+/// fee goes to `fee_account`, or to the token account of the Jupiter `referral_account`
+/// for the mint the fee is charged in. Routes through several hops pass the tokens
+/// in between through associated token accounts of the user, which are created under
+/// Token-2022 for the mints in `intermediate_token_2022_mints`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterSwapOptions {
+    pub wrap_and_unwrap_sol: bool,
+    pub destination_token_account: Option<String>,
+    pub fee_account: Option<String>,
+    pub referral_account: Option<String>,
+    pub input_token_program: Option<String>,
+    pub output_token_program: Option<String>,
+    pub intermediate_token_2022_mints: Vec<String>,
+}
+
+/// A leg of a Jupiter swap as it was executed, from the `SwapEvent` Jupiter emitted
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterSwapEvent {
+    pub amm: String,
+    pub input_mint: String,
+    pub input_amount: u64,
+    pub output_mint: String,
+    pub output_amount: u64,
+}
+
+/// What a Jupiter swap actually spent and received, summed up over its `legs`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JupiterSwapFill {
+    pub legs: Vec<JupiterSwapEvent>,
+    pub input_mint: String,
+    pub input_amount: u64,
+    pub output_mint: String,
+    pub output_amount: u64,
+}
+
+/// An action on a Jupiter limit order or DCA. Amounts are in the smallest unit of
+/// the token, token programs default to the SPL token program.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JupiterOrderAction {
+    /// Opens a limit order selling `making_amount` of `input_mint` for `taking_amount`
+    /// of `output_mint`. `unique_id` tells the orders of a maker apart.
+    CreateLimitOrder {
+        maker: ChainPublicKey,
+        input_mint: ChainPublicKey,
+        output_mint: ChainPublicKey,
+        making_amount: DecimalNumber,
+        taking_amount: DecimalNumber,
+        expired_at: Option<i64>,
+        unique_id: u64,
+        input_token_program: Option<String>,
+        output_token_program: Option<String>,
+    },
+    CancelLimitOrder {
+        maker: ChainPublicKey,
+        order: ChainPublicKey,
+        input_mint: ChainPublicKey,
+        input_token_program: Option<String>,
+    },
+    /// Opens a DCA swapping `in_amount_per_cycle` every `cycle_frequency` seconds,
+    /// within the optional bounds on the output of a cycle. The DCA program is
+    /// passed the token program of the input mint.
+    OpenDca {
+        user: ChainPublicKey,
+        input_mint: ChainPublicKey,
+        output_mint: ChainPublicKey,
+        in_amount: DecimalNumber,
+        in_amount_per_cycle: DecimalNumber,
+        cycle_frequency: i64,
+        min_out_amount: Option<DecimalNumber>,
+        max_out_amount: Option<DecimalNumber>,
+        start_at: Option<i64>,
+        application_idx: u64,
+        input_token_program: Option<String>,
+        output_token_program: Option<String>,
+    },
+    CloseDca {
+        user: ChainPublicKey,
+        dca: ChainPublicKey,
+        input_mint: ChainPublicKey,
+        output_mint: ChainPublicKey,
+        input_token_program: Option<String>,
+        output_token_program: Option<String>,
+    },
+}
+
+/// Account of a Jupiter limit order or DCA. `data` is the base64 encoded account data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JupiterOrderAccount {
+    pub address: String,
+    pub data: String,
+}
+
+/// A decoded Jupiter limit order or DCA. Times are unix timestamps.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JupiterOrder {
+    LimitOrder {
+        address: String,
+        maker: String,
+        input_mint: String,
+        output_mint: String,
+        unique_id: u64,
+        ori_making_amount: u64,
+        ori_taking_amount: u64,
+        making_amount: u64,
+        taking_amount: u64,
+        expired_at: Option<i64>,
+        created_at: i64,
+    },
+    Dca {
+        address: String,
+        user: String,
+        input_mint: String,
+        output_mint: String,
+        idx: u64,
+        next_cycle_at: i64,
+        in_deposited: u64,
+        in_withdrawn: u64,
+        in_used: u64,
+        out_received: u64,
+        out_withdrawn: u64,
+        in_amount_per_cycle: u64,
+        cycle_frequency: i64,
+        min_out_amount: Option<u64>,
+        max_out_amount: Option<u64>,
+        created_at: i64,
+    },
+}
+
+/// An instruction of the Jupiter referral program. `project` defaults to Jupiter's
+/// project and `token_program` to the SPL token program.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JupiterReferralAction {
+    /// Creates the account collecting the platform fees in `mint` for `referral_account`
+    InitializeReferralTokenAccount {
+        payer: ChainPublicKey,
+        referral_account: ChainPublicKey,
+        mint: ChainPublicKey,
+        project: Option<String>,
+        token_program: Option<String>,
+    },
+    /// Pays the fees collected in `mint` out to the associated token account of `partner`,
+    /// minus the share of the `project_admin`
+    Claim {
+        payer: ChainPublicKey,
+        referral_account: ChainPublicKey,
+        partner: ChainPublicKey,
+        mint: ChainPublicKey,
+        project: Option<String>,
+        project_admin: ChainPublicKey,
+        token_program: Option<String>,
+    },
+}
+
+/// Authority of a mint or token account replaced by `TokenAccountAction::SetAuthority`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenAuthorityType {
+    MintTokens,
+    FreezeAccount,
+    AccountOwner,
+    CloseAccount,
+}
+
+/// An instruction managing a token account. `token_program` defaults to the SPL token
+/// program and amounts are in the smallest unit of the token.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TokenAccountAction {
+    /// Lets `delegate` transfer up to `amount` tokens of `account`
+    Approve {
+        account: ChainPublicKey,
+        delegate: ChainPublicKey,
+        owner: ChainPublicKey,
+        amount: u64,
+        token_program: Option<String>,
+    },
+    /// Removes the delegate of `account`
+    Revoke {
+        account: ChainPublicKey,
+        owner: ChainPublicKey,
+        token_program: Option<String>,
+    },
+    /// Burns `amount` tokens of `account`, checking the `decimals` of the mint if given
+    Burn {
+        account: ChainPublicKey,
+        mint: ChainPublicKey,
+        owner: ChainPublicKey,
+        amount: u64,
+        decimals: Option<u8>,
+        token_program: Option<String>,
+    },
+    Freeze {
+        account: ChainPublicKey,
+        mint: ChainPublicKey,
+        freeze_authority: ChainPublicKey,
+        token_program: Option<String>,
+    },
+    Thaw {
+        account: ChainPublicKey,
+        mint: ChainPublicKey,
+        freeze_authority: ChainPublicKey,
+        token_program: Option<String>,
+    },
+    /// Replaces the `authority_type` authority of the mint or token `account`, or
+    /// removes it without a `new_authority`
+    SetAuthority {
+        account: ChainPublicKey,
+        current_authority: ChainPublicKey,
+        authority_type: TokenAuthorityType,
+        new_authority: Option<ChainPublicKey>,
+        token_program: Option<String>,
+    },
+    /// Updates the token amount of the wrapped SOL `account` to its lamports
+    SyncNative {
+        account: ChainPublicKey,
+        payer: ChainPublicKey,
+        token_program: Option<String>,
+    },
+}
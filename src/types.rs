@@ -76,6 +76,15 @@ pub trait TransactionFactory {
         parameters: TransactionParameters,
     ) -> Result<String, TransactionError>;
 
+    /// Pays every recipient of `transfers` from `sender`, using as few unsigned
+    /// transactions as the packet size and compute budget allow
+    fn batch_transaction(
+        &self,
+        sender: ChainPublicKey,
+        transfers: Vec<BatchTransfer>,
+        parameters: TransactionParameters,
+    ) -> Result<Vec<String>, TransactionError>;
+
     fn sign_transaction(
         &self,
         transaction: String,
@@ -83,6 +92,30 @@ pub trait TransactionFactory {
         parameters: Option<TransactionParameters>,
     ) -> Result<ChainTransaction, TransactionError>;
 
+    /// Inspects a transaction that `owner` is asked to sign and returns what the
+    /// user should be warned about. Programs outside of `allowed_programs` are only
+    /// flagged when an allowlist is given. `address_lookup_tables` resolve the accounts
+    /// of versioned transactions.
+    fn analyze_transaction(
+        &self,
+        transaction: String,
+        owner: ChainPublicKey,
+        allowed_programs: Option<Vec<String>>,
+        trusted_delegates: Vec<String>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<TransactionWarning>, TransactionError>;
+
+    /// Estimates the SOL and token balance changes of every wallet involved in the
+    /// transaction without simulating it. `accounts` describe the token accounts
+    /// that already exist, so that transfers can be attributed to their owners.
+    /// `address_lookup_tables` resolve the accounts of versioned transactions.
+    fn estimate_balance_changes(
+        &self,
+        transaction: String,
+        accounts: Vec<TokenAccountSnapshot>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<BalanceChange>, TransactionError>;
+
     fn sign_message(
         &self,
         message: String,
@@ -102,8 +135,13 @@ pub trait TransactionFactory {
         parameters: TransactionParameters,
     ) -> Result<String, TransactionError>;
 
-    fn parse_transaction(&self, transaction: String)
-        -> Result<ChainTransaction, TransactionError>;
+    /// Parses a transaction. The accounts a V0 transaction loads are only resolved
+    /// when the data of the `address_lookup_tables` it uses is given.
+    fn parse_transaction(
+        &self,
+        transaction: String,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<ChainTransaction, TransactionError>;
 
     fn get_associated_token_address(
         &self,
@@ -121,6 +159,152 @@ pub trait TransactionFactory {
     fn get_message(&self, transaction: String) -> Result<String, TransactionError>;
 
     fn append_signature_to_transaction(&self, signer: String, signature: String, transaction: String) -> Result<String, TransactionError>;
+
+    /// Size of the transaction once it's signed and the number of accounts it locks
+    fn estimate_transaction_size(&self, transaction: String) -> Result<TransactionSize, TransactionError>;
+
+    /// Fee of the transaction, read from its signatures and compute budget instructions
+    fn calculate_transaction_fee(&self, transaction: String) -> Result<TransactionFee, TransactionError>;
+
+    /// Compute unit price that pays at most `priority_fee` lamports for `compute_unit_limit` units
+    fn compute_unit_price_for_priority_fee(
+        &self,
+        priority_fee: u64,
+        compute_unit_limit: u32,
+    ) -> Result<u64, TransactionError>;
+
+    /// Creates an unsigned transaction managing a durable nonce account
+    fn nonce_account_transaction(
+        &self,
+        action: NonceAccountAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Decodes the base64 encoded data of a nonce account
+    fn parse_nonce_account(&self, data: String) -> Result<NonceAccount, TransactionError>;
+
+    /// Creates an unsigned transaction managing a native stake account
+    fn stake_account_transaction(
+        &self,
+        action: StakeAccountAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Address of a stake account created by `base` with `seed`
+    fn get_stake_account_address_with_seed(
+        &self,
+        base: String,
+        seed: String,
+    ) -> Result<ChainPublicKey, TransactionError>;
+
+    /// Decodes the base64 encoded data of a stake account
+    fn parse_stake_account(&self, data: String) -> Result<StakeAccount, TransactionError>;
+
+    /// Creates an unsigned transaction depositing into or withdrawing from an SPL
+    /// stake pool, described by the account data of `stake_pool`
+    fn stake_pool_transaction(
+        &self,
+        stake_pool: StakePoolAccount,
+        action: StakePoolAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Decodes the account data of an SPL stake pool
+    fn parse_stake_pool(
+        &self,
+        stake_pool: StakePoolAccount,
+    ) -> Result<StakePoolState, TransactionError>;
+
+    /// Compares a Jupiter swap transaction with the JSON `quote` it was requested for
+    /// and returns every value that differs. The swap has to use the associated token
+    /// accounts of `user` and pay the platform fee into `fee_account`, if given.
+    fn validate_jupiter_swap(
+        &self,
+        transaction: String,
+        quote: String,
+        user: ChainPublicKey,
+        fee_account: Option<String>,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<Vec<JupiterSwapMismatch>, TransactionError>;
+
+    /// Creates an unsigned V0 transaction swapping what the JSON `quote` of the Jupiter
+    /// quote API quoted for `user`, with the AMM details of every step in `legs`
+    fn jupiter_swap_transaction(
+        &self,
+        quote: String,
+        user: ChainPublicKey,
+        legs: Vec<JupiterSwapLeg>,
+        options: JupiterSwapOptions,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Decodes the swap events Jupiter emitted while executing `transaction` from its
+    /// confirmed `meta` JSON, as returned by `getTransaction`
+    fn parse_jupiter_swap_events(
+        &self,
+        transaction: String,
+        meta: String,
+    ) -> Result<Option<JupiterSwapFill>, TransactionError>;
+
+    /// Creates an unsigned transaction creating or cancelling a Jupiter limit order,
+    /// or opening or closing a Jupiter DCA
+    fn jupiter_order_transaction(
+        &self,
+        action: JupiterOrderAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Decodes the account data of a Jupiter limit order or DCA
+    fn parse_jupiter_order(
+        &self,
+        account: JupiterOrderAccount,
+    ) -> Result<JupiterOrder, TransactionError>;
+
+    /// Returns the Jupiter referral account created under `project` with `name`,
+    /// Jupiter's own project if none is given
+    fn get_jupiter_referral_account(
+        &self,
+        project: Option<String>,
+        name: String,
+    ) -> Result<ChainPublicKey, TransactionError>;
+
+    /// Returns the account collecting the platform fees in `mint` of a Jupiter
+    /// referral account, which swaps charging their fee in `mint` pay into
+    fn get_jupiter_referral_token_account(
+        &self,
+        referral_account: String,
+        mint: String,
+    ) -> Result<ChainPublicKey, TransactionError>;
+
+    /// Creates an unsigned transaction initializing a referral token account or
+    /// claiming the fees it collected
+    fn jupiter_referral_transaction(
+        &self,
+        action: JupiterReferralAction,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
+
+    /// Adds the signature of `sponsor` to a transaction it pays the fee of. Fails when
+    /// the sponsor has any other role than fee payer and, if `allow_rent` is set, payer
+    /// of the rent of associated token accounts, or when the fee and rent exceed
+    /// `max_fee` lamports.
+    fn sign_sponsored_transaction(
+        &self,
+        transaction: String,
+        sponsor: ChainPrivateKey,
+        max_fee: u64,
+        allow_rent: bool,
+        address_lookup_tables: Vec<AddressLookupTableData>,
+    ) -> Result<ChainTransaction, TransactionError>;
+
+    /// Creates an unsigned transaction approving or revoking delegates, burning tokens,
+    /// freezing or thawing accounts, replacing authorities or syncing wrapped SOL.
+    /// The authority of the first action pays for it.
+    fn token_account_transaction(
+        &self,
+        actions: Vec<TokenAccountAction>,
+        parameters: TransactionParameters,
+    ) -> Result<String, TransactionError>;
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -243,6 +427,19 @@ pub enum TransactionParameters {
         swap_slippage_bps: Option<u16>,
         compute_budget_unit_price: Option<u64>,
         compute_budget_unit_limit: Option<u32>,
+        /// Caps the account data the transaction loads, which lowers its priority fee
+        loaded_accounts_data_size_limit: Option<u32>,
+        /// Lookup tables used by a V0 transaction, to resolve its accounts
+        address_lookup_tables: Vec<AddressLookupTableData>,
+        /// Used instead of `external_address` by the transaction builders
+        durable_nonce: Option<DurableNonce>,
+        /// Added to the transaction by `sign_transaction` and `modify_transaction`
+        jito_tip: Option<JitoTip>,
+        /// Pays the fee of built transactions instead of the sender, and the rent of the
+        /// token account `token_transaction` creates. It signs the transaction too.
+        fee_payer: Option<String>,
+        /// Applies the Token-2022 extensions of these accounts to `token_transaction`
+        token_extensions: Option<TokenExtensionAccounts>,
     },
 }
 
@@ -268,10 +465,27 @@ pub enum TransactionKind {
 pub enum TokenDestination {
     /// Token account transfer destination
     Account { transfer_destination: String },
-    /// In case the destination wallet doesn't have an account to transfer the token,
-    /// the transaction will create a new token account owned by a wallet public key
-    /// and then the desired token will be transfered to the new account
-    Wallet { public_key: ChainPublicKey },
+    /// Transfers to the associated token account of the wallet public key. Unless
+    /// `account_exists` is set, the transaction creates the account first when it's
+    /// missing, without failing when it's already there
+    Wallet {
+        public_key: ChainPublicKey,
+        account_exists: bool,
+    },
+}
+
+/// A single payout of a batch. Without a `mint` the `amount` is in SOL, otherwise it's
+/// in the smallest unit of the token. `owner_program` and `decimals` default to the
+/// ones of the transaction parameters. `account_exists` skips the creation of the
+/// recipient's token account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchTransfer {
+    pub recipient: ChainPublicKey,
+    pub amount: DecimalNumber,
+    pub mint: Option<ChainPublicKey>,
+    pub owner_program: Option<String>,
+    pub decimals: Option<u8>,
+    pub account_exists: bool,
 }
 
 /*
@@ -287,9 +501,15 @@ pub struct ChainTransaction {
     pub tx: String,
     pub signers: Vec<ChainPublicKey>,
     pub accounts: Vec<ChainPublicKey>,
+    pub account_metas: Vec<TransactionAccount>,
     pub full_signature: Option<String>,
     pub signatures: Option<Vec<String>>,
     pub instruction_programs: Vec<String>,
+    pub instructions: Vec<SolanaInstruction>,
+    /// Compute budget instructions `sign_transaction` added or replaced
+    pub compute_budget_changes: Vec<ComputeBudgetChange>,
+    /// Signers whose signature the transaction still lacks
+    pub missing_signers: Vec<ChainPublicKey>,
 }
 
 /// Structure representing a NaCl KeyPair with Base64-encoded keys.